@@ -41,6 +41,26 @@ void void_fn(){}
 void voidfn(void){}
 static int static_fn(){}
 void return_only(){return;}
+int add10(int a, int b, int c, int d, int e, int f, int g, int h, int i, int j) {
+  return a + b + c + d + e + f + g + h + i + j;
+}
+long sub8(char a, short b, int c, long d, char e, short f, int g, long h) {
+  return a - b - c - d - e - f - g - h;
+}
+struct t76 {int a; int b;};
+struct t76 ret_t76(int x) { struct t76 s = {x, x + 1}; return s; }
+struct big76 {long a[5];};
+struct big76 ret_big76(long x) { struct big76 s; s.a[0] = x; s.a[4] = x * 4; return s; }
+
+struct node77;
+struct node77 *head77;
+struct node77 {int val; struct node77 *next;};
+struct flex77 {long n; int a[];};
+char buf77[64];
+
+struct cache78 {int head; _Alignas(64) int tail;};
+char pad78;
+_Alignas(64) struct cache78 q78;
 
 int g_1;
 int g_2;
@@ -82,6 +102,75 @@ Tree *tree = &(Tree){
   0,
 };
 
+struct layout80 {
+  char tag;
+  long value;
+  _Static_assert(sizeof(long) == 8, "long must be 8 bytes");
+};
+_Static_assert(sizeof(struct layout80) == 16, "struct layout80 must be 16 bytes");
+
+int arr83[10];
+int *p83 = &arr83[3];
+long d83 = &arr83[7] - &arr83[2];
+int a83[sizeof(struct layout80) * 2 + (1 ? 1 : 2)];
+
+struct config84 {
+  char *name;
+  int val;
+  int flags[3];
+};
+struct config84 table84[] = {
+    [1] = {.name = "beta", .val = 2},
+    [0] = {.name = "alpha", .val = 1, .flags = {[2] = 8}},
+};
+int range84[8] = {[0 ... 7] = -1, [3] = 3};
+
+struct point85 {
+  int x;
+  int y;
+};
+struct shape85 {
+  char name[8];
+  int n;
+  struct point85 pts[2];
+};
+struct shape85 shapes85[] = {"line", 2, 0, 0, 3, 4, "dot", 1, {{5, 6}}};
+int cube85[2][2][2] = {1, 2, {3}, 4, 5, 6};
+
+int a86 = 1, *b86 = &a86, c86[3] = {2, 3}, d86;
+typedef int int86, *intp86;
+struct pair86 {
+  int x, y, *p;
+};
+
+int add87(int a, int b) { return a + b; }
+int sub87(int a, int b) { return a - b; }
+int (*ops87[])(int, int) = {add87, sub87};
+typedef int binop87(int, int);
+int (*pick87(int i))(int, int) { return ops87[i]; }
+int apply87(binop87 *f, int a, int b) { return f(a, b); }
+
+int int_max88 = 2147483647;
+long wrap88 = 2147483647 + 1;
+char narrow88(char c) { return c; }
+short widen88(int x) { return x; }
+
+int add89(int, int);
+int add89(int a, int b);
+int add89(int a, int b) { return a + b; }
+int unproto89();
+int unproto89(int a) { return a * 2; }
+void *id90(void *v) { return v; }
+int *null90(void) { return 0; }
+int *gp90 = 0;
+
+int sum79(int n, int a[n]) {
+  int s = 0;
+  for (int i = 0; i < n; i++)
+    s += a[i];
+  return s;
+}
+
 int main() {
   /*
   this is test script.
@@ -735,6 +824,144 @@ int main() {
   assert('a', ((struct {char a; int b;}){'a', 3}).a, "((struct {char a; int b;}){'a', 3}).a");
   assert(3, ({ int x=3; (int){x}; }), "int x=3; (int){x};");
 
+  // #75
+  printf("\n\n#75\n");
+  assert(55, add10(1,2,3,4,5,6,7,8,9,10), "add10(1,2,3,4,5,6,7,8,9,10)");
+  assert(-28, sub8(0,1,2,3,4,5,6,7), "sub8(0,1,2,3,4,5,6,7)");
+  assert(10, ({ int x=1; add10(x,x,x,x,x,x,x,x,x,x); }), "int x=1; add10(x,x,x,x,x,x,x,x,x,x);");
+  assert(56, add10(1,2,3,4,5,6,7,8,9,add10(1,1,1,1,1,1,1,1,1,2)), "add10(1,2,3,4,5,6,7,8,9,add10(1,1,1,1,1,1,1,1,1,2))");
+
+  // #76
+  printf("\n\n#76\n");
+  assert(3, ({ struct u76 {int a; int b;} x = {1, 2}; struct u76 y = x; y.a + y.b; }), "struct u76 y = x;");
+  assert(7, ({ struct u76 {int a; int b;} x = {3, 4}; struct u76 y; struct u76 *p = &y; *p = x; y.a + y.b; }), "*p = x;");
+  assert(12, ({ struct s76 {long a[4];} x; x.a[3] = 12; struct s76 y; y = x; y.a[3]; }), "y = x;");
+  assert(4, ({ struct u76 {int a; int b;} x = {1, 2}; struct u76 y = {3, 4}; (x, y).b; }), "(x, y).b");
+  assert(1, ({ int i = 0; (i++, i); }), "(i++, i)");
+  assert(8, ({ union {int a; char b;} x; x.a = 264; x.b; }), "union");
+  assert(3, ret_t76(1).a + ret_t76(1).b, "ret_t76(1).a + ret_t76(1).b");
+  assert(45, ({ struct big76 s = ret_big76(9); s.a[0] + s.a[4]; }), "struct big76 s = ret_big76(9);");
+
+  // #77
+  printf("\n\n#77\n");
+  assert(8, sizeof(struct flex77), "sizeof(struct flex77)");
+  assert(9, ({ struct flex77 *p = buf77; p->n = 2; p->a[0] = 4; p->a[1] = 5; p->a[0] + p->a[1]; }), "p->a[0] + p->a[1]");
+  assert(3, ({ struct node77 a; struct node77 b; a.val = 1; b.val = 2; a.next = &b; head77 = &a; head77->val + head77->next->val; }), "head77->val + head77->next->val");
+  assert(16, ({ struct t76 {long a; long b;}; sizeof(struct t76); }), "struct t76 {long a; long b;};");
+  assert(1, ({ struct t76 x = ret_t76(1); x.a; }), "struct t76 x = ret_t76(1);");
+
+  // #78
+  printf("\n\n#78\n");
+  assert(64, _Alignof(struct cache78), "_Alignof(struct cache78)");
+  assert(128, sizeof(struct cache78), "sizeof(struct cache78)");
+  assert(1, ({ long p = &q78; long t = &q78.tail; (p & 63) == 0 && t - p == 64; }), "&q78.tail - &q78");
+  assert(1, ({ char c; _Alignas(32) int x = 5; long p = &x; (p & 31) == 0 && x == 5; }), "_Alignas(32) int x = 5;");
+  assert(8, alignof(long), "alignof(long)");
+
+  // #79
+  printf("\n\n#79\n");
+  assert(40, ({ int n = 10; int a[n]; sizeof(a); }), "int a[n]; sizeof(a)");
+  assert(15, ({ int n = 5; int a[n]; for (int i = 0; i < n; i++) a[i] = i + 1; sum79(n, a); }), "sum79(n, a)");
+  assert(96, ({ int n = 4; long b[n][3]; sizeof(b); }), "long b[n][3]; sizeof(b)");
+  assert(7, ({ int n = 4; long b[n][3]; b[3][2] = 7; b[3][2]; }), "b[3][2]");
+
+  // #80
+  printf("\n\n#80\n");
+  assert(16, ({ _Static_assert(sizeof(struct layout80) == 16, "layout80"); sizeof(struct layout80); }), "_Static_assert(sizeof(struct layout80) == 16)");
+  assert(2, ({ static_assert(1); 2; }), "static_assert(1);");
+
+  // #81
+  printf("\n\n#81\n");
+  assert(8, ({ long x = 3; typeof(x) y = 5; sizeof(y); }), "typeof(x) y; sizeof(y)");
+  assert(16, ({ struct layout80 a; __typeof__(a) b; sizeof(b); }), "__typeof__(a) b; sizeof(b)");
+  assert(5, ({ int a = 3; int b = 5; ({ typeof(a) t = a; a = b; b = t; }); a; }), "swap with typeof");
+  assert(3, ({ int a = 3; typeof_unqual(&a) p = &a; *p; }), "typeof_unqual(&a) p");
+
+  // #82
+  printf("\n\n#82\n");
+  assert(1, ({ char c; _Generic(c, char: 1, int: 2, default: 3); }), "_Generic(c, char: 1, int: 2, default: 3)");
+  assert(3, ({ long l; _Generic(l, char: 1, int: 2, default: 3); }), "_Generic(l, char: 1, int: 2, default: 3)");
+  assert(4, ({ char *s = "abc"; _Generic(s, char *: 4, int *: 5); }), "_Generic(s, char *: 4, int *: 5)");
+  assert(16, ({ struct layout80 x; _Generic(x, struct layout80: sizeof(x), default: 0); }), "_Generic(x, struct layout80: sizeof(x))");
+
+  // #83
+  printf("\n\n#83\n");
+  assert(1, p83 == arr83 + 3, "p83 == arr83 + 3");
+  assert(5, d83, "d83");
+  assert(132, sizeof(a83), "sizeof(a83)");
+  assert(5, ({ int a[6 ^ 3]; sizeof(a) / 4; }), "int a[6 ^ 3];");
+
+  // #84
+  printf("\n\n#84\n");
+  assert(48, sizeof(table84), "sizeof(table84)");
+  assert(8, table84[0].flags[2], "table84[0].flags[2]");
+  assert(98, table84[1].name[0], "table84[1].name[0]");
+  assert(2, table84[1].val, "table84[1].val");
+  assert(-1, range84[7], "range84[7]");
+  assert(3, range84[3], "range84[3]");
+  assert(7, ({ struct config84 c = {.flags[1] = 7, .val = 4}; c.flags[1]; }), "c.flags[1]");
+
+  // #85
+  printf("\n\n#85\n");
+  assert(2, sizeof(shapes85) / sizeof(shapes85[0]), "sizeof(shapes85) / sizeof(shapes85[0])");
+  assert(105, shapes85[0].name[1], "shapes85[0].name[1]");
+  assert(4, shapes85[0].pts[1].y, "shapes85[0].pts[1].y");
+  assert(100, shapes85[1].name[0], "shapes85[1].name[0]");
+  assert(6, shapes85[1].pts[0].y, "shapes85[1].pts[0].y");
+  assert(0, shapes85[1].pts[1].x, "shapes85[1].pts[1].x");
+  assert(3, cube85[0][1][0], "cube85[0][1][0]");
+  assert(0, cube85[1][1][1], "cube85[1][1][1]");
+  assert(13, ({ struct shape85 s[2] = {"a", 1, 2, 3, 4, 5, "b", 6, 7}; s[1].n + s[1].pts[0].x; }), "s[1].n + s[1].pts[0].x");
+  assert(5, ({ struct point85 p = {2, 3}; struct shape85 s = {"c", 0, p}; s.pts[0].x + s.pts[0].y + s.pts[1].x; }), "s.pts[0].x + s.pts[0].y");
+
+  // #86
+  printf("\n\n#86\n");
+  assert(1, *b86, "*b86");
+  assert(3, c86[1], "c86[1]");
+  assert(0, c86[2] + d86, "c86[2] + d86");
+  assert(16, sizeof(struct pair86), "sizeof(struct pair86)");
+  assert(5, ({ int86 i = 5; intp86 p = &i; *p; }), "*p");
+  assert(6, ({ int x = 1, y = x + 1, z[2] = {3}; x + y + z[0] + z[1]; }), "x + y + z[0] + z[1]");
+  assert(9, ({ struct pair86 q = {4, 5, &q.x}; *q.p + q.y; }), "*q.p + q.y");
+
+  // #87
+  printf("\n\n#87\n");
+  assert(16, sizeof(ops87), "sizeof(ops87)");
+  assert(7, ops87[0](3, 4), "ops87[0](3, 4)");
+  assert(-1, (*ops87[1])(3, 4), "(*ops87[1])(3, 4)");
+  assert(2, pick87(1)(5, 3), "pick87(1)(5, 3)");
+  assert(9, apply87(add87, 4, 5), "apply87(add87, 4, 5)");
+  assert(1, apply87(&sub87, 4, 3), "apply87(&sub87, 4, 3)");
+  assert(10, ({ binop87 *f = add87; f(6, 4); }), "f(6, 4)");
+  assert(1, ({ int (*f)(int, int) = sub87; f == ops87[1]; }), "f == ops87[1]");
+
+  // #88
+  printf("\n\n#88\n");
+  assert(4, ({ char c; sizeof(c + c); }), "sizeof(c + c)");
+  assert(8, ({ char c; long l; sizeof(c - l); }), "sizeof(c - l)");
+  assert(-2147483648, wrap88, "wrap88");
+  assert(1, ({ long l = int_max88 + 1; l < 0; }), "l < 0");
+  assert(-2, ({ long l = int_max88 * 2; l; }), "int_max88 * 2");
+  assert(44, narrow88(300), "narrow88(300)");
+  assert(4464, widen88(70000), "widen88(70000)");
+  assert(-56, ({ char c = 100; c += 100; }), "c += 100");
+  assert(-128, ({ char c = 127; ++c; }), "++c");
+  assert(3, ({ int a[5]; &a[4] - &a[1]; }), "&a[4] - &a[1]");
+
+  // #89
+  printf("\n\n#89\n");
+  assert(7, add89(3, 4), "add89(3, 4)");
+  assert(8, unproto89(4), "unproto89(4)");
+  assert(5, ({ int add89(int, int); add89(2, 3); }), "add89(2, 3)");
+
+  // #90
+  printf("\n\n#90\n");
+  assert(4, ({ int x = 4; int *p = id90(&x); *p; }), "int x = 4; int *p = id90(&x); *p;");
+  assert(1, null90() == 0, "null90() == 0");
+  assert(1, gp90 == (void *)0, "gp90 == (void *)0");
+  assert(1, ({ char c; void *v = &c; char *p = v; p == &c; }), "char c; void *v = &c; char *p = v; p == &c;");
+  assert(2, ({ int a[2][3]; int (*p)[3] = a; p[0][1] = 2; a[0][1]; }), "int a[2][3]; int (*p)[3] = a; p[0][1] = 2; a[0][1];");
+
   assert(1, tree->val, "tree->val");
   assert(2, tree->lhs->val, "tree->lhs->val");
   assert(3, tree->lhs->lhs->val, "tree->lhs->lhs->val");
@@ -31,6 +31,24 @@ int add_all(int n, ...) {
         sum += va_arg(ap, int);
     return sum;
 }
+int add10(int a, int b, int c, int d, int e, int f, int g, int h, int i, int j) {
+    return a+b+c+d+e+f+g+h+i+j;
+}
+long sub8(char a, short b, int c, long d, char e, short f, int g, long h) {
+    return a-b-c-d-e-f-g-h;
+}
+int rs_add8(int a, int b, int c, int d, int e, int f, int g, int h) __attribute__((weak));
+int call_rs_add8() {
+    return rs_add8(1, 2, 3, 4, 5, 6, 7, 8);
+}
+struct s3 { char a; short b; int c; char d; };
+struct s3 ret_s3(int x) { struct s3 s = {x, x + 1, x + 2, x + 3}; return s; }
+struct big { long a; long b; long c; };
+struct big ret_big(long x) { struct big s = {x, x * 2, x * 3}; return s; }
+struct s3 rs_ret_s3(int x) __attribute__((weak));
+struct big rs_ret_big(long x) __attribute__((weak));
+int call_rs_ret_s3() { struct s3 s = rs_ret_s3(10); return s.a + s.b + s.c + s.d; }
+long call_rs_ret_big() { struct big s = rs_ret_big(5); return s.a + s.b + s.c; }
 EOF
 
 # 1
@@ -780,6 +798,332 @@ compound_literal() {
     assert 3 'int main(){ return ({ int x=3; (int){x}; }); }'
 }
 
+# 75
+many_args() {
+    assert 55 'int add10(int a, int b, int c, int d, int e, int f, int g, int h, int i, int j);int main(){return add10(1,2,3,4,5,6,7,8,9,10);}'
+    assert 55 'int add10(int a, int b, int c, int d, int e, int f, int g, int h, int i, int j);int main(){int x = 0; x = x + 1; return add10(1,2,3,4,5,6,7,8,9,10);}'
+    assert 28 'long sub8(char a, short b, int c, long d, char e, short f, int g, long h);int main(){return -sub8(0,1,2,3,4,5,6,7);}'
+    assert 36 'int rs_add8(int a, int b, int c, int d, int e, int f, int g, int h){return a+b+c+d+e+f+g+h;} int call_rs_add8(); int main(){return call_rs_add8();}'
+    assert 8 'int last(int a, int b, int c, int d, int e, int f, char g, long h){return h;} int main(){return last(1,2,3,4,5,6,7,8);}'
+    assert 7 'int last(int a, int b, int c, int d, int e, int f, char g, long h){return g;} int main(){return last(1,2,3,4,5,6,7,8);}'
+    assert 45 'int add_all(int n,...);int main(){return add_all(9,1,2,3,4,5,6,7,8,9);}'
+}
+
+# 76
+struct_assign() {
+    assert 6 'int main(){struct t {int a; char b; long c;} x = {1, 2, 3}; struct t y; y = x; return y.a + y.b + y.c;}'
+    assert 3 'struct t {int a; int b;}; int main(){struct t x = {1, 2}; struct t *p = &x; struct t y; y = *p; return y.a + y.b;}'
+    assert 7 'struct t {int a; int b;}; int main(){struct t x = {3, 4}; struct t y; struct t *p = &y; *p = x; return y.a + y.b;}'
+    assert 30 'struct t {int a; int b;}; int main(){struct t x = {10, 20}; struct t y = x; return y.a + y.b;}'
+    assert 9 'struct t {int a[20];}; int main(){struct t x; x.a[19] = 9; struct t y; y = x; return y.a[19];}'
+    assert 2 'struct t {int a; int b;}; int main(){struct t x = {1, 2}; struct t y = {3, 4}; struct t z; z = 0 ? x : y; return z.a - x.a;}'
+    assert 4 'struct t {int a; int b;}; int main(){struct t x = {1, 2}; struct t y = {3, 4}; return (x, y).b;}'
+    assert 3 'struct t {int a; int b;}; int main(){struct t x = {1, 2}; struct t y; struct t z; z = y = x; return z.a + z.b;}'
+    assert 5 'struct in {int a; int b;}; struct out {int c; struct in i;}; int main(){struct out x; x.i.a = 2; x.i.b = 3; struct in y; y = x.i; return y.a + y.b;}'
+    assert 8 'union u {int a; char b;}; int main(){union u x; x.a = 264; return x.b;}'
+    assert 7 'union u {int a; char b[3];}; int main(){union u x = {7}; union u y; y = x; return y.a;}'
+    assert 3 'struct t {int a; int b;}; struct t f(int x){struct t s = {x, x + 1}; return s;} int main(){struct t s = f(1); return s.a + s.b;}'
+    assert 14 'struct t {char a; short b; int c; long d;}; struct t f(int x){struct t s = {x, x + 1, x + 2, x + 3}; return s;} int main(){struct t s; s = f(2); return s.a + s.b + s.c + s.d;}'
+    assert 10 'struct t {long a[5];}; struct t f(long x){struct t s; for (int i = 0; i < 5; i++) s.a[i] = x + i; return s;} int main(){return f(0).a[4] + f(2).a[4];}'
+    assert 30 'struct t {long a[20];}; struct t f(long x){struct t s; for (int i = 0; i < 20; i++) s.a[i] = x; return s;} int main(){struct t s = f(10); return s.a[0] + s.a[10] + s.a[19];}'
+    assert 10 'struct s3 {char a; short b; int c; char d;}; struct s3 ret_s3(int x); int main(){struct s3 s = ret_s3(1); return s.a + s.b + s.c + s.d;}'
+    assert 60 'struct big {long a; long b; long c;}; struct big ret_big(long x); int main(){struct big s = ret_big(10); return s.a + s.b + s.c;}'
+    assert 46 'struct s3 {char a; short b; int c; char d;}; struct s3 rs_ret_s3(int x){struct s3 s = {x, x + 1, x + 2, x + 3}; return s;} int call_rs_ret_s3(); int main(){return call_rs_ret_s3();}'
+    assert 30 'struct big {long a; long b; long c;}; struct big rs_ret_big(long x){struct big s = {x, x * 2, x * 3}; return s;} long call_rs_ret_big(); int main(){return call_rs_ret_big();}'
+}
+
+# 77
+flexible_array() {
+    assert 8 'struct t {long n; char a[];}; int main(){return sizeof(struct t);}'
+    assert 12 'struct t {int n; int a[];}; char buf[64]; int main(){struct t *p = buf; p->n = 3; for (int i = 0; i < p->n; i++) p->a[i] = i + 3; return p->a[0] + p->a[1] + p->a[2];}'
+    assert 7 'void *malloc(long n); struct t {int n; long a[];}; int main(){struct t *p = malloc(sizeof(struct t) + sizeof(long) * 4); p->a[3] = 7; return p->a[3];}'
+    assert 3 'struct node; struct node *head; struct node {int val; struct node *next;}; int main(){struct node a; struct node b; a.val = 1; b.val = 2; a.next = &b; head = &a; return head->val + head->next->val;}'
+    assert 5 'struct t; int f(struct t *p); struct t {int a;}; int f(struct t *p){return p->a;} int main(){struct t x; x.a = 5; return f(&x);}'
+    assert 16 'struct t {int a; int b;}; int main(){struct t x; {struct t {long a; long b;}; x.a = sizeof(struct t);} return x.a;}'
+    assert 8 'struct t {int a; int b;}; int main(){{struct t; struct t {char a;} y;} return sizeof(struct t);}'
+    assert 1 'struct t {int a; int b;}; int main(){{struct t; struct t *p;} struct t x; x.a = 1; return x.a;}'
+}
+
+# 78
+alignment() {
+    assert 1 'int main(){return _Alignof(char);}'
+    assert 4 'int main(){return _Alignof(int[3]);}'
+    assert 8 'int main(){return alignof(long);}'
+    assert 8 'int main(){return _Alignof(struct {char a;});}'
+    assert 32 'struct t {int a; _Alignas(32) int b;}; int main(){return _Alignof(struct t);}'
+    assert 64 'struct t {int a; _Alignas(32) int b;}; int main(){return sizeof(struct t);}'
+    assert 16 'struct t {char a; _Alignas(16) int b;}; int main(){struct t s; long p = &s; long q = &s.b; return q - p;}'
+    assert 8 'int main(){_Alignas(long) char c; return _Alignof(c);}'
+    assert 16 'int main(){_Alignas(8) _Alignas(16) char c; return _Alignof(c);}'
+    assert 1 'int main(){char a; _Alignas(64) char x; long p = &x; return (p & 63) == 0;}'
+    assert 1 'int main(){char a; _Alignas(32) int x = 3; long p = &x; return (p & 31) == 0 && x == 3;}'
+    assert 1 'int main(){char a; _Alignas(64) int x[] = {1, 2, 3}; long p = x; return (p & 63) == 0 && x[2] == 3;}'
+    assert 1 'char a; _Alignas(64) char g; char b; int main(){long p = &g; return (p & 63) == 0;}'
+    assert 1 'struct t {char a; _Alignas(64) char b;}; char c; struct t g; int main(){long p = &g; return (p & 63) == 0;}'
+    assert 1 'int main(){static _Alignas(32) char x; long p = &x; return (p & 31) == 0;}'
+    assert 8 'int f(int a, int b, int c, int d, int e, int f, int g, int h){_Alignas(64) int x = h; long p = &x; return x + (p & 63);} int main(){return f(1,2,3,4,5,6,7,8);}'
+    assert 55 'int add10(int a, int b, int c, int d, int e, int f, int g, int h, int i, int j); int main(){_Alignas(64) int x = 1; return add10(x,2,3,4,5,6,7,8,9,10);}'
+    assert 123 'int f(int n, int m, int *p){int s = 0; int i = 0; int j = 0; return n * 100 + m * 10 + *p + s + i + j;} int main(){int x = 3; return f(1, 2, &x);}'
+}
+
+# 79
+vla() {
+    assert 20 'int main(){int n = 5; int a[n]; return sizeof(a);}'
+    assert 24 'int main(){int n = 3; long a[n]; return sizeof a;}'
+    assert 12 'int main(){int n = 3; return sizeof(int[n]);}'
+    assert 60 'int main(){int n = 5; int a[n][3]; return sizeof(a);}'
+    assert 10 'int main(){int n = 5; int a[n]; for (int i = 0; i < n; i++) a[i] = i; int s = 0; for (int i = 0; i < n; i++) s += a[i]; return s;}'
+    assert 9 'int main(){int n = 5; long b[n][3]; b[4][2] = 9; return b[4][2];}'
+    assert 6 'int sum(int n, int a[n]){int s = 0; for (int i = 0; i < n; i++) s += a[i]; return s;} int main(){int n = 3; int a[n]; a[0] = 1; a[1] = 2; a[2] = 3; return sum(n, a);}'
+    assert 3 'int main(){int n = 4; char a[n]; char *p = a; p[3] = 3; return *(a + 3);}'
+    assert 1 'int main(){int n = 4; int a[n]; int *p = &a; return p == a;}'
+    assert 1 'int main(){int n = 3; _Alignas(64) char c; long a[n]; long p = a; return (p & 7) == 0 && sizeof(a) == 24;}'
+    assert 3 'int main(){int x = 1; int n = 3; int a[n]; a[2] = 2; int y = 0; return x + a[2] + y;}'
+    assert 8 'int main(){int m = 3; return 1 + ({int x[m]; x[2] = 7; x[2];});}'
+    assert 42 'int main(){int t = 0; for (int i = 0; i < 100000; i++) {long buf[100]; int n = 100; long v[n]; v[99] = i; if (i == 42) {t = v[99]; break;} if (i & 1) continue;} return t;}'
+    assert 99 'int main(){int i = 0; again: {int n = 100; long big[n]; big[n - 1] = i; i++; if (i < 100000) goto again; return big[n - 1] - 99900;}}'
+    assert 1 'int main(){int n = 1000; int k = 0; while (k < 100000) {char s[n]; s[0] = 1; switch (k) {case 0: {char t[n]; k++; break;} default: k++;}} return 1;}'
+    assert 23 'int main(){int n = 3; int m = 4; int a[n][m]; for (int i = 0; i < n; i++) for (int j = 0; j < m; j++) a[i][j] = i * 10 + j; return a[2][3];}'
+    assert 48 'int main(){int n = 3; int m = 4; int a[n][m]; n = 10; m = 10; return sizeof(a) + a - a;}'
+    assert 16 'int main(){int n = 3; int m = 4; int a[n][m]; m = 10; return sizeof(a[1]);}'
+    assert 9 'int main(){int n = 5; int a[3][n]; a[2][4] = 9; return a[2][4];}'
+    assert 2 'int main(){int n = 5; int a[3][n]; return &a[2] - &a[0];}'
+    assert 77 'int main(){int n = 3; int m = 5; long t[2][n][m]; t[1][2][4] = 77; return t[1][2][4] + sizeof(t) - 240;}'
+    assert 138 'int sum(int n, int m, int a[n][m]){int s = 0; for (int i = 0; i < n; i++) for (int j = 0; j < m; j++) s += a[i][j]; return s;} int main(){int a[3][4]; for (int i = 0; i < 3; i++) for (int j = 0; j < 4; j++) a[i][j] = i * 10 + j; return sum(3, 4, a);}'
+    assert 9 'int main(){int n = 3; int m = 5; int a[n][m]; a[2][4] = 9; int (*row)[m] = a; return row[2][4];}'
+}
+
+# 80
+static_assertion() {
+    assert 3 '_Static_assert(sizeof(int) == 4, "int"); int main(){return 3;}'
+    assert 3 'static_assert(1); int main(){return 3;}'
+    assert 16 'struct s {int a; long b; _Static_assert(sizeof(long) == 8, "long");}; _Static_assert(sizeof(struct s) == 16, "struct s"); int main(){return sizeof(struct s);}'
+    assert 5 'int main(){_Static_assert(2 > 1, "ok"); int x = 5; static_assert(sizeof(x) == 4); return x;}'
+    assert 4 'enum {A = 4}; int main(){for (;;) {_Static_assert(A == 4, "A"); break;} return A;}'
+}
+
+# 81
+type_of() {
+    assert 8 'int main(){long x = 3; typeof(x) y = 5; return sizeof(y) + y - 5;}'
+    assert 4 'int main(){char c; typeof(int) x = 4; return x;}'
+    assert 12 'int main(){int a[3]; typeof(a) b; return sizeof(b);}'
+    assert 3 'int main(){int a = 3; __typeof__(&a) p = &a; return *p;}'
+    assert 1 'int main(){char c; typeof_unqual(c) d; return sizeof(d);}'
+    assert 0 'int cnt; int next(){cnt++; return cnt;} int main(){typeof(next()) x = 0; return cnt + x;}'
+    assert 7 'int main(){int a = 3; int b = 7; ({typeof(a) t = a; a = b; b = t;}); return a;}'
+    assert 2 'int main(){int a = 2; int b = 5; return ({typeof(a) _a = a; typeof(b) _b = b; _a < _b ? _a : _b;});}'
+    assert 16 'struct s {long a; long b;}; int main(){struct s x; typeof(x) y; return sizeof(y);}'
+    assert 8 'int main(){int a = 1; return sizeof(typeof(&a));}'
+    assert 5 'int g = 5; typeof(g) h = 5; int main(){return h;}'
+}
+
+# 82
+generic() {
+    assert 1 'int main(){char c; return _Generic(c, char: 1, int: 2, long: 3);}'
+    assert 2 'int main(){int i; return _Generic(i, char: 1, int: 2, long: 3);}'
+    assert 3 'int main(){long l; return _Generic(l, char: 1, int: 2, long: 3);}'
+    assert 4 'int main(){short s; return _Generic(s, char: 1, int: 2, default: 4);}'
+    assert 5 'int main(){int *p; return _Generic(p, char *: 6, int *: 5, default: 7);}'
+    assert 5 'int main(){int a[3]; return _Generic(a, int *: 5, default: 7);}'
+    assert 8 'struct s {int a;}; int main(){struct s x; return _Generic(x, struct s: 8, default: 9);}'
+    assert 0 'int cnt; int next(){cnt++; return cnt;} int main(){return _Generic(next(), int: 0, default: 1) + cnt;}'
+    assert 6 'int main(){int x = 3; return _Generic(x, int: x * 2, default: x);}'
+    assert 1 'int main(){return _Generic(1, default: 1);}'
+    assert 4 'int f(int x){return x;} int main(){return _Generic(f, int(*)(int): 4, int: 2, default: 3);}'
+    assert 5 'int f(int x){return x;} int main(){return _Generic(f, int(*)(int, int): 4, int(*)(int): 5, default: 3);}'
+}
+
+# 83
+const_expr() {
+    assert 9 'int a[sizeof(long) + (1 ? 1 : 2)]; int main(){return sizeof(a) / 4;}'
+    assert 5 'int main(){int a[6 ^ 3]; return sizeof(a) / 4;}'
+    assert 44 'char c = (char)300; int main(){return c;}'
+    assert 16 'int g; int *p = &g + 4; int main(){return (long)p - (long)&g;}'
+    assert 1 'int a[10]; int *p = &a[3]; int main(){return p == a + 3;}'
+    assert 1 'int a[10]; int *p = 2 + a; int main(){return p == &a[2];}'
+    assert 5 'int a[10]; long d = &a[7] - &a[2]; int main(){return d;}'
+    assert 8 'struct s {int a; long b;} g; long *p = &g.b; int main(){return (char *)p - (char *)&g;}'
+    assert 101 'char *s = "hello" + 1; int main(){return *s;}'
+    assert 1 'int m[2][3]; int *p = m[1]; int main(){return p == &m[1][0];}'
+    assert 7 'enum {A = 3, B = A * 2 + 1}; int main(){int a[B > 6 ? B : 1]; return sizeof(a) / 4;}'
+    assert 1 'int main(){switch (3) {case 1 << 1 | 1: return 1;} return 0;}'
+}
+
+# 84
+designated_initializer() {
+    assert 5 'int main(){int a[4] = {[2] = 5, 6}; return a[2];}'
+    assert 6 'int main(){int a[4] = {[2] = 5, 6}; return a[3] + a[0] + a[1];}'
+    assert 12 'int main(){int a[] = {[2] = 1}; return sizeof(a);}'
+    assert 1 'int main(){int a[] = {[2] = 1}; return a[2];}'
+    assert 21 'int main(){struct {int x; int y;} p = {.y = 1, .x = 20}; return p.x + p.y;}'
+    assert 7 'int main(){struct {int a[3]; int b;} s = {.a[1] = 3, .b = 4}; return s.a[1] + s.b + s.a[0] + s.a[2];}'
+    assert 3 'int main(){struct {struct {int a; int b;} in; int c;} s = {.in.b = 3}; return s.in.b + s.in.a + s.c;}'
+    assert 10 'int main(){int a[10] = {[0 ... 9] = 1}; int s = 0; for (int i = 0; i < 10; i++) s += a[i]; return s;}'
+    assert 14 'int main(){int a[10] = {[0 ... 9] = 1, [4] = 5}; int s = 0; for (int i = 0; i < 10; i++) s += a[i]; return s;}'
+    assert 20 'int main(){int a[4] = {1, 2, 3, 4, [1] = 20}; return a[1];}'
+    assert 2 'int main(){union {char c; int i;} u = {.i = 258}; return u.c;}'
+    assert 5 'int main(){struct {int x; int y;} p[3] = {[1].y = 5, [2] = {6, 7}}; return p[1].y + p[1].x + p[0].x;}'
+    assert 13 'int main(){struct {int x; int y;} p[3] = {[1].y = 5, [2] = {6, 7}}; return p[2].x + p[2].y;}'
+    assert 5 'int a[4] = {[2] = 5, 6}; int main(){return a[2];}'
+    assert 6 'int a[4] = {[2] = 5, 6}; int main(){return a[3];}'
+    assert 12 'int a[] = {[2] = 1}; int main(){return sizeof(a);}'
+    assert 21 'struct {int x; int y;} p = {.y = 1, .x = 20}; int main(){return p.x + p.y;}'
+    assert 14 'int a[10] = {[0 ... 9] = 1, [4] = 5}; int main(){int s = 0; for (int i = 0; i < 10; i++) s += a[i]; return s;}'
+    assert 2 'union {char c; int i;} u = {.i = 258}; int main(){return u.c;}'
+    assert 98 'struct {char *name; int val;} t[] = {[1] = {.name = "beta", .val = 2}, [0] = {"alpha", 1}}; int main(){return t[1].name[0];}'
+    assert 32 'struct {char *name; int val;} t[] = {[1] = {.name = "beta", .val = 2}, [0] = {"alpha", 1}}; int main(){return sizeof(t);}'
+    assert 11 'int cnt; int f(){return ++cnt;} int main(){int a[10] = {[0 ... 9] = f()}; int s = 0; for (int i = 0; i < 10; i++) s += a[i]; return s + cnt;}'
+    assert 3 'int cnt; int f(){return ++cnt;} int main(){int a[3] = {[0 ... 2] = f(), [1] = 40}; return cnt + a[0] + a[2];}'
+    assert 8 'int cnt; int f(){return ++cnt;} int main(){struct {int x; int y;} p[4] = {[1].y = 7, [0 ... 3].x = f()}; return cnt + p[1].y + p[0].y;}'
+    assert 3 'int main(){struct {struct {int a; int b;} s;} x = {.s.a = 5, .s = {3}}; return x.s.a + x.s.b;}'
+    assert 18 'int main(){int m[2][3] = {[0][1] = 5, 6, 7}; return m[0][1] + m[0][2] + m[1][0] * (m[1][1] + 1) + m[1][2];}'
+    assert 123 'struct S {int a; int b[3]; struct {int x, y;} p;} g = {.b[1] = 7, 1, 2, 3}; int main(){return g.b[2] * 100 + g.p.x * 10 + g.p.y + g.a + g.b[0] - g.b[1] + 7;}'
+    assert 123 'int main(){struct {int a; int b[3]; struct {int x, y;} p;} l = {.b[1] = 7, 1, 2, 3}; return l.b[2] * 100 + l.p.x * 10 + l.p.y + l.a + l.b[0] - l.b[1] + 7;}'
+    assert 105 'struct S {int a; int b[3]; struct {int x, y;} p;} g = {.p.y = 5, .b[1] = 7, 1}; int main(){return g.b[2] * 100 + g.p.x * 10 + g.p.y;}'
+    assert 0 'int main(){struct {char n[4];} t = {.n[2] = 113, .n = "x"}; return t.n[2];}'
+}
+
+# 85
+brace_elision() {
+    assert 10 'int main(){struct {int a[2]; struct {int x; int y;} p;} v = {1, 2, 3, 4}; return v.a[0] + v.a[1] + v.p.x + v.p.y;}'
+    assert 4 'int main(){struct {int a[2]; struct {int x; int y;} p;} v = {1, 2, 3, 4}; return v.p.y;}'
+    assert 9 'int main(){int m[2][2][2] = {1, 2, {3}, 4, 5, 6}; return m[1][1][0] + m[0][1][0];}'
+    assert 3 'int main(){int m[2][3] = {{1}, 2, 3}; return m[1][1] + m[0][1];}'
+    assert 99 'int main(){char s[2][4] = {"ab", "cd"}; return s[1][0];}'
+    assert 100 'int main(){struct {char name[4]; int n;} t[2] = {"ab", 1, "cd", 2}; return t[1].name[1];}'
+    assert 2 'int main(){struct {char name[4]; int n;} t[2] = {"ab", 1, "cd", 2}; return t[1].n;}'
+    assert 16 'int main(){struct {int x; int y;} p[] = {1, 2, 3}; return sizeof(p);}'
+    assert 0 'int main(){struct {int x; int y;} p[] = {1, 2, 3}; return p[1].y;}'
+    assert 4 'int main(){struct {int a[2]; int b;} s = {1, .b = 3}; return s.b + s.a[0] + s.a[1];}'
+    assert 12 'int main(){int m[2][2] = {1, [1] = 5, 6}; return m[1][0] + m[1][1] + m[0][0] + m[0][1];}'
+    assert 7 'int main(){struct {union {int i; char c[4];} u; int k;} s[2] = {1, 2, 3, 4}; return s[1].u.i + s[1].k;}'
+    assert 5 'int main(){struct P {int x; int y;}; struct P p = {2, 3}; struct {struct P p; int n;} t[2] = {p, 4, {p}}; return t[1].p.x + t[1].p.y + t[1].n;}'
+    assert 0 'int main(){struct {} e = {}; int a[3] = {}; return a[2];}'
+    assert 10 'struct {int a[2]; struct {int x; int y;} p;} v = {1, 2, 3, 4}; int main(){return v.a[0] + v.a[1] + v.p.x + v.p.y;}'
+    assert 9 'int m[2][2][2] = {1, 2, {3}, 4, 5, 6}; int main(){return m[1][1][0] + m[0][1][0];}'
+    assert 6 'int cnt; int f(){return ++cnt;} int main(){struct {struct {int a, b;} s; int z;} q = {f(), f(), f()}; return cnt + q.z;}'
+    assert 42 'int main(){struct P {int a; int b;} p = {4, 2}; struct {struct P s; char *t;} q = {p, "z"}; return q.s.a * 10 + q.s.b + q.t[1];}'
+    assert 100 'struct {char name[4]; int n;} t[2] = {"ab", 1, "cd", 2}; int main(){return t[1].name[1];}'
+    assert 7 'struct {union {int i; char c[4];} u; int k;} s[2] = {1, 2, 3, 4}; int main(){return s[1].u.i + s[1].k;}'
+    assert 120 'struct {int a; union {int i; char c[4];} u; int b;} k[2] = {1, {.c = "xy"}, 2, 3}; int main(){return k[0].u.c[0];}'
+}
+
+# 86
+multiple_declarators() {
+    assert 1 'int main(){int a = 1, *b = &a, c[3] = {0}; return *b + c[2];}'
+    assert 12 'int main(){int a = 1, *b = &a, c[3] = {0}; return sizeof(c);}'
+    assert 8 'int main(){int a, *b; return sizeof(b);}'
+    assert 5 'int main(){int a = 2, b = a + 3; return b;}'
+    assert 30 'int main(){int s = 0; for (int i = 0, j = 10; i < j; i++, j--) s += j - i; return s;}'
+    assert 7 'int main(){typedef int T, *PT; T t = 7; PT p = &t; return *p;}'
+    assert 3 'int main(){typedef char C, A[3]; A a; return sizeof(a);}'
+    assert 9 'int main(){static int a = 4, b = 5; return a + b;}'
+    assert 12 'int main(){int n = 3, v[n], w = 1; return sizeof(v);}'
+    assert 24 'int main(){struct {int x, *p, a[2];} s; return sizeof(s);}'
+    assert 6 'int main(){struct {char a, b; int c;} s = {1, 2, 3}; return s.a + s.b + s.c;}'
+    assert 6 'int a = 1, *b = &a, c[3] = {2, 3}; int main(){return *b + c[0] + c[1] + c[2];}'
+    assert 7 'int f(int x), g = 7; int f(int x){return x;} int main(){return f(g);}'
+    assert 8 'typedef long L, *PL; PL p; int main(){return sizeof(p);}'
+}
+
+# 87
+function_pointer() {
+    assert 3 'int plus(int a, int b){return a + b;} int main(){int (*fp)(int, int) = plus; return fp(1, 2);}'
+    assert 3 'int plus(int a, int b){return a + b;} int main(){int (*fp)(int, int) = &plus; return (*fp)(1, 2);}'
+    assert 8 'int main(){int (*fp)(int, int); return sizeof(fp);}'
+    assert 6 'int plus(int a, int b){return a + b;} int times(int a, int b){return a * b;} int main(){int (*ops[2])(int, int) = {plus, times}; return ops[0](1, 2) + ops[1](1, 3);}'
+    assert 16 'int main(){int (*ops[2])(int, int); return sizeof(ops);}'
+    assert 12 'int times(int a, int b){return a * b;} int (*pick(void))(int, int){return times;} int main(){return pick()(3, 4);}'
+    assert 5 'int inc(int x){return x + 1;} int apply(int (*f)(int), int x){return f(x);} int main(){return apply(inc, 4);}'
+    assert 5 'int inc(int x){return x + 1;} int apply(int f(int), int x){return f(x);} int main(){return apply(inc, 4);}'
+    assert 7 'int g; void set(int x){g = x;} void (*reg(int n, void (*h)(int)))(int){h(n); return h;} int main(){reg(3, set)(7); return g;}'
+    assert 12 'int main(){int a[2][4]; return sizeof(*(int (*)[4])a) - sizeof(int);}'
+    assert 12 'int (*getarr(void))[3]; int arr[2][3]; int (*getarr(void))[3]{return arr;} int main(){return sizeof(*getarr());}'
+    assert 9 'typedef int F(int); F sq; int sq(int x){return x * x;} int main(){F *f = sq; return f(3);}'
+    assert 4 'int half(int x){return x / 2;} struct S {int (*fn)(int); int v;}; int main(){struct S s = {half, 8}; return s.fn(s.v);}'
+    assert 2 'int plus(int a, int b){return a + b;} int minus(int a, int b){return a - b;} int (*tbl[])(int, int) = {plus, minus}; int main(){return tbl[1](5, 3);}'
+    assert 1 'int f(int x){return x;} int main(){return f == &f && *f == f;}'
+    assert 36 'int sum8(int a, int b, int c, int d, int e, int f, int g, int h){return a+b+c+d+e+f+g+h;} int main(){int (*p)(int, int, int, int, int, int, int, int) = sum8; return p(1, 2, 3, 4, 5, 6, 7, 8);}'
+    assert 4 'int main(){int twice(int); return twice(2);} int twice(int x){return x * 2;}'
+}
+
+# 88
+arithmetic_conversion() {
+    assert 4 'int main(){char c; return sizeof(c + c);}'
+    assert 8 'int main(){char c; long l; return sizeof(c * l);}'
+    assert 4 'int main(){char c; return sizeof(-c) + sizeof(~c) - sizeof(c < c);}'
+    assert 8 'int main(){int i; long l; return sizeof(1 ? i : l);}'
+    assert 200 'int main(){char a = 100, b = 100; return (a + b) / 1;}'
+    assert 1 'int main(){int i = 2147483647; long l = i + 1; return l == -2147483647 - 1;}'
+    assert 1 'int main(){long l = 1 << 31; return l < 0;}'
+    assert 1 'int main(){int i = 2147483647; long l = i * 2; return l == -2;}'
+    assert 44 'int main(){char c; return c = 300;}'
+    assert 200 'int main(){char c = 127; ++c; return c == -128 ? 200 : 0;}'
+    assert 56 'int main(){char c = 100; return -(c += 100);}'
+    assert 1 'int main(){_Bool b = 2; return b;}'
+    assert 44 'char f(char c){return c;} int main(){return f(300);}'
+    assert 3 'int f(int x){return x;} int main(){long l = 4294967299; return f(l);}'
+    assert 4 'char g(void){return 260;} int main(){return g();}'
+    assert 3 'int main(){int a[5]; return &a[4] - &a[1];}'
+    assert 8 'int main(){long a[2]; return (char *)&a[1] - (char *)&a[0];}'
+    assert 3 'int main(){int a[5]; a[2] = 3; return *(2 + a);}'
+    assert 1 'int g = 2147483647 + 1; int main(){return g < 0;}'
+    assert 1 'long g = 2147483647 * 2; int main(){return g == -2;}'
+}
+
+# 89
+prototype_check() {
+    assert 3 'int f(int a, int b); int f(int a, int b){return a + b;} int main(){return f(1, 2);}'
+    assert 3 'int f(int, int); int f(int x, int y); int f(int a, int b){return a + b;} int main(){return f(1, 2);}'
+    assert 2 'int f(); int f(int a){return a;} int main(){return f(2);}'
+    assert 5 'int f(int a){return a;} int f(); int main(){return f(5);}'
+    assert 0 'int g(); int main(){return g(1, 2, 3);} int g(){return 0;}'
+    assert 6 'int sum(int n, ...); int main(){return sum(3, 1, 2, 3) * 0 + 6;} int sum(int n, ...){return n;}'
+    assert 4 'int f(void (*h)(int)); void nop(int x){} int f(void (*g)(int x)){return 4;} int main(){return f(nop);}'
+    assert 7 'int main(){int f(int); return f(7);} int f(int x){return x;}'
+}
+
+# 90
+pointer_conversion() {
+    assert 5 'int main(){int x = 5; void *v = &x; int *p = v; return *p;}'
+    assert 6 'void *id(void *v){return v;} int main(){int x = 6; int *p = id(&x); return *p;}'
+    assert 1 'int main(){int *p = 0; return p == 0;}'
+    assert 1 'int main(){int *p = (void *)0; return !p;}'
+    assert 1 'int main(){char *p; p = 1 - 1; return p == (void *)0;}'
+    assert 1 'int main(){int x; void *v = &x; int *p = &x; return v == p;}'
+    assert 1 'int *null(void){return 0;} int main(){return null() == 0;}'
+    assert 1 'int main(){int x; _Bool b = &x; return b;}'
+    assert 7 'int main(){int a[2][3]; int (*p)[3] = a; p[1][2] = 7; return a[1][2];}'
+    assert 3 'int f(int *p){return *p;} int main(){int x = 3; int (*fp)(int *) = f; return fp(&x);}'
+}
+
+# 91
+diagnostic_pragma() {
+    assert 3 '#pragma GCC diagnostic push
+#pragma GCC diagnostic ignored "-Wint-conversion"
+int main(){int *p = 3; return p;}
+#pragma GCC diagnostic pop'
+    assert 2 '#pragma once
+int main(){return 2;}'
+}
+
+# 92
+control_flow() {
+    assert 0 'int main(){int x = 3; x++;}'
+    assert 3 'int f(int x){switch(x){case 1: x++; __attribute__((fallthrough)); case 2: x++; break; default: return 0;} return x;} int main(){return f(1);}'
+    assert 4 'int f(int x){switch(x){case 1: x += 2; [[fallthrough]]; case 2: x++;} return x;} int main(){return f(1);}'
+    assert 2 'int main(){for (;;) return 2;}'
+}
+
+# 93
+case_range() {
+    assert 1 'int f(int c){switch(c){case 0 ... 9: return 1; case 10 ... 19: return 2;} return 0;} int main(){return f(9);}'
+    assert 2 "int f(int c){switch(c){case 'a' ... 'z': return 2; default: return 0;} return 1;} int main(){return f('m');}"
+    assert 3 'int f(int c){switch(c){case -5 ... -1: return 3; case 0: return 4;} return 0;} int main(){return f(-5);}'
+    assert 0 'int f(int c){switch(c){case 1 ... 3: return 1;} return 0;} int main(){return f(4);}'
+    assert 5 'int f(int c){switch(c){case 1: case 2 ... 4: {case 5: return 5;}} return 0;} int main(){return f(3);}'
+}
+
 test() {
     cd /rs9cc/bin &&
         make test.exe &&
@@ -904,6 +1248,25 @@ if [ $# -eq 0 ]; then
     anonymous_struct
     bitassign
     compound_literal
+    many_args
+    struct_assign
+    flexible_array
+    alignment
+    vla
+    static_assertion
+    type_of
+    generic
+    const_expr
+    designated_initializer
+    brace_elision
+    multiple_declarators
+    function_pointer
+    arithmetic_conversion
+    prototype_check
+    pointer_conversion
+    diagnostic_pragma
+    control_flow
+    case_range
 fi
 
 while [ $# -ne 0 ]; do
@@ -982,6 +1345,25 @@ while [ $# -ne 0 ]; do
     "72") anonymous_struct ;;
     "73") bitassign ;;
     "74") compound_literal ;;
+    "75") many_args ;;
+    "76") struct_assign ;;
+    "77") flexible_array ;;
+    "78") alignment ;;
+    "79") vla ;;
+    "80") static_assertion ;;
+    "81") type_of ;;
+    "82") generic ;;
+    "83") const_expr ;;
+    "84") designated_initializer ;;
+    "85") brace_elision ;;
+    "86") multiple_declarators ;;
+    "87") function_pointer ;;
+    "88") arithmetic_conversion ;;
+    "89") prototype_check ;;
+    "90") pointer_conversion ;;
+    "91") diagnostic_pragma ;;
+    "92") control_flow ;;
+    "93") case_range ;;
     esac
     shift
 done
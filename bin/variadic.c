@@ -24,9 +24,30 @@ char *fmt(char *buf, char *fmt, ...) {
   vsprintf(buf, fmt, ap);
 }
 
+// スタックフレームを揃え直す関数でも可変長引数を読める
+char *fmt_aligned(char *buf, char *fmt, ...) {
+  va_list ap;
+  va_start(ap);
+  _Alignas(64) char pad[3];
+  long p = (long)pad;
+  if (p & 63)
+    exit(1);
+
+  vsprintf(buf, fmt, ap);
+  return buf;
+}
+
 int main(){
    char buf[100]; 
    fmt(buf, "aaa %d %d, %s \n",12,110,"hello world"); 
    printf(buf);
+   fmt(buf, "%d %d %d %d %d %d %d %d\n", 1, 2, 3, 4, 5, 6, 7, 8);
+   printf(buf);
+   if (strcmp(buf, "1 2 3 4 5 6 7 8\n"))
+     exit(1);
+   fmt_aligned(buf, "%d %d %d %d %d %d %d %d\n", 8, 7, 6, 5, 4, 3, 2, 1);
+   printf(buf);
+   if (strcmp(buf, "8 7 6 5 4 3 2 1\n"))
+     exit(1);
    return 0;
 }
\ No newline at end of file
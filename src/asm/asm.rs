@@ -1,6 +1,6 @@
 use super::error::Error;
 use crate::ast::{Initializer, Node, NodeKind, Program};
-use crate::base_types::{self, TypeKind};
+use crate::base_types::TypeKind;
 use std::fmt::Write;
 
 // jump の連番とかを格納しておく
@@ -10,6 +10,7 @@ pub struct Context {
     continue_label: usize,
     case_label: (usize, usize), // case_label, end_label
     func_name: String,
+    ret_ptr: Option<u64>, // 戻り値の構造体の書き込み先が保存されている変数のoffset
     asm: String,
 }
 
@@ -21,6 +22,7 @@ impl Context {
             continue_label: 0,
             case_label: (0, 0),
             func_name: String::new(),
+            ret_ptr: None,
             asm: String::new(),
         }
     }
@@ -31,6 +33,9 @@ const ARGREG2: [&str; 6] = ["di", "si", "dx", "cx", "r8w", "r9w"];
 const ARGREG4: [&str; 6] = ["edi", "esi", "edx", "ecx", "r8d", "r9d"];
 const ARGREG8: [&str; 6] = ["rdi", "rsi", "rdx", "rcx", "r8", "r9"];
 
+/// これより大きい構造体のコピーはmemcpyを呼ぶ
+const MAX_INLINE_COPY_SIZE: u64 = 64;
+
 pub fn code_gen(program: Program) -> Result<String, Error> {
     let mut ctx = Context::new();
 
@@ -48,6 +53,7 @@ pub fn code_gen(program: Program) -> Result<String, Error> {
         } else {
             writeln!(ctx.asm, ".global {}", name)?;
         }
+        writeln!(ctx.asm, "    .align {}", gvar.dec.align())?;
         writeln!(ctx.asm, "{}:", name)?;
         if gvar.init.len() == 0 {
             writeln!(ctx.asm, "    .zero {}", gvar.size)?;
@@ -89,12 +95,40 @@ pub fn code_gen(program: Program) -> Result<String, Error> {
         // プロローグ
         writeln!(ctx.asm, "    push rbp")?;
         writeln!(ctx.asm, "    mov rbp, rsp")?;
+        // 16バイトを超えるアライメントが必要なローカル変数がある場合は
+        // rbpをそのアライメントに揃え直し、元のrbpを[rbp]に保存しておく
+        let max_align = function.get_max_align();
+        let realign = max_align > 16;
+        if realign {
+            writeln!(ctx.asm, "    mov r11, rbp")?;
+            writeln!(ctx.asm, "    sub rbp, 8")?;
+            writeln!(ctx.asm, "    and rbp, -{}", max_align)?;
+            writeln!(ctx.asm, "    mov [rbp], r11")?;
+            writeln!(ctx.asm, "    mov rsp, rbp")?;
+        }
         writeln!(ctx.asm, "    sub rsp, {}", function.get_all_var_size())?;
 
+        // 戻り値の書き込み先のアドレスは隠れた第1引数として渡される
+        let reg_base = match &function.ret_ptr {
+            Some(ret_ptr) => {
+                writeln!(ctx.asm, "    mov [rbp-{}], rdi", ret_ptr.offset)?;
+                ctx.ret_ptr = Some(ret_ptr.offset);
+                1
+            }
+            None => {
+                ctx.ret_ptr = None;
+                0
+            }
+        };
+        let arg_num = function.def.param_num + reg_base;
+
         // save arg registers if variadic
         if let Some(_) = function.va_area {
-            let gp = function.def.param_num; //  `...`より前にある引数の数を入れる。
-                                             // def.param_numは
+            //  `...`より前にある引数のうちレジスタで渡されたものの数を入れる。
+            let gp = arg_num.min(ARGREG8.len());
+            // `...`に対応する引数のうちスタックで渡されたものは
+            // 名前付き引数のスタック渡しの分の後ろから始まる
+            let overflow_area = 16 + arg_num.saturating_sub(ARGREG8.len()) * 8;
 
             // let off = function
             //     .all_vars
@@ -109,7 +143,13 @@ pub fn code_gen(program: Program) -> Result<String, Error> {
             // fp_offset
             writeln!(ctx.asm, "    mov dword ptr [rbp-{}], 0", off - 4)?;
             // overflow_area
-            writeln!(ctx.asm, "    mov qword ptr [rbp-{}], 48", off - 8)?;
+            if realign {
+                writeln!(ctx.asm, "    mov rax, [rbp]")?;
+                writeln!(ctx.asm, "    add rax, {}", overflow_area)?;
+            } else {
+                writeln!(ctx.asm, "    lea rax, [rbp+{}]", overflow_area)?;
+            }
+            writeln!(ctx.asm, "    mov qword ptr [rbp-{}], rax", off - 8)?;
             // reg_save_area
             writeln!(ctx.asm, "    mov qword ptr [rbp-{}], rbp", off - 16)?;
             writeln!(
@@ -137,24 +177,30 @@ pub fn code_gen(program: Program) -> Result<String, Error> {
         }
 
         // 引数をローカル変数としてスタックに載せる
-        let mut offset = function
-            .all_vars
-            .as_ref()
-            .map(|v| v.borrow().offset)
-            .unwrap_or(0)
-            - function.get_param_size();
-
-        for i in 0..function.def.param_num {
+        for (i, offset) in function.param_offsets().into_iter().enumerate() {
             let type_kind = &function.def.params[i].type_kind;
-            offset += type_kind.size();
-            offset = base_types::align_to(offset, type_kind.align());
+            // 7個目以降の引数は呼び出し元のスタックに積まれている
+            // [rbp+16]から順に並んでいるのでr10を経由してコピーする
+            let i = i + reg_base;
+            let i = if i < ARGREG8.len() {
+                i
+            } else {
+                let stack_offset = 16 + (i - ARGREG8.len()) * 8;
+                if realign {
+                    writeln!(ctx.asm, "    mov r10, [rbp]")?;
+                    writeln!(ctx.asm, "    mov r10, [r10+{}]", stack_offset)?;
+                } else {
+                    writeln!(ctx.asm, "    mov r10, [rbp+{}]", stack_offset)?;
+                }
+                ARGREG8.len()
+            };
             writeln!(ctx.asm, "    mov rax, rbp")?;
             writeln!(ctx.asm, "    sub rax, {}", offset)?;
             let reg = match type_kind.size() {
-                1 => ARGREG1[i],
-                2 => ARGREG2[i],
-                4 => ARGREG4[i],
-                8 => ARGREG8[i],
+                1 => ARGREG1.get(i).unwrap_or(&"r10b"),
+                2 => ARGREG2.get(i).unwrap_or(&"r10w"),
+                4 => ARGREG4.get(i).unwrap_or(&"r10d"),
+                8 => ARGREG8.get(i).unwrap_or(&"r10"),
                 _ => unreachable!(),
             };
             writeln!(ctx.asm, "    mov [rax], {}", reg)?;
@@ -169,7 +215,11 @@ pub fn code_gen(program: Program) -> Result<String, Error> {
         // エピローグ
         // 最後の式の結果がRAXに残っているのでそれが返り値になる
         writeln!(ctx.asm, ".L.return.{}:", ctx.func_name)?;
-        writeln!(ctx.asm, "    mov rsp, rbp")?;
+        if realign {
+            writeln!(ctx.asm, "    mov rsp, [rbp]")?;
+        } else {
+            writeln!(ctx.asm, "    mov rsp, rbp")?;
+        }
         writeln!(ctx.asm, "    pop rbp")?;
         writeln!(ctx.asm, "    ret")?;
     }
@@ -177,6 +227,11 @@ pub fn code_gen(program: Program) -> Result<String, Error> {
 }
 
 pub fn gen(node: &Node, ctx: &mut Context) -> Result<(), Error> {
+    // 失敗したノードの位置をエラーに残す
+    gen_node(node, ctx).map_err(|err| err.at(&node.span))
+}
+
+fn gen_node(node: &Node, ctx: &mut Context) -> Result<(), Error> {
     match &node.kind {
         NodeKind::Num(x) => {
             #[cfg(debug_assertions)]
@@ -207,6 +262,12 @@ pub fn gen(node: &Node, ctx: &mut Context) -> Result<(), Error> {
             load(&node, ctx)?;
             return Ok(());
         }
+        NodeKind::FuncName(_) => {
+            #[cfg(debug_assertions)]
+            writeln!(ctx.asm, "# NodeKind::FuncName")?;
+            // 関数の値はそのアドレス
+            return gen_val(node, ctx);
+        }
         NodeKind::Member(_, member) => {
             #[cfg(debug_assertions)]
             writeln!(ctx.asm, "# NodeKind::Member")?;
@@ -286,6 +347,10 @@ pub fn gen(node: &Node, ctx: &mut Context) -> Result<(), Error> {
                 }
                 _ => unreachable!(),
             }
+            // 式の値は代入後の左辺の値
+            if let Ok(type_kind) = lhs.get_type() {
+                truncate(&type_kind, ctx)?;
+            }
             writeln!(ctx.asm, "    push rax")?;
             store(node, ctx)?;
             return Ok(());
@@ -295,7 +360,21 @@ pub fn gen(node: &Node, ctx: &mut Context) -> Result<(), Error> {
             writeln!(ctx.asm, "# NodeKind::Return")?;
             if let Some(lhs) = &node.lhs {
                 gen(&lhs, ctx)?;
-                writeln!(ctx.asm, "    pop rax")?;
+                if let Ok(TypeKind::Struct(_struct)) = lhs.get_type() {
+                    let size = _struct.borrow().get_size();
+                    writeln!(ctx.asm, "    pop rdi")?;
+                    if let Some(ret_ptr) = ctx.ret_ptr {
+                        // 呼び出し元の領域にコピーしてそのアドレスを返す
+                        writeln!(ctx.asm, "    mov rax, [rbp-{}]", ret_ptr)?;
+                        gen_struct_copy(size, ctx)?;
+                    } else {
+                        // 16byte以下の構造体はrax, rdxに詰めて返す
+                        load_struct_reg("rdx", "dl", 8, size.saturating_sub(8), ctx)?;
+                        load_struct_reg("rax", "al", 0, size.min(8), ctx)?;
+                    }
+                } else {
+                    writeln!(ctx.asm, "    pop rax")?;
+                }
             }
             writeln!(ctx.asm, "    jmp .L.return.{}", ctx.func_name)?;
             return Ok(());
@@ -486,11 +565,19 @@ pub fn gen(node: &Node, ctx: &mut Context) -> Result<(), Error> {
             let mut has_default = -1;
             for case in cases {
                 match case.kind {
-                    NodeKind::Case(num) => {
-                        writeln!(ctx.asm, "    cmp rax, {}", num)?;
+                    NodeKind::Case(low, high) if low == high => {
+                        writeln!(ctx.asm, "    cmp rax, {}", low)?;
                         writeln!(ctx.asm, "    je .L.case.{}", ctx.jump_label)?;
                         ctx.jump_label += 1;
                     }
+                    // low <= rax <= high を rax - low <= high - low の符号なし比較にする
+                    NodeKind::Case(low, high) => {
+                        writeln!(ctx.asm, "    mov rdi, rax")?;
+                        writeln!(ctx.asm, "    sub rdi, {}", low)?;
+                        writeln!(ctx.asm, "    cmp rdi, {}", high - low)?;
+                        writeln!(ctx.asm, "    jbe .L.case.{}", ctx.jump_label)?;
+                        ctx.jump_label += 1;
+                    }
                     NodeKind::DefaultCase => {
                         has_default = ctx.jump_label as isize;
                         ctx.jump_label += 1;
@@ -514,14 +601,14 @@ pub fn gen(node: &Node, ctx: &mut Context) -> Result<(), Error> {
             ctx.break_label = break_num;
             return Ok(());
         }
-        NodeKind::Case(_) | NodeKind::DefaultCase => {
+        NodeKind::Case(..) | NodeKind::DefaultCase => {
             writeln!(ctx.asm, ".L.case.{}:", ctx.case_label.0)?;
             ctx.case_label.0 += 1;
             let mut case_lable = ctx.case_label;
             let mut node = node;
             loop {
                 match node.lhs.as_ref().unwrap().kind {
-                    NodeKind::Case(_) | NodeKind::DefaultCase => {
+                    NodeKind::Case(..) | NodeKind::DefaultCase => {
                         writeln!(ctx.asm, ".L.case.{}:", ctx.case_label.0)?;
                         ctx.case_label.0 += 1;
                         case_lable = ctx.case_label;
@@ -548,34 +635,76 @@ pub fn gen(node: &Node, ctx: &mut Context) -> Result<(), Error> {
             let jlb_num = ctx.jump_label;
             ctx.jump_label += 1;
 
-            // printf 関数はalに浮動小数点数の引数の個数をいれる必要がある
-            // 今はないので決め打ちで0にする
-            writeln!(ctx.asm, "    mov al, 0")?;
+            // 構造体を返す関数の場合は戻り値を置く一時領域のoffset
+            let ret_buf = match node.lhs.as_deref().map(|lhs| &lhs.kind) {
+                Some(NodeKind::Lvar(lvar)) => Some(lvar.borrow().offset),
+                _ => None,
+            };
+            let reg_base = if func_prototype.has_ret_ptr() { 1 } else { 0 };
+            let callee = if node.rhs.is_some() {
+                "r10"
+            } else {
+                func_prototype.ident.name.as_str()
+            };
 
-            for i in args {
+            // 7個目以降の引数はスタックに積んで渡す。
+            // 7個目の引数が一番下(rspの位置)に来るように後ろから評価する
+            let stack_num = (args.len() + reg_base).saturating_sub(ARGREG8.len());
+            for i in args.iter().rev() {
                 gen(i, ctx)?;
             }
-            for i in (0..args.len()).rev() {
-                writeln!(ctx.asm, "    pop {}", ARGREG8[i])?;
+            // 関数ポインタを通して呼び出すときは、呼び出す関数のアドレスをr10に置く
+            if let Some(callee) = &node.rhs {
+                gen(callee, ctx)?;
+                writeln!(ctx.asm, "    pop r10")?;
+            }
+            for reg in ARGREG8.iter().skip(reg_base).take(args.len()) {
+                writeln!(ctx.asm, "    pop {}", reg)?;
+            }
+            if let (1, Some(ret_buf)) = (reg_base, ret_buf) {
+                writeln!(ctx.asm, "    lea rdi, [rbp-{}]", ret_buf)?;
             }
 
             // 16の倍数にしてcall
+            // ずれている場合はスタック渡しの引数を8byte下にずらす
             writeln!(ctx.asm, "    mov rax, rsp")?;
             writeln!(ctx.asm, "    and rax, 15")?;
             writeln!(ctx.asm, "    jnz .L.call.{}", jlb_num)?;
+            // printf 関数はalに浮動小数点数の引数の個数をいれる必要がある
+            // 今はないので決め打ちで0にする
             writeln!(ctx.asm, "    mov rax, 0")?;
-            writeln!(ctx.asm, "    call {}", func_prototype.ident.name)?;
+            writeln!(ctx.asm, "    call {}", callee)?;
+            if stack_num > 0 {
+                writeln!(ctx.asm, "    add rsp, {}", stack_num * 8)?;
+            }
             writeln!(ctx.asm, "    jmp .L.end.{}", jlb_num)?;
             writeln!(ctx.asm, ".L.call.{}:", jlb_num)?;
             writeln!(ctx.asm, "    sub rsp, 8")?;
+            for i in 0..stack_num {
+                writeln!(ctx.asm, "    mov rax, [rsp+{}]", i * 8 + 8)?;
+                writeln!(ctx.asm, "    mov [rsp+{}], rax", i * 8)?;
+            }
             writeln!(ctx.asm, "    mov rax, 0")?;
-            writeln!(ctx.asm, "    call {}", func_prototype.ident.name)?;
-            writeln!(ctx.asm, "    add rsp, 8")?;
+            writeln!(ctx.asm, "    call {}", callee)?;
+            writeln!(ctx.asm, "    add rsp, {}", stack_num * 8 + 8)?;
             writeln!(ctx.asm, ".L.end.{}:", jlb_num)?;
-            writeln!(ctx.asm, "    push rax")?;
 
-            if func_prototype.as_ref().type_kind != TypeKind::Void {
-                cast(&func_prototype.type_kind, ctx)?;
+            match (&func_prototype.type_kind, ret_buf) {
+                (TypeKind::Struct(_struct), Some(ret_buf)) => {
+                    // 構造体の値はアドレスで扱うので、一時領域のアドレスを積む
+                    writeln!(ctx.asm, "    lea rdi, [rbp-{}]", ret_buf)?;
+                    if reg_base == 0 {
+                        let size = _struct.borrow().get_size();
+                        store_struct_reg("rax", "al", 0, size.min(8), ctx)?;
+                        store_struct_reg("rdx", "dl", 8, size.saturating_sub(8), ctx)?;
+                    }
+                    writeln!(ctx.asm, "    push rdi")?;
+                }
+                (TypeKind::Void, _) => writeln!(ctx.asm, "    push rax")?,
+                (type_kind, _) => {
+                    writeln!(ctx.asm, "    push rax")?;
+                    cast(type_kind, ctx)?;
+                }
             }
             return Ok(());
         }
@@ -583,7 +712,12 @@ pub fn gen(node: &Node, ctx: &mut Context) -> Result<(), Error> {
             #[cfg(debug_assertions)]
             writeln!(ctx.asm, "# NodeKind::Addr")?;
             if let Some(lhs) = &node.lhs {
-                gen_val(&lhs, ctx)?;
+                // 可変長配列の変数には領域のアドレスが入っている
+                if let Ok(TypeKind::Vla(_, _)) = lhs.get_type() {
+                    gen(&lhs, ctx)?;
+                } else {
+                    gen_val(&lhs, ctx)?;
+                }
             } else {
                 return Err(Error::not_found());
             }
@@ -594,7 +728,10 @@ pub fn gen(node: &Node, ctx: &mut Context) -> Result<(), Error> {
             writeln!(ctx.asm, "# NodeKind::Deref")?;
             if let Some(lhs) = &node.lhs {
                 gen(&lhs, ctx)?;
-                if let Ok(TypeKind::Array(_, _, _)) = node.get_type() {
+                if let Ok(TypeKind::Array(_, _, _))
+                | Ok(TypeKind::Vla(_, _))
+                | Ok(TypeKind::Func(_, _, _)) = node.get_type()
+                {
                     return Ok(());
                 }
                 load(node, ctx)?;
@@ -625,7 +762,43 @@ pub fn gen(node: &Node, ctx: &mut Context) -> Result<(), Error> {
             }
             return Ok(());
         }
-        NodeKind::Null => return Ok(()),
+        NodeKind::VlaAlloc(sp) => {
+            #[cfg(debug_assertions)]
+            writeln!(ctx.asm, "# NodeKind::VlaAlloc")?;
+            let lhs = node.lhs.as_ref().ok_or(Error::not_found())?;
+            let rhs = node.rhs.as_ref().ok_or(Error::not_found())?;
+            let align = match lhs.get_type() {
+                Ok(TypeKind::Vla(base, _)) => base.borrow().align().max(16),
+                _ => 16,
+            };
+            writeln!(ctx.asm, "    mov [rbp-{}], rsp", sp.borrow().offset)?;
+            gen(rhs, ctx)?;
+            writeln!(ctx.asm, "    pop rax")?;
+            writeln!(ctx.asm, "    sub rsp, rax")?;
+            writeln!(ctx.asm, "    and rsp, -{}", align)?;
+            gen_val(lhs, ctx)?;
+            writeln!(ctx.asm, "    pop rax")?;
+            writeln!(ctx.asm, "    mov [rax], rsp")?;
+            return Ok(());
+        }
+        NodeKind::VlaFree(sp) => {
+            #[cfg(debug_assertions)]
+            writeln!(ctx.asm, "# NodeKind::VlaFree")?;
+            if let Some(lhs) = &node.lhs {
+                gen(lhs, ctx)?;
+            }
+            if let Some(sp) = &*sp.borrow() {
+                if node.lhs.is_some() {
+                    writeln!(ctx.asm, "    pop rax")?;
+                }
+                writeln!(ctx.asm, "    mov rsp, [rbp-{}]", sp.borrow().offset)?;
+                if node.lhs.is_some() {
+                    writeln!(ctx.asm, "    push rax")?;
+                }
+            }
+            return Ok(());
+        }
+        NodeKind::Null | NodeKind::Fallthrough => return Ok(()),
         NodeKind::Cast(type_kind) => {
             #[cfg(debug_assertions)]
             writeln!(ctx.asm, "# cast")?;
@@ -638,6 +811,7 @@ pub fn gen(node: &Node, ctx: &mut Context) -> Result<(), Error> {
             writeln!(ctx.asm, "# comma")?;
             gen(node.lhs.as_ref().unwrap(), ctx)?;
             gen(node.rhs.as_ref().unwrap(), ctx)?;
+            return Ok(());
         }
         NodeKind::PreInc => {
             #[cfg(debug_assertions)]
@@ -789,6 +963,22 @@ pub fn gen(node: &Node, ctx: &mut Context) -> Result<(), Error> {
             writeln!(ctx.asm, "# Sub")?;
             ptr_op(node, ctx)?;
             writeln!(ctx.asm, "    sub rax, rdi")?;
+            // ポインタどうしの差は要素の大きさで割る
+            let lhs = node.lhs.as_ref().and_then(|lhs| pointee_size(lhs));
+            let rhs = node.rhs.as_ref().and_then(|rhs| pointee_size(rhs));
+            if let (Some(size), Some(_)) = (lhs, rhs) {
+                match vla_pointee_size(node.lhs.as_ref().unwrap()) {
+                    Some(size) => {
+                        writeln!(ctx.asm, "    push rax")?;
+                        gen(&size, ctx)?;
+                        writeln!(ctx.asm, "    pop rdi")?;
+                        writeln!(ctx.asm, "    pop rax")?;
+                    }
+                    None => writeln!(ctx.asm, "    mov rdi, {}", size.max(1))?,
+                }
+                writeln!(ctx.asm, "    cqo")?;
+                writeln!(ctx.asm, "    idiv rdi")?;
+            }
         }
         NodeKind::Mul => {
             #[cfg(debug_assertions)]
@@ -849,6 +1039,10 @@ pub fn gen(node: &Node, ctx: &mut Context) -> Result<(), Error> {
         }
         _ => (),
     }
+    // 64bitで計算した結果を演算の型に合わせる
+    if let Ok(type_kind) = node.get_type() {
+        truncate(&type_kind, ctx)?;
+    }
 
     writeln!(ctx.asm, "    push rax")?;
     Ok(())
@@ -858,6 +1052,10 @@ fn gen_val(node: &Node, ctx: &mut Context) -> Result<(), Error> {
     #[cfg(debug_assertions)]
     writeln!(ctx.asm, "# gen val")?;
     if !is_left_value(node) {
+        // 構造体の値は常にアドレスとして積まれている
+        if let Ok(TypeKind::Struct(_)) = node.get_type() {
+            return gen(node, ctx);
+        }
         return Err(Error::not_lvar());
     }
 
@@ -888,6 +1086,17 @@ fn gen_val(node: &Node, ctx: &mut Context) -> Result<(), Error> {
             writeln!(ctx.asm, "    push rax")?;
             Ok(())
         }
+        NodeKind::FuncName(func_prototype) => {
+            #[cfg(debug_assertions)]
+            writeln!(ctx.asm, "# func name")?;
+            writeln!(
+                ctx.asm,
+                "    mov rax, OFFSET FLAT:{}",
+                func_prototype.ident.name
+            )?;
+            writeln!(ctx.asm, "    push rax")?;
+            Ok(())
+        }
         NodeKind::Deref => {
             #[cfg(debug_assertions)]
             writeln!(ctx.asm, "# deref")?;
@@ -918,7 +1127,7 @@ fn gen_val(node: &Node, ctx: &mut Context) -> Result<(), Error> {
 fn is_left_value(node: &Node) -> bool {
     use NodeKind::*;
     match node.kind {
-        Lvar(_) | Gvar(_) | Deref | Member(_, _) => true,
+        Lvar(_) | Gvar(_) | FuncName(_) | Deref | Member(_, _) => true,
         _ => false,
     }
 }
@@ -929,6 +1138,8 @@ fn load(node: &Node, ctx: &mut Context) -> Result<(), Error> {
     let mut word = "mov rax, [rax]";
     if let Ok(type_kind) = node.get_type() {
         match type_kind {
+            // 構造体はアドレスのまま扱う
+            TypeKind::Struct(_) => return Ok(()),
             TypeKind::Array(_, type_kind, _) => {
                 word = gen_load_asm(type_kind.borrow().size(), true).unwrap_or(word)
             }
@@ -1001,10 +1212,7 @@ fn store(node: &Node, ctx: &mut Context) -> Result<(), Error> {
                 TypeKind::Struct(_struct) => {
                     writeln!(ctx.asm, "    pop rdi")?;
                     writeln!(ctx.asm, "    pop rax")?;
-                    for i in 0.._struct.borrow().get_size() {
-                        writeln!(ctx.asm, "    mov r8b, [rdi+{}]", i)?;
-                        writeln!(ctx.asm, "    mov [rax+{}], r8b", i)?;
-                    }
+                    gen_struct_copy(_struct.borrow().get_size(), ctx)?;
                     writeln!(ctx.asm, "    push rax")?;
                     return Ok(());
                 }
@@ -1015,6 +1223,84 @@ fn store(node: &Node, ctx: &mut Context) -> Result<(), Error> {
     _store(node, ctx)
 }
 
+/// rdiが指す構造体をraxが指す先にsize byteコピーする
+/// コピーの後もraxの値は保たれる
+fn gen_struct_copy(size: u64, ctx: &mut Context) -> Result<(), Error> {
+    #[cfg(debug_assertions)]
+    writeln!(ctx.asm, "# struct copy")?;
+    if size > MAX_INLINE_COPY_SIZE {
+        let jlb_num = ctx.jump_label;
+        ctx.jump_label += 1;
+        writeln!(ctx.asm, "    push rax")?;
+        writeln!(ctx.asm, "    mov rsi, rdi")?;
+        writeln!(ctx.asm, "    mov rdi, rax")?;
+        writeln!(ctx.asm, "    mov rdx, {}", size)?;
+        // 16の倍数にしてcall
+        writeln!(ctx.asm, "    mov rax, rsp")?;
+        writeln!(ctx.asm, "    and rax, 15")?;
+        writeln!(ctx.asm, "    jnz .L.memcpy.{}", jlb_num)?;
+        writeln!(ctx.asm, "    call memcpy")?;
+        writeln!(ctx.asm, "    jmp .L.memcpy.end.{}", jlb_num)?;
+        writeln!(ctx.asm, ".L.memcpy.{}:", jlb_num)?;
+        writeln!(ctx.asm, "    sub rsp, 8")?;
+        writeln!(ctx.asm, "    call memcpy")?;
+        writeln!(ctx.asm, "    add rsp, 8")?;
+        writeln!(ctx.asm, ".L.memcpy.end.{}:", jlb_num)?;
+        writeln!(ctx.asm, "    pop rax")?;
+        return Ok(());
+    }
+
+    let mut offset = 0;
+    for (chunk, reg) in [(8, "r8"), (4, "r8d"), (2, "r8w"), (1, "r8b")] {
+        while size - offset >= chunk {
+            writeln!(ctx.asm, "    mov {}, [rdi+{}]", reg, offset)?;
+            writeln!(ctx.asm, "    mov [rax+{}], {}", offset, reg)?;
+            offset += chunk;
+        }
+    }
+    Ok(())
+}
+
+/// rdiが指す構造体の[offset, offset+size)をレジスタに詰める
+/// 構造体の外を読まないように1byteずつ読む
+fn load_struct_reg(
+    reg: &str,
+    low: &str,
+    offset: u64,
+    size: u64,
+    ctx: &mut Context,
+) -> Result<(), Error> {
+    if size == 8 {
+        writeln!(ctx.asm, "    mov {}, [rdi+{}]", reg, offset)?;
+        return Ok(());
+    }
+    writeln!(ctx.asm, "    mov {}, 0", reg)?;
+    for i in (0..size).rev() {
+        writeln!(ctx.asm, "    shl {}, 8", reg)?;
+        writeln!(ctx.asm, "    mov {}, [rdi+{}]", low, offset + i)?;
+    }
+    Ok(())
+}
+
+/// レジスタに詰められた構造体の一部をrdiが指す先の[offset, offset+size)に書き込む
+fn store_struct_reg(
+    reg: &str,
+    low: &str,
+    offset: u64,
+    size: u64,
+    ctx: &mut Context,
+) -> Result<(), Error> {
+    if size == 8 {
+        writeln!(ctx.asm, "    mov [rdi+{}], {}", offset, reg)?;
+        return Ok(());
+    }
+    for i in 0..size {
+        writeln!(ctx.asm, "    mov [rdi+{}], {}", offset + i, low)?;
+        writeln!(ctx.asm, "    shr {}, 8", reg)?;
+    }
+    Ok(())
+}
+
 fn gen_store_asm(size: u64) -> Option<&'static str> {
     match size {
         1 => Some("mov [rax], dil"),
@@ -1028,37 +1314,84 @@ fn gen_store_asm(size: u64) -> Option<&'static str> {
 fn ptr_op(node: &Node, ctx: &mut Context) -> Result<(), Error> {
     #[cfg(debug_assertions)]
     writeln!(ctx.asm, "# ptr op")?;
-    if let Some(ref lhs) = node.lhs {
-        if let Ok(type_kind) = &lhs.get_type() {
-            match type_kind {
-                TypeKind::Ptr(ptr) | TypeKind::Array(_, ptr, _) => {
-                    writeln!(ctx.asm, "    imul rdi, {}", ptr.borrow().size())?;
-                }
-                _ => (),
+    let lhs = node.lhs.as_ref().and_then(|lhs| pointee_size(lhs));
+    let rhs = node.rhs.as_ref().and_then(|rhs| pointee_size(rhs));
+    match (lhs, rhs) {
+        (Some(size), None) => scale(node.lhs.as_ref().unwrap(), size, "rdi", ctx)?,
+        // `1 + p`は整数の方を要素の大きさ倍する
+        (None, Some(size)) => scale(node.rhs.as_ref().unwrap(), size, "rax", ctx)?,
+        _ => (),
+    }
+    Ok(())
+}
+
+/// `reg`を`ptr`の要素の大きさ倍する。要素が可変長配列なら大きさを実行時に読む
+fn scale(ptr: &Node, size: u64, reg: &str, ctx: &mut Context) -> Result<(), Error> {
+    let size_node = match vla_pointee_size(ptr) {
+        Some(size_node) => size_node,
+        None => {
+            writeln!(ctx.asm, "    imul {}, {}", reg, size)?;
+            return Ok(());
+        }
+    };
+    writeln!(ctx.asm, "    push rax")?;
+    writeln!(ctx.asm, "    push rdi")?;
+    gen(&size_node, ctx)?;
+    writeln!(ctx.asm, "    pop rcx")?;
+    writeln!(ctx.asm, "    pop rdi")?;
+    writeln!(ctx.asm, "    pop rax")?;
+    writeln!(ctx.asm, "    imul {}, rcx", reg)?;
+    Ok(())
+}
+
+/// 要素が可変長配列なら、その大きさを求める式を返す
+fn vla_pointee_size(node: &Node) -> Option<Node> {
+    match node.get_type() {
+        Ok(TypeKind::Ptr(ptr)) | Ok(TypeKind::Array(_, ptr, _)) | Ok(TypeKind::Vla(ptr, _)) => {
+            match &*ptr.borrow() {
+                TypeKind::Vla(_, size) => Some(size.as_ref().clone()),
+                _ => None,
             }
         }
+        _ => None,
     }
-    Ok(())
 }
 
-fn cast(type_kind: &TypeKind, ctx: &mut Context) -> Result<(), Error> {
-    use TypeKind::*;
+/// ポインタか配列ならその要素の大きさを返す
+fn pointee_size(node: &Node) -> Option<u64> {
+    match node.get_type() {
+        Ok(TypeKind::Ptr(ptr)) | Ok(TypeKind::Array(_, ptr, _)) | Ok(TypeKind::Vla(ptr, _)) => {
+            Some(ptr.borrow().size())
+        }
+        _ => None,
+    }
+}
 
+fn cast(type_kind: &TypeKind, ctx: &mut Context) -> Result<(), Error> {
     #[cfg(debug_assertions)]
     writeln!(ctx.asm, "# cast")?;
     writeln!(ctx.asm, "    pop rax")?;
-    if type_kind == &_Bool {
+    truncate(type_kind, ctx)?;
+    writeln!(ctx.asm, "    push rax")?;
+    Ok(())
+}
+
+/// raxの値を`type_kind`の大きさに切り詰めて符号拡張する
+/// 64bitで計算した結果を型に合わせるのに使う
+fn truncate(type_kind: &TypeKind, ctx: &mut Context) -> Result<(), Error> {
+    if type_kind == &TypeKind::_Bool {
         writeln!(ctx.asm, "    cmp rax, 0")?;
         writeln!(ctx.asm, "    setne al")?;
     }
-
+    if !type_kind.is_integer() {
+        return Ok(());
+    }
     match type_kind.size() {
         1 => writeln!(ctx.asm, "    movsx rax, al")?,
         2 => writeln!(ctx.asm, "    movsx rax, ax")?,
         4 => writeln!(ctx.asm, "    movsxd rax, eax")?,
         _ => (),
     }
-    writeln!(ctx.asm, "    push rax")?;
     Ok(())
 }
 
@@ -1070,6 +1403,9 @@ pub fn inc(node: &Node, ctx: &mut Context) -> Result<(), Error> {
     writeln!(ctx.asm, "    mov rdi, 1")?;
     ptr_op(node, ctx)?;
     writeln!(ctx.asm, "    add rax, rdi")?;
+    if let Ok(type_kind) = node.get_type() {
+        truncate(&type_kind, ctx)?;
+    }
     writeln!(ctx.asm, "    pop rdi")?;
     writeln!(ctx.asm, "    push rax")?;
     Ok(())
@@ -1083,6 +1419,9 @@ pub fn dec(node: &Node, ctx: &mut Context) -> Result<(), Error> {
     writeln!(ctx.asm, "    mov rdi, 1")?;
     ptr_op(node, ctx)?;
     writeln!(ctx.asm, "    sub rax, rdi")?;
+    if let Ok(type_kind) = node.get_type() {
+        truncate(&type_kind, ctx)?;
+    }
     writeln!(ctx.asm, "    pop rdi")?;
     writeln!(ctx.asm, "    push rax")?;
     Ok(())
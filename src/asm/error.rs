@@ -1,4 +1,6 @@
 use self::ErrorKind::*;
+use crate::render::{Diagnostic, Level, Location};
+use crate::token::Span;
 use std::fmt;
 
 #[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Debug)]
@@ -17,26 +19,40 @@ pub enum ErrorKind {
 pub struct Error {
     kind: ErrorKind,
     msg: Option<String>,
+    /// エラーになったノードのソース上の位置
+    span: Span,
 }
 
 impl Error {
+    /// エラーになったノードの位置を記録する
+    /// 内側のノードで記録した位置があればそちらを残す
+    pub fn at(mut self, span: &Span) -> Self {
+        if self.span.is_dummy() {
+            self.span = span.clone();
+        }
+        self
+    }
+
     // pub fn not_lvar(input: impl Into<String>, token: Token) -> Error {
     pub fn not_lvar() -> Self {
         Self {
             kind: NoLVar,
             msg: None,
+            span: Span::default(),
         }
     }
     pub fn not_gvar() -> Self {
         Self {
             kind: NoGvar,
             msg: None,
+            span: Span::default(),
         }
     }
     pub fn not_found() -> Self {
         Self {
             kind: NotFound,
             msg: None,
+            span: Span::default(),
         }
     }
 
@@ -44,6 +60,7 @@ impl Error {
         Self {
             kind: StrayBreak,
             msg: None,
+            span: Span::default(),
         }
     }
 
@@ -51,6 +68,7 @@ impl Error {
         Self {
             kind: StrayContinue,
             msg: None,
+            span: Span::default(),
         }
     }
 
@@ -58,6 +76,7 @@ impl Error {
         Self {
             kind: UnknownSize,
             msg: None,
+            span: Span::default(),
         }
     }
 
@@ -65,6 +84,7 @@ impl Error {
         Self {
             kind: Todo,
             msg: None,
+            span: Span::default(),
         }
     }
 }
@@ -85,16 +105,21 @@ impl<'a> fmt::Display for Error {
 }
 
 fn err_format(err: &Error, f: &mut fmt::Formatter) -> fmt::Result {
-    match &err.kind {
-        NoLVar => writeln!(f, "Left Value is not substitutable"),
-        NoGvar => writeln!(f, "not global value"),
-        NotFound => writeln!(f, "Node not found"),
-        StrayBreak => writeln!(f, "stray break"),
-        StrayContinue => writeln!(f, "stray continue"),
-        UnknownSize => writeln!(f, "cannot get size"),
-        WriteError(string) => writeln!(f, "{}", string),
-        Todo => writeln!(f, "todo"),
+    let msg = match &err.kind {
+        NoLVar => "Left Value is not substitutable",
+        NoGvar => "not global value",
+        NotFound => "Node not found",
+        StrayBreak => "stray break",
+        StrayContinue => "stray continue",
+        UnknownSize => "cannot get size",
+        WriteError(string) => string,
+        Todo => "todo",
+    };
+    if err.span.is_dummy() {
+        return write!(f, "error: {}", msg);
     }
+    let loc = Location::from_span(&err.span);
+    write!(f, "{}", Diagnostic::new(Level::Error, &loc, msg))
 }
 
 impl From<std::fmt::Error> for Error {
@@ -102,6 +127,7 @@ impl From<std::fmt::Error> for Error {
         Self {
             kind: WriteError(format!("{}", error)),
             msg: None,
+            span: Span::default(),
         }
     }
 }
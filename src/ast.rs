@@ -1,11 +1,19 @@
 pub mod ast;
+pub mod cfg;
+pub mod diagnostics;
 pub mod error;
+pub mod flow;
 pub mod types;
+pub mod typing;
+pub mod uninit;
+pub mod unused;
 pub mod util;
 
-pub use ast::program;
+pub use ast::{program, program_with_context};
+pub use diagnostics::{Diagnostics, Warning};
 pub use error::Error;
 pub use types::{
     Context, Declaration, Designator, FuncPrototype, FuncPrototypeMp, Function, GlobalContext,
-    Gvar, GvarMp, Ident, Initializer, LocalContext, Lvar, Node, NodeKind, Program, Scope, Var,
+    Gvar, GvarMp, Ident, InitTree, Initializer, LocalContext, Lvar, Node, NodeKind, Program, Scope,
+    UsageContext, Var, VlaContext,
 };
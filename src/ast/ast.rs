@@ -1,11 +1,13 @@
-use super::error::Error;
+use super::error::{Error, Warn};
+use super::typing::add_conversions;
 use super::util::*;
 use super::NodeKind;
+use super::{flow, unused};
 use super::{
-    Context, Declaration, Designator, FuncPrototype, Function, Gvar, Ident, Initializer,
-    LocalContext, Lvar, Node, Program, Var,
+    Context, Declaration, Designator, FuncPrototype, Function, Gvar, Ident, InitTree, Initializer,
+    LocalContext, Lvar, Node, Program, Var, VlaContext,
 };
-use crate::token::{Block, KeyWord, Operator, TokenKind, TokenStream};
+use crate::token::{Block, KeyWord, Operator, Span, TokenKind, TokenPos, TokenStream};
 use crate::{
     base_types::{self, Enum, Member, Struct, TagTypeKind, TypeKind},
     token::tokenize,
@@ -13,110 +15,195 @@ use crate::{
 use std::rc::Rc;
 use std::{cell::RefCell, cmp::min};
 
-// program         = (function | declaration ("=" initialize)? ";" | func-prototype )*
-pub fn program(iter: &mut TokenStream) -> Result<Program, Error> {
+// program         = (function | type-specifier init-declarator-list? ";" | static-assert)*
+// init-declarator-list = init-declarator ("," init-declarator)*
+// init-declarator = declarator type-suffix ("=" initializer)?
+pub fn program(iter: &mut TokenStream) -> Result<Program, Vec<Error>> {
+    program_with_context(iter, Context::new())
+}
+
+/// `ctx`の設定で解析する
+/// エラーがあっても宣言や文の区切りから解析を続け、見つけたエラーを警告と一緒に出した順ですべて返す
+/// エラーがなければ、警告は`ctx.diagnostics`に残す
+pub fn program_with_context(iter: &mut TokenStream, ctx: Context) -> Result<Program, Vec<Error>> {
     let mut program = Program::new();
-    let mut ctx = &mut program.ctx;
+    program.ctx = ctx;
+    program.ctx.diag.borrow_mut().add_pragmas(&iter.pragmas);
     while iter.peek() != None {
-        // to distinguish global variable and function
-        // read base type and ident
-        // then peek next token.
-        // if next token is ; or [], it is global variable
-        // if next token is ( , it is function
-
-        let (type_kind, (is_typedef, is_static, is_extern)) = type_specifier(iter, ctx)?;
-        let type_kind = Rc::new(RefCell::new(type_kind.clone()));
-        let mut ident = Ident::new_anonymous();
-        let type_kind =
-            if let Some(type_kind) = consume_declarator(iter, ctx, type_kind.clone(), &mut ident) {
-                type_kind
-            } else {
-                type_kind
-            };
+        let begin = iter.idx;
+        let sc = program.ctx.s.clone();
+        if let Err(err) = external_declaration(iter, &mut program) {
+            program.ctx.s = sc;
+            if let Err(err) = recover(iter, &mut program.ctx, begin, err) {
+                program.ctx.diagnostics.push(err);
+                break;
+            }
+        }
+    }
+    unused::check_program(&mut program);
+    if program.ctx.error_count() > 0 {
+        return Err(std::mem::take(&mut program.ctx.diagnostics));
+    }
+    Ok(program)
+}
 
-        if let Some(next) = iter.next() {
-            match &next.kind {
-                // function
-                TokenKind::Reserved(Operator::LParen) => {
-                    let mut fn_params = Vec::new();
-                    let mut is_variadic = false;
-                    if !consume(iter, Operator::RParen) {
-                        let tmp = params(iter, ctx)?;
-                        fn_params = tmp.0;
-                        is_variadic = tmp.1;
-                    }
+/// 関数定義か、ファイルスコープの宣言を1つ読む
+fn external_declaration(iter: &mut TokenStream, program: &mut Program) -> Result<(), Error> {
+    let ctx = &mut program.ctx;
+    if is_next_keyword(iter, KeyWord::StaticAssert) {
+        return static_assert(iter, ctx);
+    }
 
-                    let func_prototype = FuncPrototype::new(
-                        type_kind.replace(TypeKind::Int),
-                        ident,
-                        fn_params,
-                        is_static,
-                    );
-                    // int test();
-                    // int test(){}
-                    // を認めるためにチェックを止める
-
-                    // let checked_func_prototype = Rc::new(check_func_prototype(
-                    //     iter,
-                    //     &ctx.g.func_prototype_mp,
-                    //     func_prototype,
-                    // )?);
-                    let checked_func_prototype = Rc::new(func_prototype);
-                    ctx.g.func_prototype_mp.insert(
-                        checked_func_prototype.ident.name.clone(),
-                        checked_func_prototype.clone(),
-                    );
-                    if consume_semi(iter) {
-                        continue;
-                    }
-                    let sc = ctx.s.enter();
-                    let func = function(iter, checked_func_prototype, &mut ctx, is_variadic)?;
+    // to distinguish global variable and function
+    // read base type and ident
+    // then peek next token.
+    // if next token is ; or [], it is global variable
+    // if next token is ( , it is function
 
-                    ctx.s.leave(sc);
-                    program.functions.push(func);
+    let begin = iter.idx;
+    let (base_type, storage) = type_specifier(iter, ctx)?;
+    let (is_typedef, is_static, _, _) = storage;
+    let mut first = true;
+    loop {
+        let dec_begin = iter.idx;
+        let type_kind = Rc::new(RefCell::new(base_type.clone()));
+        let mut ident = Ident::new_anonymous();
+        let type_kind = declarator(iter, ctx, type_kind, &mut ident)?;
+        let span = iter.span_from(dec_begin);
+        let func_prototype = if is_typedef {
+            None
+        } else {
+            FuncPrototype::from_type(&type_kind.borrow(), ident.clone(), is_static).map(|mut f| {
+                f.span = span.clone();
+                f
+            })
+        };
+
+        // function
+        if let Some(func_prototype) = func_prototype {
+            let is_variadic = func_prototype.is_variadic;
+            // int test();
+            // int test(){}
+            // のように型が一致する再宣言は認める
+            let checked_func_prototype = Rc::new(check_func_prototype(
+                iter,
+                &ctx.g.func_prototype_mp,
+                func_prototype.clone(),
+            )?);
+            ctx.g.func_prototype_mp.insert(
+                checked_func_prototype.ident.name.clone(),
+                checked_func_prototype,
+            );
+            // 関数定義は宣言子が1つだけのときに限る
+            if first && is_next_block(iter, Block::LParen) {
+                if let Some(prev) = program
+                    .functions
+                    .iter()
+                    .find(|f| f.def.ident == func_prototype.ident)
+                {
+                    let err = Error::redefinition(
+                        iter.filepath.clone(),
+                        iter.input.clone(),
+                        span.pos(),
+                        func_prototype.ident,
+                    );
+                    return Err(with_previous(err, &prev.span, "definition"));
                 }
-                x => {
-                    let mut init = Vec::new();
-                    if x == &TokenKind::Reserved(Operator::Assign) {
-                        gvar_initializer(iter, ctx, &mut init, type_kind.clone())?;
-                        expect_semi(iter)?;
-                    } else if x != &TokenKind::SemiColon {
-                        return Err(Error::unexpected_token(
-                            iter.filepath.clone(),
-                            iter.input.clone(),
-                            &next,
-                            TokenKind::SemiColon,
-                        ));
-                    }
-                    let mut dec = Declaration::new(type_kind.borrow().clone(), ident);
-                    dec.is_typedef = is_typedef;
-                    dec.is_static = is_static;
-                    dec.is_extern = is_extern;
-                    if is_typedef {
-                        let _result = ctx.s.insert_t(
-                            Rc::new(dec.ident.clone().get_typedef_ident()),
-                            TagTypeKind::Typedef(Rc::new(dec.clone())),
-                        );
-                        // if let Some(_) = result {
-                        //     return Err(Error::re_declare(
-                        //         iter.filepath.clone(),
-                        //         iter.input.clone(),
-                        //         dec.ident.clone(),
-                        //         iter.pos,
-                        //         None,
-                        //     ));
-                        // }
-                        continue;
-                    }
-                    if dec.ident.is_anonymous() {
-                        continue;
-                    }
-                    ctx.insert_g(Rc::new(check_g_var(iter, &ctx.g.gvar_mp, dec, init)?));
+                let sc = ctx.s.enter();
+                let recovered = ctx.recovered_errors;
+                let mut func = function(iter, Rc::new(func_prototype), ctx, is_variadic)?;
+                func.span = iter.span_from(begin);
+                // エラーのあった関数は文が欠けているので、後の解析は誤った警告を出す
+                if ctx.recovered_errors == recovered {
+                    unused::check_function(&func, ctx);
+                    flow::check_function(&mut func, ctx);
+                } else {
+                    ctx.usage.static_locals.clear();
                 }
+
+                ctx.s.leave(sc);
+                program.functions.push(func);
+                break;
+            }
+        } else if let Err(err) =
+            global_declaration(iter, ctx, type_kind, ident.clone(), span, storage)
+        {
+            if !is_typedef && !ident.is_anonymous() {
+                ctx.failed_decls.push(ident);
             }
+            return Err(err);
+        }
+
+        first = false;
+        if !consume_comma(iter) {
+            expect_semi(iter)?;
+            break;
         }
+        expect_declarator(iter)?;
     }
-    Ok(program)
+    Ok(())
+}
+
+/// ファイルスコープの変数宣言子を1つ処理する
+/// `;`や`,`は呼び出し側で読む
+fn global_declaration(
+    iter: &mut TokenStream,
+    ctx: &mut Context,
+    type_kind: Rc<RefCell<TypeKind>>,
+    ident: Ident,
+    span: Span,
+    (is_typedef, is_static, is_extern, alignas): StorageClass,
+) -> Result<(), Error> {
+    let mut init = Vec::new();
+    if consume(iter, Operator::Assign) {
+        gvar_initializer(iter, ctx, &mut init, type_kind.clone())?;
+    }
+    let mut dec = Declaration::new(type_kind.borrow().clone(), ident);
+    dec.span = span;
+    dec.is_typedef = is_typedef;
+    dec.is_static = is_static;
+    dec.is_extern = is_extern;
+    dec.alignas = alignas;
+    if let TypeKind::Vla(_, _) = dec.type_kind {
+        return Err(Error::invalid_vla(
+            iter.filepath.clone(),
+            iter.input.clone(),
+            iter.pos,
+            "variable length array declared at file scope",
+        ));
+    }
+    // 要素数のない配列は仮定義として扱う
+    if !is_typedef
+        && !is_extern
+        && !dec.ident.is_anonymous()
+        && !matches!(dec.type_kind, TypeKind::Array(_, _, false))
+    {
+        expect_complete_object(iter, ctx, &dec)?;
+    }
+    if is_typedef {
+        let _result = ctx.s.insert_t(
+            Rc::new(dec.ident.clone().get_typedef_ident()),
+            TagTypeKind::Typedef(Rc::new(dec.clone())),
+        );
+        // if let Some(_) = result {
+        //     return Err(Error::re_declare(
+        //         iter.filepath.clone(),
+        //         iter.input.clone(),
+        //         dec.ident.clone(),
+        //         iter.pos,
+        //         None,
+        //     ));
+        // }
+        return Ok(());
+    }
+    if dec.ident.is_anonymous() {
+        return Ok(());
+    }
+    if is_static {
+        ctx.usage.statics.push(dec.clone());
+    }
+    ctx.insert_g(Rc::new(check_g_var(iter, &ctx.g.gvar_mp, dec, init)?));
+    Ok(())
 }
 
 pub fn gvar_initializer(
@@ -125,839 +212,1431 @@ pub fn gvar_initializer(
     initializers: &mut Vec<Initializer>,
     type_kind: Rc<RefCell<TypeKind>>,
 ) -> Result<(), Error> {
-    if let TypeKind::Array(size, base, is_sized) = &mut *type_kind.borrow_mut() {
-        if &*base.borrow() == &TypeKind::Char {
-            if let Some(string) = consume_string(iter) {
-                if !*is_sized {
-                    *is_sized = true;
-                    *size = string.len() as u64;
-                }
-                let len = min(string.len() as u64, *size);
-                let string = string.as_bytes();
-                let mut i = 0;
+    let mut tree = InitTree::new(type_kind.borrow().clone());
+    initializer(iter, ctx, &mut tree)?;
+    *type_kind.borrow_mut() = tree.type_kind.clone();
+    gvar_init_data(iter, &tree, initializers)
+}
 
-                while i < len {
-                    new_init_val(initializers, 1, string[i as usize] as i64);
-                    i += 1;
-                }
-                if i < *size {
-                    new_init_zero(initializers, base.borrow().size() * (*size - i));
+/// 初期化子の木をグローバル変数のデータに展開する
+fn gvar_init_data(
+    iter: &TokenStream,
+    tree: &InitTree,
+    initializers: &mut Vec<Initializer>,
+) -> Result<(), Error> {
+    match &tree.type_kind {
+        TypeKind::Array(_, _, _) => {
+            for child in &tree.children {
+                gvar_init_data(iter, child, initializers)?;
+            }
+            Ok(())
+        }
+        TypeKind::Struct(_) if tree.expr.is_some() => Err(not_constant(iter)),
+        TypeKind::Struct(_struct) => {
+            let _struct = _struct.borrow();
+            let size = _struct.get_size();
+            if _struct.is_union {
+                let idx = tree.member.unwrap_or(0);
+                if let Some(child) = tree.children.get(idx) {
+                    gvar_init_data(iter, child, initializers)?;
+                    new_init_zero(initializers, size - child.type_kind.size());
+                } else {
+                    new_init_zero(initializers, size);
                 }
                 return Ok(());
             }
-        }
-    }
-
-    match &mut *type_kind.borrow_mut() {
-        TypeKind::Array(size, base, is_sized) => {
-            let open = consume_block(iter, Block::LParen);
-            let mut i = 0;
-            let limit = if !*is_sized { u64::MAX } else { *size };
-            if !peek_end(iter) {
-                while {
-                    gvar_initializer(iter, ctx, initializers, base.clone())?;
-                    i += 1;
-                    i < limit && !peek_end(iter) && consume_comma(iter)
-                } {}
+            let members = &_struct.members;
+            for (i, child) in tree.children.iter().enumerate() {
+                gvar_init_data(iter, child, initializers)?;
+                emit_struct_padding(
+                    initializers,
+                    size,
+                    members[i].clone(),
+                    members.get(i + 1).cloned(),
+                );
             }
-
-            if open && !consume_end(iter) {
-                skip_excess_elements(iter, ctx)?;
+            if members.is_empty() {
+                new_init_zero(initializers, size);
             }
-            if i < *size {
-                new_init_zero(initializers, base.borrow().size() * (*size - i));
+            Ok(())
+        }
+        type_kind => {
+            let node = match &tree.expr {
+                Some(node) => node,
+                None => {
+                    new_init_val(initializers, type_kind.size(), 0);
+                    return Ok(());
+                }
+            };
+            let mut var = None;
+            let addend = eval2(iter, node, &mut var)?;
+            match var {
+                Some(label) => new_init_label(initializers, label.name, addend),
+                None => new_init_val(initializers, type_kind.size(), addend),
             }
+            Ok(())
+        }
+    }
+}
 
-            if !*is_sized {
-                *is_sized = true;
-                *size = i;
+// initializer      = "{" initializer-list "}" | assign
+// initializer-list = (designation? initializer ("," designation? initializer)*)? ","?
+// designation      = ("[" const-expr ("..." const-expr)? "]" | "." ident)+ "="
+// 集成体の`{}`は省略できる
+fn initializer(
+    iter: &mut TokenStream,
+    ctx: &mut Context,
+    tree: &mut InitTree,
+) -> Result<(), Error> {
+    // `{}`で初期化するときは、指示子で先に初期化した値を捨てる
+    if is_next_block(iter, Block::LParen)
+        && matches!(
+            tree.type_kind,
+            TypeKind::Array(_, _, _) | TypeKind::Struct(_)
+        )
+    {
+        tree.replace();
+    }
+    match tree.type_kind.clone() {
+        TypeKind::Array(_, base, _) => {
+            if *base.borrow() == TypeKind::Char && string_initializer(iter, tree)? {
+                return Ok(());
             }
-            return Ok(());
+            array_initializer(iter, ctx, tree)
         }
-        TypeKind::Struct(_struct) => {
+        TypeKind::Struct(_) => struct_initializer(iter, ctx, tree),
+        _ => {
             let open = consume_block(iter, Block::LParen);
-            let members = _struct.borrow().members.clone();
-            let mut i = 0;
-            if !peek_end(iter) {
-                while {
-                    gvar_initializer(
-                        iter,
-                        ctx,
-                        initializers,
-                        Rc::new(RefCell::new(members[i].type_kind.as_ref().clone())), // todo
-                    )?;
-                    let next = if members.len() > i + 1 {
-                        Some(members[i + 1].clone())
-                    } else {
-                        None
-                    };
-                    emit_struct_padding(
-                        initializers,
-                        _struct.borrow().get_size(),
-                        members[i].clone(),
-                        next,
-                    );
-                    i += 1;
-
-                    i < members.len() && !peek_end(iter) && consume_comma(iter)
-                } {}
-            }
-            if open && !consume_end(iter) {
-                skip_excess_elements(iter, ctx)?;
-            }
-            if members.len() > i {
-                let size = _struct.borrow().get_size() - members[i].offset;
-                new_init_zero(initializers, size);
+            let pos = iter.pos;
+            let node = assign(iter, ctx)?;
+            check_assignment(iter, ctx, pos, &tree.type_kind, &node, "initialization")?;
+            tree.expr = Some(node);
+            if open {
+                expect_end(iter)?;
             }
-            return Ok(());
+            Ok(())
         }
-        _ => (),
     }
+}
 
+/// `char`の配列を文字列で初期化する
+/// 文字列でなければ何も読まずに`false`を返す
+fn string_initializer(iter: &mut TokenStream, tree: &mut InitTree) -> Result<bool, Error> {
+    let i_data = iter.save();
     let open = consume_block(iter, Block::LParen);
-    let mut node = conditional(iter, ctx)?;
+    let string = match consume_string(iter) {
+        Some(string) => string,
+        None => {
+            iter.restore(i_data);
+            return Ok(false);
+        }
+    };
     if open {
         expect_end(iter)?;
     }
-    let mut var = None;
-    let addend = eval2(&mut node, &mut var)?;
-
-    match var {
-        Some(gvar) => {
-            new_init_label(initializers, gvar.dec.ident.name, addend);
-            return Ok(());
-        }
-        None => {
-            new_init_val(initializers, type_kind.borrow().size(), addend);
-            return Ok(());
-        }
+    tree.replace();
+    let len = match tree.type_kind {
+        TypeKind::Array(size, _, true) => min(string.len(), size as usize),
+        _ => string.len(),
+    };
+    for (i, c) in string.bytes().take(len).enumerate() {
+        tree.child_mut(i).expr = Some(Node::new_num(c as i64));
     }
+    fix_array_size(tree);
+    Ok(true)
 }
 
-// type-specifier  = builtin-type | struct-dec | typedef-name | enum-specifier"
-// builtin-type    = "void"
-//                 | "_Bool"
-//                 | "char"
-//                 | "short" | "short" "int" | "int" "short"
-//                 | "int"
-//                 | "long" | "int" "long" | "long" "int"
-// static, typedef and extern can appear anywhere in type-specifier
-pub fn type_specifier(
+fn array_initializer(
     iter: &mut TokenStream,
     ctx: &mut Context,
-) -> Result<(TypeKind, (bool, bool, bool)), Error> {
-    let mut ty_vec = Vec::new();
-    let mut is_typedef = false;
-    let mut is_static = false;
-    let mut is_extern = false;
-    let mut ty = None;
-    if !is_typename(iter, ctx) {
-        return Err(Error::todo(
-            iter.filepath.clone(),
-            iter.input.clone(),
-            iter.pos,
-        ));
-    }
-    while let Some(x) = iter.peek() {
-        if let TokenKind::TypeKind(ref type_kind) = x.kind {
-            iter.next();
-            ty_vec.push(type_kind.clone());
-        } else if x.kind == TokenKind::KeyWord(KeyWord::Struct) {
-            return Ok((
-                TypeKind::Struct(struct_dec(iter, ctx)?),
-                (is_typedef, is_static, is_extern),
-            ));
-        } else if x.kind == TokenKind::KeyWord(KeyWord::Enum) {
-            return Ok((
-                TypeKind::Enum(enum_specifier(iter, ctx)?),
-                (is_typedef, is_static, is_extern),
-            ));
-        } else if x.kind == TokenKind::KeyWord(KeyWord::Typedef) {
-            iter.next();
-            is_typedef = true;
-            continue;
-        } else if x.kind == TokenKind::KeyWord(KeyWord::Static) {
-            iter.next();
-            is_static = true;
-            continue;
-        } else if x.kind == TokenKind::KeyWord(KeyWord::Extern) {
-            iter.next();
-            is_extern = true;
-            continue;
-        } else {
-            if let Some(xx) = ty {
-                return Ok((xx, (is_typedef, is_static, is_extern)));
-            }
-            if let TokenKind::Ident(ref ident) = x.kind {
-                let ident = Rc::new(Ident::from(ident.clone()));
-                if let Some(dec) = is_typedef_name(ident, ctx) {
-                    iter.next();
-                    return Ok((dec.type_kind.clone(), (is_typedef, is_static, is_extern)));
-                }
+    tree: &mut InitTree,
+) -> Result<(), Error> {
+    let open = consume_block(iter, Block::LParen);
+    array_elements(iter, ctx, tree, open, 0, true)
+}
 
-                // else {
-                //     return Err(Error::undefined_tag(
-                //         iter.filepath.clone(),
-                //         iter.input.clone(),
-                //         iter.pos,
-                //         ident,
-                //         None,
-                //     ));
-                // }
+/// 配列の`i`番目から順に要素を読む。`first`でなければ`,`から読む
+/// `{}`を省略しているときは、要素が尽きるか指示子が現れたら止まる
+fn array_elements(
+    iter: &mut TokenStream,
+    ctx: &mut Context,
+    tree: &mut InitTree,
+    open: bool,
+    mut i: usize,
+    mut first: bool,
+) -> Result<(), Error> {
+    let limit = match tree.type_kind {
+        TypeKind::Array(size, _, true) => size as usize,
+        _ => usize::MAX,
+    };
+    while !peek_end(iter) {
+        if !first {
+            let i_data = iter.save();
+            if !consume_comma(iter) {
+                break;
+            }
+            // `{}`を省略しているときは、外側の指示子や要素に任せる
+            if !open && (is_designator(iter) || i >= limit) {
+                iter.restore(i_data);
+                break;
+            }
+            if open && i >= limit && !is_designator(iter) {
+                iter.restore(i_data);
+                skip_excess_elements(iter, ctx)?;
+                fix_array_size(tree);
+                return Ok(());
             }
         }
-        {
-            use TypeKind::*;
-            let type_kind = match ty_vec.as_slice() {
-                [Void] => Void,
-                [_Bool] => _Bool,
-                [Char] => Char,
-                [Short] | [Short, Int] | [Int, Short] => Short,
-                [Int] => Int,
-                [Long] | [Long, Int] | [Int, Long] => Long,
-                [] if is_typedef => match ty {
-                    Some(x) => x,
-                    None => Int,
-                },
-                _ => {
-                    iter.prev();
-                    return Err(Error::unexpected_token(
-                        iter.filepath.clone(),
-                        iter.input.clone(),
-                        &x,
-                        TokenKind::TypeKind(base_types::TypeKind::Int),
-                    ));
-                }
-            };
-            ty = Some(type_kind);
+        first = false;
+        if open && is_designator(iter) {
+            i = designation(iter, ctx, tree)?;
+            continue;
         }
+        initializer(iter, ctx, tree.child_mut(i))?;
+        i += 1;
     }
-    Err(Error::eof(
-        iter.filepath.clone(),
-        iter.input.clone(),
-        iter.pos,
-        TokenKind::TypeKind(base_types::TypeKind::Int),
-        None,
-    ))
+    if open {
+        expect_end(iter)?;
+    }
+    fix_array_size(tree);
+    Ok(())
 }
 
-// declarator      = "*"* ("(" declarator ")" | ident) type-suffix
-pub fn declarator(
+fn struct_initializer(
     iter: &mut TokenStream,
     ctx: &mut Context,
-    mut type_kind: Rc<RefCell<TypeKind>>,
-    ident: &mut Ident,
-) -> Result<Rc<RefCell<TypeKind>>, Error> {
-    loop {
-        if consume(iter, Operator::Mul) {
-            type_kind = Rc::new(RefCell::new(TypeKind::Ptr(type_kind)));
-        } else {
-            break;
-        }
+    tree: &mut InitTree,
+) -> Result<(), Error> {
+    // `{}`を省略しているときは、式を一度だけ読んでその型で決める
+    // 文字列は先頭のメンバの配列の初期化かもしれないので、メンバに任せる
+    if !is_next_block(iter, Block::LParen) && !is_next_string(iter) {
+        let pos = iter.pos;
+        let node = assign(iter, ctx)?;
+        return struct_initializer_from(iter, ctx, tree, node, pos);
     }
 
-    if consume(iter, Operator::LParen) {
-        let placeholder = Rc::new(RefCell::new(TypeKind::PlaceHolder));
-        let new = declarator(iter, ctx, placeholder.clone(), ident)?;
-        expect(iter, Operator::RParen)?;
-        *placeholder.borrow_mut() = type_suffix(iter, ctx, type_kind)?.borrow().clone();
-        return Ok(new);
-    }
-    *ident = expect_ident(iter)?;
-    type_suffix(iter, ctx, type_kind)
+    let open = consume_block(iter, Block::LParen);
+    struct_members(iter, ctx, tree, open, 0, true)
 }
 
-// abstract-declarator     = "*"* ("(" declarator ")")? type-suffix
-pub fn abstract_declarator(
+/// `{}`を省略した構造体を、読み終えた式から初期化する
+/// 同じ型の構造体の値なら丸ごとコピーし、そうでなければ先頭のメンバの初期化として扱う
+fn struct_initializer_from(
     iter: &mut TokenStream,
     ctx: &mut Context,
-    mut type_kind: Rc<RefCell<TypeKind>>,
-) -> Result<Rc<RefCell<TypeKind>>, Error> {
-    loop {
-        if consume(iter, Operator::Mul) {
-            type_kind = Rc::new(RefCell::new(TypeKind::Ptr(type_kind)));
-        } else {
-            break;
+    tree: &mut InitTree,
+    node: Node,
+    pos: TokenPos,
+) -> Result<(), Error> {
+    if let Ok(type_kind) = node.get_type() {
+        if TypeKind::is_compatible(&type_kind, &tree.type_kind) {
+            tree.expr = Some(node);
+            return Ok(());
         }
     }
-
-    if consume(iter, Operator::LParen) {
-        let placeholder = Rc::new(RefCell::new(TypeKind::PlaceHolder));
-        let new = abstract_declarator(iter, ctx, placeholder.clone())?;
-        expect(iter, Operator::RParen)?;
-        *placeholder.borrow_mut() = type_suffix(iter, ctx, type_kind)?.borrow().clone();
-        return Ok(new);
+    let _struct = match &tree.type_kind {
+        TypeKind::Struct(_struct) => _struct.clone(),
+        _ => unreachable!(),
+    };
+    let member = match _struct.borrow().members.first() {
+        Some(member) => member.clone(),
+        None => {
+            ctx.warn(Warn::excess_initializer(
+                iter.filepath.clone(),
+                iter.input.clone(),
+                pos,
+            ));
+            return Ok(());
+        }
+    };
+    if _struct.borrow().is_union {
+        tree.member = Some(0);
     }
-    type_suffix(iter, ctx, type_kind)
+    expect_member_initializable(iter, &member)?;
+    initializer_from(iter, ctx, tree.child_mut(0), node, pos)?;
+    struct_members(iter, ctx, tree, false, 1, false)
 }
 
-// type-suffix     = ("[" const-expr? "]" type-suffix)?
-pub fn type_suffix(
+/// 読み終えた式で`tree`の先頭のスカラーを初期化し、続く要素を`{}`を省略したものとして読む
+fn initializer_from(
     iter: &mut TokenStream,
     ctx: &mut Context,
-    type_kind: Rc<RefCell<TypeKind>>,
-) -> Result<Rc<RefCell<TypeKind>>, Error> {
-    if !consume(iter, Operator::LArr) {
-        return Ok(type_kind);
-    }
-    if consume(iter, Operator::RArr) {
-        let type_kind = type_suffix(iter, ctx, type_kind)?;
-        return Ok(Rc::new(RefCell::new(TypeKind::array_of(
-            0, type_kind, false,
-        ))));
+    tree: &mut InitTree,
+    node: Node,
+    pos: TokenPos,
+) -> Result<(), Error> {
+    match tree.type_kind {
+        TypeKind::Array(_, _, _) => {
+            initializer_from(iter, ctx, tree.child_mut(0), node, pos)?;
+            array_elements(iter, ctx, tree, false, 1, false)
+        }
+        TypeKind::Struct(_) => struct_initializer_from(iter, ctx, tree, node, pos),
+        _ => {
+            check_assignment(iter, ctx, pos, &tree.type_kind, &node, "initialization")?;
+            tree.expr = Some(node);
+            Ok(())
+        }
     }
-    let idx = const_expr(iter, ctx)?;
-    expect(iter, Operator::RArr)?;
-    let type_kind = type_suffix(iter, ctx, type_kind)?;
-    return Ok(Rc::new(RefCell::new(TypeKind::array_of(
-        idx as u64, type_kind, true,
-    ))));
 }
 
-// type-name               = type-specifier abstract-declarator type-suffix
-pub fn type_name(
+/// 構造体の`i`番目のメンバから順に読む。`first`でなければ`,`から読む
+/// `{}`を省略しているときは、メンバが尽きるか指示子が現れたら止まる
+fn struct_members(
     iter: &mut TokenStream,
     ctx: &mut Context,
-) -> Result<Rc<RefCell<TypeKind>>, Error> {
-    let (type_kind, _) = type_specifier(iter, ctx)?;
-    let type_kind = Rc::new(RefCell::new(type_kind));
-    let type_kind = abstract_declarator(iter, ctx, type_kind)?;
-    type_suffix(iter, ctx, type_kind)
-}
-
-// struct-dec      = "struct" ident? ("{" declaration ";" "}")?
-pub fn struct_dec(iter: &mut TokenStream, ctx: &mut Context) -> Result<Rc<RefCell<Struct>>, Error> {
-    expect_keyword(iter, KeyWord::Struct)?;
-    let ident = consume_ident(iter);
-
-    if let Some(ident) = &ident {
-        if !consume_block(iter, Block::LParen) {
-            if let Some(tag) = ctx.s.find_upper_tag(Rc::new(ident.clone())) {
-                if let TagTypeKind::Struct(_struct) = tag.as_ref() {
-                    return Ok(_struct.clone());
-                } else {
-                    dbg!("not a struct tag");
-                    return Err(Error::todo(
-                        iter.filepath.clone(),
-                        iter.input.clone(),
-                        iter.pos,
-                    ));
-                }
-            } else {
-                let ident = Rc::new(ident.clone());
-                let mut _struct = Struct::new(ident.clone(), Rc::new(Vec::new()));
-
-                _struct.is_incomplete = true;
-                let _struct = Rc::new(RefCell::new(_struct));
-                ctx.s
-                    .insert_t(ident.clone(), TagTypeKind::Struct(_struct.clone()));
-
-                return Ok(_struct);
+    tree: &mut InitTree,
+    open: bool,
+    mut i: usize,
+    mut first: bool,
+) -> Result<(), Error> {
+    let _struct = match &tree.type_kind {
+        TypeKind::Struct(_struct) => _struct.clone(),
+        _ => unreachable!(),
+    };
+    let is_union = _struct.borrow().is_union;
+    let members = _struct.borrow().members.clone();
+    // unionは先頭のメンバだけを初期化する
+    let limit = if is_union {
+        min(1, members.len())
+    } else {
+        members.len()
+    };
+    while !peek_end(iter) {
+        if !first {
+            let i_data = iter.save();
+            if !consume_comma(iter) {
+                break;
             }
-        } else {
-            iter.prev();
+            if !open && (is_designator(iter) || i >= limit) {
+                iter.restore(i_data);
+                break;
+            }
+            if open && i >= limit && !is_designator(iter) {
+                iter.restore(i_data);
+                skip_excess_elements(iter, ctx)?;
+                return Ok(());
+            }
+        }
+        first = false;
+        if open && is_designator(iter) {
+            i = designation(iter, ctx, tree)?;
+            continue;
+        }
+        if i >= limit {
+            break;
+        }
+        if is_union {
+            tree.member = Some(i);
         }
+        expect_member_initializable(iter, &members[i])?;
+        initializer(iter, ctx, tree.child_mut(i))?;
+        i += 1;
     }
-
-    if !consume_block(iter, Block::LParen) {
-        let mut _struct = Struct::new_anonymous(Rc::new(Vec::new()));
-        _struct.is_incomplete = true;
-        return Ok(Rc::new(RefCell::new(_struct)));
+    if open {
+        expect_end(iter)?;
     }
+    Ok(())
+}
 
-    let _struct = if let Some(ident) = &ident {
-        if let Some(tag) = ctx.s.find_upper_tag(Rc::new(ident.clone())) {
-            if let TagTypeKind::Struct(_struct) = tag.as_ref() {
-                _struct.clone()
+/// 指示子を読んで、指定された要素を初期化する
+/// 次に初期化する要素の位置を返す
+fn designation(
+    iter: &mut TokenStream,
+    ctx: &mut Context,
+    tree: &mut InitTree,
+) -> Result<usize, Error> {
+    match tree.type_kind.clone() {
+        TypeKind::Array(size, _, is_sized) => {
+            expect(iter, Operator::LArr)?;
+            let begin = const_expr(iter, ctx)?;
+            // `[0 ... 9]`はgccの拡張
+            let end = if consume(iter, Operator::ThreeDots) {
+                const_expr(iter, ctx)?
             } else {
-                dbg!("not a struct tag");
-                return Err(Error::todo(
+                begin
+            };
+            expect(iter, Operator::RArr)?;
+            if end < begin {
+                return Err(Error::invalid_initializer(
                     iter.filepath.clone(),
                     iter.input.clone(),
                     iter.pos,
+                    "empty index range in initializer",
                 ));
             }
-        } else {
-            let mut _struct = Struct::new(Rc::new(ident.clone()), Rc::new(Vec::new()));
-            _struct.is_incomplete = true;
-            let _struct = Rc::new(RefCell::new(_struct));
-            ctx.s
-                .insert_t(Rc::new(ident.clone()), TagTypeKind::Struct(_struct.clone()));
-
-            _struct
+            if begin < 0 || (is_sized && end as u64 >= size) {
+                return Err(Error::invalid_initializer(
+                    iter.filepath.clone(),
+                    iter.input.clone(),
+                    iter.pos,
+                    "array index in initializer exceeds array bounds",
+                ));
+            }
+            // 初期化子は一度だけ読んで、範囲の要素ごとに重ねる
+            let mut patch = InitTree::new(tree.child_mut(begin as usize).type_kind.clone());
+            designation_tail(iter, ctx, &mut patch)?;
+            share_side_effects(ctx, &mut patch, &mut tree.prelude);
+            for idx in begin as usize..=end as usize {
+                tree.child_mut(idx).overlay(&patch);
+            }
+            Ok(end as usize + 1)
         }
-    } else {
-        Rc::new(RefCell::new(Struct::new_anonymous(Rc::new(Vec::new()))))
-    };
-
-    let sc = ctx.s.enter();
-    let mut members = Vec::new();
-    while !consume_block(iter, Block::RParen) {
-        members.push(declaration(iter, ctx)?);
-        expect_semi(iter)?;
-    }
-    ctx.s.leave(sc);
-    let mut offset = 0;
-    let members: Vec<Rc<Member>> = members
-        .into_iter()
-        .map(|m| {
-            offset = base_types::align_to(offset, m.type_kind.align());
-            let _offset = offset;
-            offset += m.type_kind.size();
-            let mem = Member::new(Rc::new(m.type_kind), _offset, m.ident);
-            Rc::new(mem)
-        })
-        .collect();
-
-    _struct.borrow_mut().members = Rc::new(members);
-    _struct.borrow_mut().is_incomplete = false;
-    Ok(_struct)
-}
-
-// enum-specifier          = enum ident? "{" enum-list? "}"
-//                         | enum ident
-// enum-list               = enum-elem ("," enum-elem)* ","?
-// enum-elem               = ident ("=" const-expr)?
-pub fn enum_specifier(iter: &mut TokenStream, ctx: &mut Context) -> Result<Rc<Enum>, Error> {
-    expect_keyword(iter, KeyWord::Enum)?;
-    let tag = consume_ident(iter);
-    if !consume_block(iter, Block::LParen) {
-        match &tag {
-            Some(x) => {
-                if let Some(tag) = ctx.s.find_upper_tag(Rc::new(x.clone())) {
-                    if let TagTypeKind::Enum(_enum) = tag.as_ref() {
-                        return Ok(_enum.clone());
-                    }
-                } else {
-                    iter.prev();
-                    return Err(Error::undefined_tag(
+        TypeKind::Struct(_struct) => {
+            if !consume_period(iter) {
+                return Err(Error::invalid_initializer(
+                    iter.filepath.clone(),
+                    iter.input.clone(),
+                    iter.pos,
+                    "array index in non-array initializer",
+                ));
+            }
+            let ident = expect_ident(iter)?;
+            let _struct = _struct.borrow();
+            let idx = _struct
+                .members
+                .iter()
+                .position(|m| m.ident == ident)
+                .ok_or_else(|| {
+                    Error::undefined_member(
                         iter.filepath.clone(),
                         iter.input.clone(),
                         iter.pos,
-                        x.clone(),
-                        None,
-                    ));
-                }
-            }
-            None => {
-                dbg!("incomplete enum dec");
-                return Err(Error::todo(
-                    iter.filepath.clone().clone(),
-                    iter.input.clone().clone(),
-                    iter.pos,
-                ));
+                        ident.clone(),
+                        suggest_member(&_struct, &ident.name),
+                    )
+                })?;
+            let member = _struct.members[idx].clone();
+            if _struct.is_union && tree.member != Some(idx) {
+                // 別のメンバで初期化し直す
+                tree.member = Some(idx);
+                tree.children[idx] = InitTree::new(member.type_kind.as_ref().clone());
             }
+            drop(_struct);
+            expect_member_initializable(iter, &member)?;
+            designation_tail(iter, ctx, tree.child_mut(idx))?;
+            Ok(idx + 1)
         }
+        _ => Err(Error::invalid_initializer(
+            iter.filepath.clone(),
+            iter.input.clone(),
+            iter.pos,
+            "designator in initializer for scalar type",
+        )),
     }
+}
 
-    if let Some(t) = &tag {
-        if !t.is_anonymous() {
-            if let Some(_) = ctx.s.find_cur_tag(Rc::new(t.clone())) {
-                // iter.prev();
-                // return Err(Error::re_declare(
-                //     iter.filepath.clone().clone(),
-                //     iter.input.clone().clone(),
-                //     t.clone(),
-                //     iter.pos,
-                //     None,
-                // ));
-            }
-        }
+fn designation_tail(
+    iter: &mut TokenStream,
+    ctx: &mut Context,
+    tree: &mut InitTree,
+) -> Result<(), Error> {
+    if is_designator(iter) {
+        let i = designation(iter, ctx, tree)?;
+        // 指示した要素に続く初期化子は、同じ集成体の次の要素から順に初期化する
+        return match tree.type_kind {
+            TypeKind::Array(_, _, _) => array_elements(iter, ctx, tree, false, i, false),
+            TypeKind::Struct(_) => struct_members(iter, ctx, tree, false, i, false),
+            _ => Ok(()),
+        };
     }
+    expect(iter, Operator::Assign)?;
+    initializer(iter, ctx, tree)
+}
 
-    let mut enum_list = Vec::new();
-    let mut count = 0;
-    while !consume_block(iter, Block::RParen) {
-        let ident = expect_ident(iter)?;
-        if consume(iter, Operator::Assign) {
-            count = const_expr(iter, ctx)?;
-        }
+/// 副作用のある式は前もって一度だけ評価して隠れた変数に入れ、要素ではその値を読む
+fn share_side_effects(ctx: &mut Context, tree: &mut InitTree, prelude: &mut Vec<Node>) {
+    prelude.append(&mut tree.prelude);
+    if let Some(expr) = tree.expr.take() {
+        tree.expr = Some(if has_side_effects(&expr) {
+            let ident = Ident::new("__init_tmp__");
+            ctx.push_front(Declaration::new(tree.type_kind.clone(), ident.clone()));
+            let tmp = ctx.s.find_cur_lvar(ident).unwrap();
+            prelude.push(Node::new_expr_stmt(Node::new(
+                NodeKind::Assign,
+                Node::new_lvar(tmp.clone()),
+                expr,
+            )));
+            Node::new_lvar(tmp)
+        } else {
+            expr
+        });
+    }
+    for child in &mut tree.children {
+        share_side_effects(ctx, child, prelude);
+    }
+}
 
-        ctx.push_front(Declaration::new_const(TypeKind::Int, ident.clone(), count));
-        enum_list.push(Rc::new((ident, count)));
-        count += 1;
-        consume_comma(iter);
+fn has_side_effects(node: &Node) -> bool {
+    use NodeKind::*;
+    let mut result = false;
+    node.walk(&mut |node| {
+        result |= matches!(
+            node.kind,
+            Assign
+                | AAdd
+                | ASub
+                | AMul
+                | ADiv
+                | ALShift
+                | ARShift
+                | ABitAnd
+                | ABitOr
+                | ABitXor
+                | PreInc
+                | PreDec
+                | PostInc
+                | PostDec
+                | Func(_, _)
+                | StmtExpr(_)
+        )
+    });
+    result
+}
+
+/// flexible array memberは初期化できない
+fn expect_member_initializable(iter: &TokenStream, member: &Member) -> Result<(), Error> {
+    if let TypeKind::Array(_, _, false) = member.type_kind.as_ref() {
+        return Err(Error::invalid_initializer(
+            iter.filepath.clone(),
+            iter.input.clone(),
+            iter.pos,
+            "initialization of flexible array member is not supported",
+        ));
     }
+    Ok(())
+}
 
-    let enum_list = Rc::new(enum_list);
-    let mut _enum = if let Some(tag) = tag {
-        let tag = Rc::new(tag);
-        let _enum = Rc::new(Enum::new(tag.clone(), enum_list));
-        let result = ctx
-            .s
-            .insert_t(tag.clone(), TagTypeKind::Enum(_enum.clone()));
-        if let Some(_) = result {
-            unreachable!()
-        }
-        _enum
-    } else {
-        Rc::new(Enum::new(
-            Rc::new(Ident::new(".struct.anonymous")),
-            enum_list,
-        ))
-    };
-    Ok(_enum)
+/// 要素数のない配列の大きさを初期化子の数に合わせる
+fn fix_array_size(tree: &mut InitTree) {
+    if let TypeKind::Array(_, base, false) = &tree.type_kind {
+        tree.type_kind = TypeKind::array_of(tree.children.len() as u64, base.clone(), true);
+    }
 }
 
-// declaration     = type-specifier declarator type-suffix
-//                 | type-specifier
-pub(crate) fn declaration(iter: &mut TokenStream, ctx: &mut Context) -> Result<Declaration, Error> {
-    let (type_kind, (is_typedef, is_static, is_extern)) = type_specifier(iter, ctx)?;
-    let type_kind = Rc::new(RefCell::new(type_kind));
-    let mut ident = Ident::new_anonymous();
-    let mut dec = if let Some(dec) = consume_declarator(iter, ctx, type_kind.clone(), &mut ident) {
-        let type_suffix = type_suffix(iter, ctx, dec)?;
-        let type_suffix = type_suffix.borrow().clone();
-        Declaration::new(type_suffix, ident) // todo
-    } else {
-        let type_kind = type_kind.borrow().clone();
-        Declaration::new(type_kind, ident)
-    };
+/// (is_typedef, is_static, is_extern, alignas)
+type StorageClass = (bool, bool, bool, Option<u64>);
 
-    dec.is_typedef = is_typedef;
-    dec.is_static = is_static;
-    dec.is_extern = is_extern;
+// type-specifier  = builtin-type | struct-dec | typedef-name | enum-specifier | typeof-specifier
+// builtin-type    = "void"
+//                 | "_Bool"
+//                 | "char"
+//                 | "short" | "short" "int" | "int" "short"
+//                 | "int"
+//                 | "long" | "int" "long" | "long" "int"
+// static, typedef, extern and alignment-specifier can appear anywhere in type-specifier
+pub fn type_specifier(
+    iter: &mut TokenStream,
+    ctx: &mut Context,
+) -> Result<(TypeKind, StorageClass), Error> {
+    let mut ty_vec = Vec::new();
+    let mut is_typedef = false;
+    let mut is_static = false;
+    let mut is_extern = false;
+    let mut alignas: Option<u64> = None;
+    let mut ty = None;
+    if !is_typename(iter, ctx) {
+        return Err(Error::todo(
+            iter.filepath.clone(),
+            iter.input.clone(),
+            iter.pos,
+        ));
+    }
+    while let Some(x) = iter.peek() {
+        if let TokenKind::TypeKind(ref type_kind) = x.kind {
+            iter.next();
+            ty_vec.push(type_kind.clone());
+        } else if x.kind == TokenKind::KeyWord(KeyWord::Struct)
+            || x.kind == TokenKind::KeyWord(KeyWord::Union)
+        {
+            return Ok((
+                TypeKind::Struct(struct_dec(iter, ctx)?),
+                (is_typedef, is_static, is_extern, alignas),
+            ));
+        } else if x.kind == TokenKind::KeyWord(KeyWord::Enum) {
+            return Ok((
+                TypeKind::Enum(enum_specifier(iter, ctx)?),
+                (is_typedef, is_static, is_extern, alignas),
+            ));
+        } else if x.kind == TokenKind::KeyWord(KeyWord::Typeof)
+            || x.kind == TokenKind::KeyWord(KeyWord::TypeofUnqual)
+        {
+            return Ok((
+                typeof_specifier(iter, ctx)?,
+                (is_typedef, is_static, is_extern, alignas),
+            ));
+        } else if x.kind == TokenKind::KeyWord(KeyWord::Typedef) {
+            iter.next();
+            is_typedef = true;
+            continue;
+        } else if x.kind == TokenKind::KeyWord(KeyWord::Static) {
+            iter.next();
+            is_static = true;
+            continue;
+        } else if x.kind == TokenKind::KeyWord(KeyWord::Extern) {
+            iter.next();
+            is_extern = true;
+            continue;
+        } else if x.kind == TokenKind::KeyWord(KeyWord::Alignas) {
+            let align = alignment_specifier(iter, ctx)?;
+            alignas = Some(alignas.map_or(align, |x| x.max(align)));
+            continue;
+        } else {
+            if let Some(xx) = ty {
+                return Ok((xx, (is_typedef, is_static, is_extern, alignas)));
+            }
+            if let TokenKind::Ident(ref ident) = x.kind {
+                let ident = Rc::new(Ident::from(ident.clone()));
+                if let Some(dec) = is_typedef_name(ident, ctx) {
+                    iter.next();
+                    return Ok((
+                        dec.type_kind.clone(),
+                        (is_typedef, is_static, is_extern, alignas),
+                    ));
+                }
 
-    match (
-        ctx.s.find_cur_lvar(dec.ident.clone()),
-        ctx.s.find_cur_gvar(dec.ident.clone()),
-    ) {
-        (None, None) => (),
-        _ => {
-            if !dec.ident.is_anonymous() {
-                // iter.prev();
-                // return Err(Error::re_declare(
-                //     iter.filepath.clone().clone(),
-                //     iter.input.clone().clone(),
-                //     dec.ident.clone(),
-                //     iter.pos,
-                //     None,
-                // ));
+                // else {
+                //     return Err(Error::undefined_tag(
+                //         iter.filepath.clone(),
+                //         iter.input.clone(),
+                //         iter.pos,
+                //         ident,
+                //         None,
+                //     ));
+                // }
             }
         }
-    }
-
-    if is_typedef {
-        let result = ctx.s.insert_t(
-            Rc::new(dec.ident.clone().get_typedef_ident()),
-            TagTypeKind::Typedef(Rc::new(dec.clone())),
-        );
-        if let Some(_) = result {
-            // return Err(Error::re_declare(
-            //     iter.filepath.clone().clone(),
-            //     iter.input.clone().clone(),
-            //     dec.ident.clone(),
-            //     iter.pos,
-            //     None,
-            // ));
+        {
+            use TypeKind::*;
+            let type_kind = match ty_vec.as_slice() {
+                [Void] => Void,
+                [_Bool] => _Bool,
+                [Char] => Char,
+                [Short] | [Short, Int] | [Int, Short] => Short,
+                [Int] => Int,
+                [Long] | [Long, Int] | [Int, Long] => Long,
+                [] if is_typedef => match ty {
+                    Some(x) => x,
+                    None => Int,
+                },
+                _ => {
+                    iter.prev();
+                    return Err(Error::unexpected_token(
+                        iter.filepath.clone(),
+                        iter.input.clone(),
+                        &x,
+                        TokenKind::TypeKind(base_types::TypeKind::Int),
+                    ));
+                }
+            };
+            ty = Some(type_kind);
         }
     }
+    Err(Error::eof(
+        iter.filepath.clone(),
+        iter.input.clone(),
+        iter.pos,
+        TokenKind::TypeKind(base_types::TypeKind::Int),
+        None,
+    ))
+}
 
-    if is_static {
-        //     let ident = Ident::new(ctx.make_label());
-        //     ctx.g.gvar_mp.insert(
-        //         ident.name.clone(),
-        //         Rc::new(check_g_var(
-        //             iter,
-        //             &ctx.g.gvar_mp,
-        //             dec.type_kind.clone(),
-        //             ident,
-        //             vec![],
-        //         )?),
-        //     );
-        //     // ctx.s.push_front(dec.clone(),)
+// typeof-specifier        = ("typeof" | "typeof_unqual") "(" (type-name | expr) ")"
+// 式は型を調べるだけで評価しない
+// 修飾子はないので`typeof_unqual`は`typeof`と同じ
+fn typeof_specifier(iter: &mut TokenStream, ctx: &mut Context) -> Result<TypeKind, Error> {
+    if !consume_keyword(iter, KeyWord::Typeof) {
+        expect_keyword(iter, KeyWord::TypeofUnqual)?;
     }
-    Ok(dec)
+    expect(iter, Operator::LParen)?;
+    let type_kind = if is_typename(iter, ctx) {
+        type_name(iter, ctx)?.borrow().clone()
+    } else {
+        match expr(iter, ctx)?.get_type() {
+            Ok(x) => x,
+            Err(_) => {
+                return Err(Error::todo(
+                    iter.filepath.clone(),
+                    iter.input.clone(),
+                    iter.pos,
+                ))
+            }
+        }
+    };
+    expect(iter, Operator::RParen)?;
+    Ok(type_kind)
 }
 
-// function    =  type-specifier declarator "(" params? ")" "{" stmt* "}"
-pub fn function(
-    iter: &mut TokenStream,
-    func_prototype: Rc<FuncPrototype>,
-    ctx: &mut Context,
-    is_variadic: bool,
-) -> Result<Function, Error> {
-    expect_block(iter, Block::LParen)?;
-
-    ctx.l = LocalContext::new();
-    for fn_param in func_prototype.params.clone() {
-        let tmp_lvar = Var::L(Rc::new(RefCell::new(Lvar::new_leaf(fn_param.clone(), 0))));
-        ctx.push_scope(fn_param.ident, Rc::new(tmp_lvar));
-    }
-    let lvar = if is_variadic {
-        let ident = Ident::new("__va_area__");
-        let type_kind = TypeKind::array_of(136, Rc::new(RefCell::new(TypeKind::Char)), true);
-        let dec = Declaration::new(type_kind, ident.clone());
-        ctx.push_front(dec);
-        ctx.s.find_cur_lvar(ident).map(|v| v.borrow().clone())
+// alignment-specifier     = "_Alignas" "(" (type-name | const-expr) ")"
+fn alignment_specifier(iter: &mut TokenStream, ctx: &mut Context) -> Result<u64, Error> {
+    expect_keyword(iter, KeyWord::Alignas)?;
+    expect(iter, Operator::LParen)?;
+    let align = if is_typename(iter, ctx) {
+        type_name(iter, ctx)?.borrow().align() as i64
     } else {
-        None
+        const_expr(iter, ctx)?
     };
+    if align <= 0 || align & (align - 1) != 0 {
+        return Err(Error::invalid_alignment(
+            iter.filepath.clone(),
+            iter.input.clone(),
+            iter.pos,
+            align,
+        ));
+    }
+    expect(iter, Operator::RParen)?;
+    Ok(align as u64)
+}
 
-    let mut stmt_vec = Vec::new();
-    loop {
-        if consume_block(iter, Block::RParen) {
-            let is_static = func_prototype.is_static;
-            for fn_param in func_prototype.params.clone() {
-                let l = ctx
-                    .l
-                    .lvar
-                    .as_ref()
-                    .map(|lvar| lvar.borrow().offset)
-                    .unwrap_or(0);
-                // ctx.push_front の代わりに自分で更新する
-                ctx.l.push_front(fn_param.clone(), l);
-                let offset = l + fn_param.type_kind.size();
-                let offset = base_types::align_to(offset, fn_param.type_kind.align());
-                ctx.s
-                    .find_upper_lvar(fn_param.ident.clone())
-                    .unwrap()
-                    .borrow_mut()
-                    .offset = offset;
-            }
-            return Ok(Function::new(
-                func_prototype,
-                ctx.l.lvar.clone(),
-                ctx.l.lvar_count.clone(),
-                stmt_vec,
-                is_static,
-                lvar,
-            ));
-        }
-        stmt_vec.push(stmt(iter, ctx)?);
+// static-assert           = "_Static_assert" "(" const-expr ("," str)? ")" ";"
+fn static_assert(iter: &mut TokenStream, ctx: &mut Context) -> Result<(), Error> {
+    expect_keyword(iter, KeyWord::StaticAssert)?;
+    let pos = iter.pos;
+    expect(iter, Operator::LParen)?;
+    let cond = const_expr(iter, ctx)?;
+    let msg = if consume_comma(iter) {
+        expect_string(iter)?
+    } else {
+        String::new()
+    };
+    expect(iter, Operator::RParen)?;
+    expect_semi(iter)?;
+    if cond == 0 {
+        return Err(Error::static_assertion(
+            iter.filepath.clone(),
+            iter.input.clone(),
+            pos,
+            msg,
+        ));
     }
+    Ok(())
 }
 
-// params      = declaration ("," declaration)* ("," "...")? | "void"
-pub fn params(
+// declarator      = "*"* ("(" declarator ")" | ident)? type-suffix
+// 引数の宣言などでは識別子を省略できる
+pub fn declarator(
     iter: &mut TokenStream,
     ctx: &mut Context,
-) -> Result<(Vec<Declaration>, bool), Error> {
-    let idx = iter.idx;
-    if consume_type_kind(iter) == Some(TypeKind::Void) && consume(iter, Operator::RParen) {
-        return Ok((Vec::new(), false));
+    mut type_kind: Rc<RefCell<TypeKind>>,
+    ident: &mut Ident,
+) -> Result<Rc<RefCell<TypeKind>>, Error> {
+    loop {
+        if consume(iter, Operator::Mul) {
+            type_kind = Rc::new(RefCell::new(TypeKind::Ptr(type_kind)));
+        } else {
+            break;
+        }
     }
-    iter.idx = idx;
-    let mut params = vec![read_param(iter, ctx)?];
-    while !consume(iter, Operator::RParen) {
-        expect_comma(iter)?;
-        if consume(iter, Operator::ThreeDots) {
-            expect(iter, Operator::RParen)?;
 
-            return Ok((params, true));
-        }
-        params.push(read_param(iter, ctx)?);
+    if is_nested_declarator(iter, ctx, true) {
+        expect(iter, Operator::LParen)?;
+        let placeholder = Rc::new(RefCell::new(TypeKind::PlaceHolder));
+        let new = declarator(iter, ctx, placeholder.clone(), ident)?;
+        expect(iter, Operator::RParen)?;
+        *placeholder.borrow_mut() = type_suffix(iter, ctx, type_kind)?.borrow().clone();
+        return Ok(new);
+    }
+    if let Some(name) = consume_ident(iter) {
+        *ident = name;
     }
-    Ok((params, false))
+    type_suffix(iter, ctx, type_kind)
 }
 
-pub fn read_param(iter: &mut TokenStream, ctx: &mut Context) -> Result<Declaration, Error> {
-    let mut dec = declaration(iter, ctx)?;
-    if let TypeKind::Array(_, base, _) = &dec.type_kind {
-        dec.type_kind = TypeKind::ptr_to(base.clone());
+// abstract-declarator     = "*"* ("(" abstract-declarator ")")? type-suffix
+pub fn abstract_declarator(
+    iter: &mut TokenStream,
+    ctx: &mut Context,
+    mut type_kind: Rc<RefCell<TypeKind>>,
+) -> Result<Rc<RefCell<TypeKind>>, Error> {
+    loop {
+        if consume(iter, Operator::Mul) {
+            type_kind = Rc::new(RefCell::new(TypeKind::Ptr(type_kind)));
+        } else {
+            break;
+        }
     }
-    Ok(dec)
+
+    if is_nested_declarator(iter, ctx, false) {
+        expect(iter, Operator::LParen)?;
+        let placeholder = Rc::new(RefCell::new(TypeKind::PlaceHolder));
+        let new = abstract_declarator(iter, ctx, placeholder.clone())?;
+        expect(iter, Operator::RParen)?;
+        *placeholder.borrow_mut() = type_suffix(iter, ctx, type_kind)?.borrow().clone();
+        return Ok(new);
+    }
+    type_suffix(iter, ctx, type_kind)
 }
 
-pub fn lvar_init_zero(
+// type-suffix     = ("[" conditional? "]" type-suffix)?
+//                 | "(" params? ")"
+// 要素数が定数式でなければ可変長配列になる
+pub fn type_suffix(
     iter: &mut TokenStream,
     ctx: &mut Context,
-    lvar: Rc<RefCell<Lvar>>,
     type_kind: Rc<RefCell<TypeKind>>,
-    desg: &mut Option<Box<Designator>>,
-) -> Result<Node, Error> {
-    let mut init = Vec::new();
-    match &*type_kind.borrow() {
-        TypeKind::Array(size, base, _) => {
-            let mut i = 0;
-            while i < *size {
-                let mut desg2 = Some(Box::new(Designator::new(i, desg.clone(), None)));
-                i += 1;
-                let node = lvar_init_zero(iter, ctx, lvar.clone(), base.clone(), &mut desg2)?;
-                init.push(node);
-                i += 1;
-            }
-            return Ok(Node::new_init(
-                NodeKind::Declaration(lvar.borrow().dec.clone()),
-                init,
+) -> Result<Rc<RefCell<TypeKind>>, Error> {
+    if consume(iter, Operator::LParen) {
+        // `()`はプロトタイプのない関数で、引数を検査しない
+        let (fn_params, is_variadic) = if consume(iter, Operator::RParen) {
+            (None, false)
+        } else {
+            let (fn_params, is_variadic) = params(iter, ctx)?;
+            (Some(Rc::new(fn_params)), is_variadic)
+        };
+        return Ok(Rc::new(RefCell::new(TypeKind::Func(
+            type_kind,
+            fn_params,
+            is_variadic,
+        ))));
+    }
+    if !consume(iter, Operator::LArr) {
+        return Ok(type_kind);
+    }
+    if consume(iter, Operator::RArr) {
+        let type_kind = type_suffix(iter, ctx, type_kind)?;
+        return Ok(Rc::new(RefCell::new(TypeKind::array_of(
+            0, type_kind, false,
+        ))));
+    }
+    let len = conditional(iter, ctx)?;
+    expect(iter, Operator::RArr)?;
+    let type_kind = type_suffix(iter, ctx, type_kind)?;
+    // 要素が可変長配列なら、配列全体の大きさも実行時に決まる
+    let elem_size = match &*type_kind.borrow() {
+        TypeKind::Vla(_, size) => size.as_ref().clone(),
+        base => Node::new_num(base.size() as i64),
+    };
+    if is_const_expr(&len) {
+        let idx = eval(iter, &len)?;
+        if idx < 0 {
+            return Err(Error::invalid_constant_expression(
+                iter.filepath.clone(),
+                iter.input.clone(),
+                iter.pos,
+                "size of array is negative",
             ));
         }
-        _ => (),
+        if !matches!(*type_kind.borrow(), TypeKind::Vla(_, _)) {
+            return Ok(Rc::new(RefCell::new(TypeKind::array_of(
+                idx as u64, type_kind, true,
+            ))));
+        }
     }
-    Ok(Node::new_init(
-        NodeKind::Declaration(lvar.borrow().dec.clone()),
-        vec![new_desg_node(Var::L(lvar.clone()), desg, Node::new_num(0))?],
-    ))
+    // 大きさは要素数と要素の大きさの積にする
+    let size = Node::new(NodeKind::Mul, len, elem_size);
+    Ok(Rc::new(RefCell::new(TypeKind::Vla(
+        type_kind,
+        Rc::new(size),
+    ))))
 }
 
-// lvar-initializer = assign
-//                  | "{" lvar-initializer ("," lvar-initializer)* ","? "}"
-fn lvar_initializer(
+// type-name               = type-specifier abstract-declarator type-suffix
+pub fn type_name(
     iter: &mut TokenStream,
     ctx: &mut Context,
-    lvar: Rc<RefCell<Lvar>>,
-    type_kind: Rc<RefCell<TypeKind>>,
-    desg: &mut Option<Box<Designator>>,
-) -> Result<(Node, Rc<RefCell<TypeKind>>), Error> {
-    let var = Var::L(lvar.clone());
-    if let TypeKind::Array(size, base, is_sized) = &mut *type_kind.borrow_mut() {
-        if &*base.borrow() == &TypeKind::Char {
-            if let Some(string) = consume_string(iter) {
-                if !*is_sized {
-                    *is_sized = true;
-                    *size = string.len() as u64;
-                }
-                let len = min(string.len() as u64, *size);
-                let mut i = 0;
-                let string = string.as_bytes();
-                let mut init = Vec::new();
-                while i < len {
-                    let mut desg2 = Some(Box::new(Designator::new(i, desg.clone(), None)));
-                    let rhs = Node::new_num(string[i as usize] as i64); //
-                    init.push(new_desg_node(var.clone(), &mut desg2, rhs)?);
-                    i += 1;
+) -> Result<Rc<RefCell<TypeKind>>, Error> {
+    let (type_kind, _) = type_specifier(iter, ctx)?;
+    let type_kind = Rc::new(RefCell::new(type_kind));
+    let type_kind = abstract_declarator(iter, ctx, type_kind)?;
+    type_suffix(iter, ctx, type_kind)
+}
+
+// struct-dec      = ("struct" | "union") ident? ("{" (struct-declaration | static-assert)* "}")?
+// struct-declaration = type-specifier (declarator type-suffix)? ("," declarator type-suffix)* ";"
+pub fn struct_dec(iter: &mut TokenStream, ctx: &mut Context) -> Result<Rc<RefCell<Struct>>, Error> {
+    let is_union = consume_keyword(iter, KeyWord::Union);
+    if !is_union {
+        expect_keyword(iter, KeyWord::Struct)?;
+    }
+    let ident_begin = iter.idx;
+    let ident = consume_ident(iter);
+    let ident_span = iter.span_from(ident_begin);
+
+    if let Some(ident) = &ident {
+        if !consume_block(iter, Block::LParen) {
+            // `struct tag;` は外側のスコープに同じタグがあっても新しい型を宣言する
+            let is_forward_dec = iter.peek().map(|t| t.kind) == Some(TokenKind::SemiColon);
+            let tag = if is_forward_dec {
+                ctx.s.find_cur_tag(Rc::new(ident.clone()))
+            } else {
+                ctx.s.find_upper_tag(Rc::new(ident.clone()))
+            };
+            if let Some(tag) = tag {
+                if let TagTypeKind::Struct(_struct) = tag.as_ref() {
+                    return Ok(_struct.clone());
+                } else {
+                    dbg!("not a struct tag");
+                    return Err(Error::todo(
+                        iter.filepath.clone(),
+                        iter.input.clone(),
+                        iter.pos,
+                    ));
                 }
-                for i in i..*size {
-                    let mut desg2 = Some(Box::new(Designator::new(i, desg.clone(), None)));
-                    let node = lvar_init_zero(iter, ctx, lvar.clone(), base.clone(), &mut desg2)?;
-                    init.push(node);
+            } else {
+                let ident = Rc::new(ident.clone());
+                let mut _struct = Struct::new(ident.clone(), Rc::new(Vec::new()));
+
+                _struct.is_incomplete = true;
+                let _struct = Rc::new(RefCell::new(_struct));
+                ctx.s
+                    .insert_t(ident.clone(), TagTypeKind::Struct(_struct.clone()));
+
+                return Ok(_struct);
+            }
+        } else {
+            iter.prev();
+        }
+    }
+
+    if !consume_block(iter, Block::LParen) {
+        let mut _struct = Struct::new_anonymous(Rc::new(Vec::new()));
+        _struct.is_incomplete = true;
+        return Ok(Rc::new(RefCell::new(_struct)));
+    }
+
+    // 同じスコープで前方宣言されたタグだけを完成させる
+    // 外側のスコープのタグは隠して新しい型を作る
+    let _struct = if let Some(ident) = &ident {
+        if let Some(tag) = ctx.s.find_cur_tag(Rc::new(ident.clone())) {
+            if let TagTypeKind::Struct(_struct) = tag.as_ref() {
+                if !_struct.borrow().is_incomplete {
+                    let err = Error::re_declare(
+                        iter.filepath.clone(),
+                        iter.input.clone(),
+                        ident.clone(),
+                        ident_span.pos(),
+                        None,
+                    );
+                    return Err(with_previous(err, &_struct.borrow().span, "definition"));
                 }
-                return Ok((
-                    Node::new_expr_stmt(Node::new_init(NodeKind::Lvar(lvar), init)),
-                    type_kind.clone(),
+                _struct.clone()
+            } else {
+                dbg!("not a struct tag");
+                return Err(Error::todo(
+                    iter.filepath.clone(),
+                    iter.input.clone(),
+                    iter.pos,
                 ));
             }
+        } else {
+            let mut _struct = Struct::new(Rc::new(ident.clone()), Rc::new(Vec::new()));
+            _struct.is_incomplete = true;
+            let _struct = Rc::new(RefCell::new(_struct));
+            ctx.s
+                .insert_t(Rc::new(ident.clone()), TagTypeKind::Struct(_struct.clone()));
+
+            _struct
         }
+    } else {
+        Rc::new(RefCell::new(Struct::new_anonymous(Rc::new(Vec::new()))))
+    };
+    if ident.is_some() {
+        _struct.borrow_mut().span = ident_span;
     }
 
-    match &mut *type_kind.borrow_mut() {
-        TypeKind::Array(size, base, is_sized) => {
-            let mut init = Vec::new();
-            let mut i = 0;
-            let open = consume_block(iter, Block::LParen);
-            let limit = if !*is_sized { u64::MAX } else { *size };
-            if !peek_end(iter) {
-                while {
-                    let mut desg2 = Some(Box::new(Designator::new(i, desg.clone(), None)));
-                    i += 1;
-                    let node = lvar_initializer(iter, ctx, lvar.clone(), base.clone(), &mut desg2)?;
-                    init.push(node.0);
-                    i < limit && !peek_end(iter) && consume_comma(iter)
-                } {}
-            }
-
-            if open && !consume_end(iter) {
-                skip_excess_elements(iter, ctx)?;
+    let sc = ctx.s.enter();
+    let mut members = Vec::new();
+    while !consume_block(iter, Block::RParen) {
+        if is_next_keyword(iter, KeyWord::StaticAssert) {
+            static_assert(iter, ctx)?;
+            continue;
+        }
+        let (type_kind, storage) = type_specifier(iter, ctx)?;
+        loop {
+            members.push(declaration_of(iter, ctx, &type_kind, storage)?);
+            if !consume_comma(iter) {
+                break;
             }
+            expect_declarator(iter)?;
+        }
+        expect_semi(iter)?;
+    }
+    ctx.s.leave(sc);
 
-            while i < *size {
-                let mut desg2 = Some(Box::new(Designator::new(i, desg.clone(), None)));
-                i += 1;
-                let node = lvar_init_zero(iter, ctx, lvar.clone(), base.clone(), &mut desg2)?;
-                init.push(node);
-            }
-            if !*is_sized {
-                *is_sized = true;
-                *size = init.len() as u64;
+    // 要素数のない配列はflexible array memberとして最後のメンバにだけ置ける
+    for (i, m) in members.iter().enumerate() {
+        if let TypeKind::Array(_, _, false) = m.type_kind {
+            if is_union || i + 1 != members.len() || members.len() == 1 {
+                return Err(Error::invalid_flexible_array(
+                    iter.filepath.clone(),
+                    iter.input.clone(),
+                    iter.pos,
+                ));
             }
-            return Ok((
-                Node::new_expr_stmt(Node::new_init(NodeKind::Lvar(lvar), init)),
-                type_kind.clone(),
+        } else if let TypeKind::Vla(_, _) = m.type_kind {
+            return Err(Error::invalid_vla(
+                iter.filepath.clone(),
+                iter.input.clone(),
+                iter.pos,
+                format!("member {} has variable length array type", m.ident.name),
+            ));
+        } else if m.type_kind.is_incomplete() {
+            return Err(Error::incomplete_type(
+                iter.filepath.clone(),
+                iter.input.clone(),
+                iter.pos,
+                format!("field {} has incomplete type", m.ident.name),
+                suggest_incomplete_tag(ctx, &m.type_kind),
             ));
         }
-        TypeKind::Struct(_struct) => {
-            let members = _struct.borrow().members.clone();
-            let mut init = Vec::new();
-            let mut i = 0;
-            let open = consume_block(iter, Block::LParen);
-            if !peek_end(iter) {
-                while {
-                    let mut desg2 = Some(Box::new(Designator::new(
-                        0,
-                        desg.clone(),
-                        Some(members[i].clone()),
-                    )));
-                    let node = lvar_initializer(
-                        iter,
-                        ctx,
-                        lvar.clone(),
-                        // todo struct {} だとパニックになる。
-                        // あとrefcellとかもう少しいいやり方ありそう
-                        Rc::new(RefCell::new(members[i].type_kind.as_ref().clone())),
-                        &mut desg2,
-                    )?;
-                    i += 1;
-                    init.push(node.0);
-                    i < members.len() && !peek_end(iter) && consume_comma(iter)
-                } {}
-            }
-            if open && !consume_end(iter) {
-                skip_excess_elements(iter, ctx)?;
+    }
+
+    let align = members.iter().map(|m| m.align()).fold(8, u64::max);
+    let mut offset = 0;
+    let members: Vec<Rc<Member>> = members
+        .into_iter()
+        .map(|m| {
+            offset = base_types::align_to(offset, m.align());
+            let _offset = offset;
+            if !is_union {
+                offset += m.type_kind.size();
             }
-            while members.len() > i {
-                let mut desg2 = Some(Box::new(Designator::new(
-                    0,
-                    desg.clone(),
-                    Some(members[i].clone()),
-                )));
+            let mem = Member::new(Rc::new(m.type_kind), _offset, m.ident);
+            Rc::new(mem)
+        })
+        .collect();
 
-                let node = lvar_init_zero(
-                    iter,
-                    ctx,
-                    lvar.clone(),
-                    // todo struct {} だとパニックになる。
-                    // あとrefcellとかもう少しいいやり方ありそう
-                    Rc::new(RefCell::new(members[i].type_kind.as_ref().clone())),
-                    &mut desg2,
-                )?;
-                init.push(node);
-                i += 1;
+    _struct.borrow_mut().members = Rc::new(members);
+    _struct.borrow_mut().is_incomplete = false;
+    _struct.borrow_mut().is_union = is_union;
+    _struct.borrow_mut().align = align;
+    Ok(_struct)
+}
+
+// enum-specifier          = enum ident? "{" enum-list? "}"
+//                         | enum ident
+// enum-list               = enum-elem ("," enum-elem)* ","?
+// enum-elem               = ident ("=" const-expr)?
+pub fn enum_specifier(iter: &mut TokenStream, ctx: &mut Context) -> Result<Rc<Enum>, Error> {
+    expect_keyword(iter, KeyWord::Enum)?;
+    let tag = consume_ident(iter);
+    if !consume_block(iter, Block::LParen) {
+        match &tag {
+            Some(x) => {
+                if let Some(tag) = ctx.s.find_upper_tag(Rc::new(x.clone())) {
+                    if let TagTypeKind::Enum(_enum) = tag.as_ref() {
+                        return Ok(_enum.clone());
+                    }
+                } else {
+                    iter.prev();
+                    return Err(Error::undefined_tag(
+                        iter.filepath.clone(),
+                        iter.input.clone(),
+                        iter.pos,
+                        x.clone(),
+                        suggest(&x.name, ctx.s.visible_tags()),
+                    ));
+                }
+            }
+            None => {
+                dbg!("incomplete enum dec");
+                return Err(Error::todo(
+                    iter.filepath.clone().clone(),
+                    iter.input.clone().clone(),
+                    iter.pos,
+                ));
             }
+        }
+    }
 
-            return Ok((
-                Node::new_expr_stmt(Node::new_init(NodeKind::Lvar(lvar), init)),
-                type_kind.clone(),
-            ));
+    if let Some(t) = &tag {
+        if !t.is_anonymous() {
+            if let Some(_) = ctx.s.find_cur_tag(Rc::new(t.clone())) {
+                // iter.prev();
+                // return Err(Error::re_declare(
+                //     iter.filepath.clone().clone(),
+                //     iter.input.clone().clone(),
+                //     t.clone(),
+                //     iter.pos,
+                //     None,
+                // ));
+            }
         }
-        _ => {}
     }
-    let open = consume_block(iter, Block::LParen);
-    let init = new_desg_node(var, desg, assign(iter, ctx)?)?;
-    if open {
-        expect_block(iter, Block::RParen)?;
+
+    let mut enum_list = Vec::new();
+    let mut count = 0;
+    while !consume_block(iter, Block::RParen) {
+        let ident = expect_ident(iter)?;
+        if consume(iter, Operator::Assign) {
+            count = const_expr(iter, ctx)?;
+        }
+
+        ctx.push_front(Declaration::new_const(TypeKind::Int, ident.clone(), count));
+        enum_list.push(Rc::new((ident, count)));
+        count += 1;
+        consume_comma(iter);
     }
-    Ok((
-        Node::new_expr_stmt(Node::new_init(NodeKind::Lvar(lvar), vec![init])),
-        type_kind,
-    ))
-}
 
-// stmt        = expr ";"
-//             | "return" expr? ";"
-//             | "if" "(" expr ")" stmt
-//             | "while" "(" expr ")" stmt
-//             | "do" stmt "while" "(" expr ")" ";"
-//             | "for" "(" stmt? ";" expr? ";" expr? ")" stmt
-//             | "{" stmt* "}"
-//             | declaration ("=" initialize)? ";"
-//             | "break" ";"
-//             | "continue" ";"
-//             | "goto" ident ";"
+    let enum_list = Rc::new(enum_list);
+    let mut _enum = if let Some(tag) = tag {
+        let tag = Rc::new(tag);
+        let _enum = Rc::new(Enum::new(tag.clone(), enum_list));
+        let result = ctx
+            .s
+            .insert_t(tag.clone(), TagTypeKind::Enum(_enum.clone()));
+        if let Some(_) = result {
+            unreachable!()
+        }
+        _enum
+    } else {
+        Rc::new(Enum::new(
+            Rc::new(Ident::new(".struct.anonymous")),
+            enum_list,
+        ))
+    };
+    Ok(_enum)
+}
+
+// declaration     = type-specifier declarator type-suffix
+//                 | type-specifier
+pub(crate) fn declaration(iter: &mut TokenStream, ctx: &mut Context) -> Result<Declaration, Error> {
+    let (type_kind, storage) = type_specifier(iter, ctx)?;
+    declaration_of(iter, ctx, &type_kind, storage)
+}
+
+/// 読み終えた型指定子に続く宣言子を読む
+/// `int a, *b[2];`のように宣言子ごとにポインタや配列の型が変わる
+fn declaration_of(
+    iter: &mut TokenStream,
+    ctx: &mut Context,
+    type_kind: &TypeKind,
+    (is_typedef, is_static, is_extern, alignas): StorageClass,
+) -> Result<Declaration, Error> {
+    let begin = iter.idx;
+    let type_kind = Rc::new(RefCell::new(type_kind.clone()));
+    let mut ident = Ident::new_anonymous();
+    let dec = declarator(iter, ctx, type_kind, &mut ident)?;
+    let type_suffix = type_suffix(iter, ctx, dec)?;
+    let type_suffix = type_suffix.borrow().clone();
+    let mut dec = Declaration::new(type_suffix, ident);
+    dec.span = iter.span_from(begin);
+
+    dec.is_typedef = is_typedef;
+    dec.is_static = is_static;
+    dec.is_extern = is_extern;
+    dec.alignas = alignas;
+
+    match (
+        ctx.s.find_cur_lvar(dec.ident.clone()),
+        ctx.s.find_cur_gvar(dec.ident.clone()),
+    ) {
+        (None, None) => (),
+        _ => {
+            if !dec.ident.is_anonymous() {
+                // iter.prev();
+                // return Err(Error::re_declare(
+                //     iter.filepath.clone().clone(),
+                //     iter.input.clone().clone(),
+                //     dec.ident.clone(),
+                //     iter.pos,
+                //     None,
+                // ));
+            }
+        }
+    }
+
+    if is_typedef {
+        let result = ctx.s.insert_t(
+            Rc::new(dec.ident.clone().get_typedef_ident()),
+            TagTypeKind::Typedef(Rc::new(dec.clone())),
+        );
+        if let Some(_) = result {
+            // return Err(Error::re_declare(
+            //     iter.filepath.clone().clone(),
+            //     iter.input.clone().clone(),
+            //     dec.ident.clone(),
+            //     iter.pos,
+            //     None,
+            // ));
+        }
+    }
+
+    if is_static {
+        //     let ident = Ident::new(ctx.make_label());
+        //     ctx.g.gvar_mp.insert(
+        //         ident.name.clone(),
+        //         Rc::new(check_g_var(
+        //             iter,
+        //             &ctx.g.gvar_mp,
+        //             dec.type_kind.clone(),
+        //             ident,
+        //             vec![],
+        //         )?),
+        //     );
+        //     // ctx.s.push_front(dec.clone(),)
+    }
+    Ok(dec)
+}
+
+// function    =  type-specifier declarator "(" params? ")" "{" stmt* "}"
+pub fn function(
+    iter: &mut TokenStream,
+    func_prototype: Rc<FuncPrototype>,
+    ctx: &mut Context,
+    is_variadic: bool,
+) -> Result<Function, Error> {
+    expect_block(iter, Block::LParen)?;
+
+    ctx.l = LocalContext::new();
+    ctx.vla = VlaContext::new();
+    ctx.cur_func = Some(func_prototype.clone());
+    for fn_param in func_prototype.params.clone() {
+        let tmp_lvar = Var::L(Rc::new(RefCell::new(Lvar::new_leaf(fn_param.clone(), 0))));
+        ctx.push_scope(fn_param.ident, Rc::new(tmp_lvar));
+    }
+    let lvar = if is_variadic {
+        let ident = Ident::new("__va_area__");
+        let type_kind = TypeKind::array_of(136, Rc::new(RefCell::new(TypeKind::Char)), true);
+        let dec = Declaration::new(type_kind, ident.clone());
+        ctx.push_front(dec);
+        ctx.s.find_cur_lvar(ident).map(|v| v.borrow().clone())
+    } else {
+        None
+    };
+    let ret_ptr = if func_prototype.has_ret_ptr() {
+        let ident = Ident::new("__ret_ptr__");
+        let type_kind = TypeKind::ptr_to(Rc::new(RefCell::new(func_prototype.type_kind.clone())));
+        let dec = Declaration::new(type_kind, ident.clone());
+        ctx.push_front(dec);
+        ctx.s.find_cur_lvar(ident).map(|v| v.borrow().clone())
+    } else {
+        None
+    };
+
+    let mut stmt_vec = Vec::new();
+    loop {
+        if consume_block(iter, Block::RParen) {
+            let is_static = func_prototype.is_static;
+            for fn_param in func_prototype.params.clone() {
+                let l = ctx
+                    .l
+                    .lvar
+                    .as_ref()
+                    .map(|lvar| lvar.borrow().offset)
+                    .unwrap_or(0);
+                // ctx.push_front の代わりに自分で更新する
+                ctx.l.push_front(fn_param.clone(), l);
+                let offset = l + fn_param.type_kind.size();
+                let offset = base_types::align_to(offset, fn_param.align());
+                ctx.s
+                    .find_upper_lvar(fn_param.ident.clone())
+                    .unwrap()
+                    .borrow_mut()
+                    .offset = offset;
+            }
+            for fn_param in &func_prototype.params {
+                bind_vla_params(ctx, &fn_param.type_kind);
+            }
+            let mut function = Function::new(
+                func_prototype,
+                ctx.l.lvar.clone(),
+                ctx.l.lvar_count.clone(),
+                stmt_vec,
+                is_static,
+                lvar,
+            );
+            function.ret_ptr = ret_ptr;
+            for (ident, pos) in std::mem::take(&mut ctx.l.gotos) {
+                if !ctx.l.labels.contains(&ident.name) {
+                    ctx.diagnostics.push(Error::undefined_label(
+                        iter.filepath.clone(),
+                        iter.input.clone(),
+                        pos,
+                        ident,
+                    ));
+                }
+            }
+            ctx.vla.resolve_gotos();
+            add_conversions(&mut function);
+            for node in &mut function.nodes {
+                node.fill_span();
+            }
+            return Ok(function);
+        }
+        if let Some(node) = stmt_or_recover(iter, ctx)? {
+            stmt_vec.push(node);
+        }
+    }
+}
+
+// params      = declaration ("," declaration)* ("," "...")? | "void"
+/// 引数の可変長配列の大きさの式は`params`で作った仮の変数を指しているので、引数の位置を教える
+fn bind_vla_params(ctx: &Context, type_kind: &TypeKind) {
+    match type_kind {
+        TypeKind::Ptr(base) | TypeKind::Array(_, base, _) => bind_vla_params(ctx, &base.borrow()),
+        TypeKind::Vla(base, size) => {
+            size.walk(&mut |node| {
+                if let NodeKind::Lvar(lvar) = &node.kind {
+                    let ident = lvar.borrow().dec.ident.clone();
+                    if let Some(param) = ctx.s.find_upper_lvar(ident) {
+                        if !Rc::ptr_eq(&param, lvar) {
+                            lvar.borrow_mut().offset = param.borrow().offset;
+                        }
+                    }
+                }
+            });
+            bind_vla_params(ctx, &base.borrow());
+        }
+        _ => (),
+    }
+}
+
+pub fn params(
+    iter: &mut TokenStream,
+    ctx: &mut Context,
+) -> Result<(Vec<Declaration>, bool), Error> {
+    let idx = iter.idx;
+    if consume_type_kind(iter) == Some(TypeKind::Void) && consume(iter, Operator::RParen) {
+        return Ok((Vec::new(), false));
+    }
+    iter.idx = idx;
+    // `int f(int n, int a[n])`のように前の引数を参照できるようにする
+    let sc = ctx.s.enter();
+    let mut params = vec![read_param(iter, ctx)?];
+    let mut is_variadic = false;
+    while !consume(iter, Operator::RParen) {
+        expect_comma(iter)?;
+        if consume(iter, Operator::ThreeDots) {
+            expect(iter, Operator::RParen)?;
+            is_variadic = true;
+            break;
+        }
+        params.push(read_param(iter, ctx)?);
+    }
+    ctx.s.leave(sc);
+    Ok((params, is_variadic))
+}
+
+pub fn read_param(iter: &mut TokenStream, ctx: &mut Context) -> Result<Declaration, Error> {
+    let mut dec = declaration(iter, ctx)?;
+    if let TypeKind::Array(_, base, _) | TypeKind::Vla(base, _) = &dec.type_kind {
+        dec.type_kind = TypeKind::ptr_to(base.clone());
+    }
+    // 関数型の引数は関数へのポインタになる
+    if let TypeKind::Func(_, _, _) = dec.type_kind {
+        dec.type_kind = TypeKind::ptr_to(Rc::new(RefCell::new(dec.type_kind.clone())));
+    }
+    let tmp_lvar = Var::L(Rc::new(RefCell::new(Lvar::new_leaf(dec.clone(), 0))));
+    ctx.push_scope(dec.ident.clone(), Rc::new(tmp_lvar));
+    Ok(dec)
+}
+
+fn lvar_initializer(
+    iter: &mut TokenStream,
+    ctx: &mut Context,
+    lvar: Rc<RefCell<Lvar>>,
+    type_kind: Rc<RefCell<TypeKind>>,
+) -> Result<(Node, Rc<RefCell<TypeKind>>), Error> {
+    let mut tree = InitTree::new(type_kind.borrow().clone());
+    initializer(iter, ctx, &mut tree)?;
+    let mut init = Vec::new();
+    lvar_init_nodes(&Var::L(lvar.clone()), &tree, &mut None, &mut init)?;
+    Ok((
+        Node::new_expr_stmt(Node::new_init(NodeKind::Lvar(lvar), init)),
+        Rc::new(RefCell::new(tree.type_kind)),
+    ))
+}
+
+/// 初期化子の木を代入文に展開する
+/// 初期化子のない要素は0にする
+fn lvar_init_nodes(
+    var: &Var,
+    tree: &InitTree,
+    desg: &mut Option<Box<Designator>>,
+    init: &mut Vec<Node>,
+) -> Result<(), Error> {
+    init.extend(tree.prelude.iter().cloned());
+    match &tree.type_kind {
+        TypeKind::Array(_, _, _) => {
+            for (i, child) in tree.children.iter().enumerate() {
+                let mut desg2 = Some(Box::new(Designator::new(i as u64, desg.clone(), None)));
+                lvar_init_nodes(var, child, &mut desg2, init)?;
+            }
+            Ok(())
+        }
+        TypeKind::Struct(_struct) if tree.expr.is_none() => {
+            let members = _struct.borrow().members.clone();
+            let is_union = _struct.borrow().is_union;
+            for (i, child) in tree.children.iter().enumerate() {
+                if is_union && i != tree.member.unwrap_or(0) {
+                    continue;
+                }
+                let mut desg2 = Some(Box::new(Designator::new(
+                    0,
+                    desg.clone(),
+                    Some(members[i].clone()),
+                )));
+                lvar_init_nodes(var, child, &mut desg2, init)?;
+            }
+            Ok(())
+        }
+        _ => {
+            let rhs = tree.expr.clone().unwrap_or_else(|| Node::new_num(0));
+            init.push(new_desg_node(var.clone(), desg, rhs)?);
+            Ok(())
+        }
+    }
+}
+
+// stmt        = expr ";"
+//             | "return" expr? ";"
+//             | "if" "(" expr ")" stmt
+//             | "while" "(" expr ")" stmt
+//             | "do" stmt "while" "(" expr ")" ";"
+//             | "for" "(" stmt? ";" expr? ";" expr? ")" stmt
+//             | "{" stmt* "}"
+//             | type-specifier init-declarator-list? ";"
+//             | "break" ";"
+//             | "continue" ";"
+//             | "goto" ident ";"
 //             | ident ":" stmt
 //             | "switch" "("expr")" stmt
 //             | "case" const-expr ":" stmt
 //             | "default" ":" stmt
+//             | static-assert
 pub fn stmt(iter: &mut TokenStream, ctx: &mut Context) -> Result<Node, Error> {
+    let begin = iter.idx;
+    let node = stmt_body(iter, ctx)?;
+    Ok(node.with_span(iter.span_from(begin)))
+}
+
+/// ブロックの中の文を1つ読む
+/// 失敗したらエラーを記録して次の文まで読み飛ばし、`None`を返す
+fn stmt_or_recover(iter: &mut TokenStream, ctx: &mut Context) -> Result<Option<Node>, Error> {
+    let begin = iter.idx;
+    match stmt(iter, ctx) {
+        Ok(node) => Ok(Some(node)),
+        Err(err) => {
+            recover(iter, ctx, begin, err)?;
+            Ok(None)
+        }
+    }
+}
+
+/// `stmt`の本体。ソース上の位置は`stmt`でまとめて付ける
+fn stmt_body(iter: &mut TokenStream, ctx: &mut Context) -> Result<Node, Error> {
     if let Some(x) = iter.peek() {
         match x.kind {
             TokenKind::KeyWord(key) => {
                 match key {
                     KeyWord::Return => {
                         iter.next();
+                        let pos = iter.pos;
                         if consume_semi(iter) {
                             return Ok(Node::new_leaf(NodeKind::Return));
                         }
-                        let node = Node::new_unary(NodeKind::Return, expr(iter, ctx)?);
+                        let node = expr(iter, ctx)?;
+                        if let Some(func_prototype) = ctx.cur_func.clone() {
+                            check_assignment(
+                                iter,
+                                ctx,
+                                pos,
+                                &func_prototype.type_kind,
+                                &node,
+                                "return",
+                            )?;
+                        }
+                        let node = Node::new_unary(NodeKind::Return, node);
                         expect_semi(iter)?;
                         return Ok(node);
                     }
@@ -980,13 +1659,17 @@ pub fn stmt(iter: &mut TokenStream, ctx: &mut Context) -> Result<Node, Error> {
                         expect(iter, Operator::LParen)?;
                         let mut node = Node::new_cond(NodeKind::While, expr(iter, ctx)?);
                         expect(iter, Operator::RParen)?;
+                        ctx.vla.enter_loop();
                         node.then = Some(Box::new(stmt(iter, ctx)?));
+                        ctx.vla.leave_loop();
                         return Ok(node);
                     }
                     KeyWord::Do => {
                         iter.next();
                         let mut node = Node::new_leaf(NodeKind::Do);
+                        ctx.vla.enter_loop();
                         node.then = Some(Box::new(stmt(iter, ctx)?));
+                        ctx.vla.leave_loop();
                         expect_keyword(iter, KeyWord::While)?;
                         expect(iter, Operator::LParen)?;
                         node.cond = Some(Box::new(expr(iter, ctx)?));
@@ -999,6 +1682,7 @@ pub fn stmt(iter: &mut TokenStream, ctx: &mut Context) -> Result<Node, Error> {
                         expect(iter, Operator::LParen)?;
                         let mut node = Node::new_none(NodeKind::For);
                         let sc = ctx.s.enter();
+                        let depth = ctx.vla.sp.len();
 
                         if !consume_semi(iter) {
                             node.init = Some(vec![stmt(iter, ctx)?]);
@@ -1011,25 +1695,45 @@ pub fn stmt(iter: &mut TokenStream, ctx: &mut Context) -> Result<Node, Error> {
                             node.inc = Some(Box::new(read_expr_stmt(iter, ctx)?));
                             expect(iter, Operator::RParen)?;
                         }
+                        ctx.vla.enter_loop();
                         node.then = Some(Box::new(stmt(iter, ctx)?));
+                        ctx.vla.leave_loop();
                         ctx.s.leave(sc);
+                        if let Some(free) = ctx.vla.free_to(depth) {
+                            ctx.vla.sp.truncate(depth);
+                            return Ok(Node::new_none(NodeKind::Block(vec![node, free])));
+                        }
                         return Ok(node);
                     }
                     KeyWord::Break => {
                         iter.next();
                         expect_semi(iter)?;
-                        return Ok(Node::new_leaf(NodeKind::Break));
+                        let depth = ctx.vla.break_depth.last().cloned();
+                        return Ok(ctx.vla.free_before(depth, Node::new_leaf(NodeKind::Break)));
                     }
                     KeyWord::Continue => {
                         iter.next();
                         expect_semi(iter)?;
-                        return Ok(Node::new_leaf(NodeKind::Continue));
+                        let depth = ctx.vla.continue_depth.last().cloned();
+                        return Ok(ctx
+                            .vla
+                            .free_before(depth, Node::new_leaf(NodeKind::Continue)));
                     }
                     KeyWord::Goto => {
                         iter.next();
                         let ident = expect_ident(iter)?;
+                        ctx.l.gotos.push((ident.clone(), iter.pos));
                         expect_semi(iter)?;
-                        return Ok(Node::new_leaf(NodeKind::Goto(ident)));
+                        let node = Node::new_leaf(NodeKind::Goto(ident.clone()));
+                        if ctx.vla.sp.is_empty() {
+                            return Ok(node);
+                        }
+                        // 解放するVLAは飛び先のラベルが分かってから決める
+                        let target = Rc::new(RefCell::new(None));
+                        let sp = ctx.vla.sp.clone();
+                        ctx.vla.gotos.push((ident.name, sp, target.clone()));
+                        let free = Node::new_leaf(NodeKind::VlaFree(target));
+                        return Ok(Node::new_none(NodeKind::Block(vec![free, node])));
                     }
                     KeyWord::Switch => {
                         iter.next();
@@ -1039,11 +1743,15 @@ pub fn stmt(iter: &mut TokenStream, ctx: &mut Context) -> Result<Node, Error> {
                         expect(iter, Operator::RParen)?;
 
                         let sw = std::mem::replace(&mut ctx.cur_switch, Some(vec![]));
-                        let then = Some(Box::new(stmt(iter, ctx)?));
+                        ctx.vla.break_depth.push(ctx.vla.sp.len());
+                        let then = stmt(iter, ctx);
+                        ctx.vla.break_depth.pop();
 
+                        // 本体の解析に失敗しても、外側のswitchの状態は戻しておく
                         let cases = std::mem::replace(&mut ctx.cur_switch, sw).ok_or(
                             Error::todo(iter.filepath.clone(), iter.input.clone(), iter.pos),
                         )?;
+                        let then = Some(Box::new(then?));
                         let mut node = Node::new_leaf(NodeKind::Switch(cases));
                         node.cond = cond;
                         node.then = then;
@@ -1053,13 +1761,22 @@ pub fn stmt(iter: &mut TokenStream, ctx: &mut Context) -> Result<Node, Error> {
                         iter.next();
                         match ctx.cur_switch {
                             Some(_) => {
-                                let val = const_expr(iter, ctx)?;
+                                let pos = iter.pos;
+                                let low = const_expr(iter, ctx)?;
+                                // GNU拡張の`case 1 ... 5:`
+                                let high = if consume(iter, Operator::ThreeDots) {
+                                    const_expr(iter, ctx)?
+                                } else {
+                                    low
+                                };
                                 expect_colon(iter)?;
-                                let node = Node::new_unary(NodeKind::Case(val), stmt(iter, ctx)?);
-
-                                let mut cur_case = ctx.cur_switch.take().unwrap();
-                                cur_case.push(node.clone());
-                                ctx.cur_switch = Some(cur_case);
+                                check_case(iter, ctx, pos, low, high);
+                                // 本体の中の`case`より先に登録して、出てくる順に並べる
+                                if let Some(cur_case) = &mut ctx.cur_switch {
+                                    cur_case.push(Node::new_leaf(NodeKind::Case(low, high)));
+                                }
+                                let node =
+                                    Node::new_unary(NodeKind::Case(low, high), stmt(iter, ctx)?);
                                 return Ok(node);
                             }
                             None => {
@@ -1073,12 +1790,25 @@ pub fn stmt(iter: &mut TokenStream, ctx: &mut Context) -> Result<Node, Error> {
                     }
                     KeyWord::Default => {
                         iter.next();
-                        match ctx.cur_switch.take() {
-                            Some(mut cur_case) => {
+                        match ctx.cur_switch {
+                            Some(_) => {
+                                let pos = iter.pos;
                                 expect_colon(iter)?;
+                                if let Some(cur_case) = &mut ctx.cur_switch {
+                                    if cur_case
+                                        .iter()
+                                        .any(|case| case.kind == NodeKind::DefaultCase)
+                                    {
+                                        ctx.diagnostics.push(Error::multiple_default(
+                                            iter.filepath.clone(),
+                                            iter.input.clone(),
+                                            pos,
+                                        ));
+                                    } else {
+                                        cur_case.push(Node::new_leaf(NodeKind::DefaultCase));
+                                    }
+                                }
                                 let node = Node::new_unary(NodeKind::DefaultCase, stmt(iter, ctx)?);
-                                cur_case.push(node.clone());
-                                ctx.cur_switch = Some(cur_case);
                                 return Ok(node);
                             }
                             None => {
@@ -1091,6 +1821,10 @@ pub fn stmt(iter: &mut TokenStream, ctx: &mut Context) -> Result<Node, Error> {
                         }
                     }
 
+                    KeyWord::StaticAssert => {
+                        static_assert(iter, ctx)?;
+                        return Ok(Node::new_leaf(NodeKind::Null));
+                    }
                     _ => (),
                 }
             }
@@ -1099,11 +1833,19 @@ pub fn stmt(iter: &mut TokenStream, ctx: &mut Context) -> Result<Node, Error> {
                     iter.next();
                     let mut stmt_vec = Vec::new();
                     let sc = ctx.s.enter();
+                    let depth = ctx.vla.sp.len();
 
                     while !consume_block(iter, Block::RParen) {
-                        stmt_vec.push(stmt(iter, ctx)?);
+                        if let Some(node) = stmt_or_recover(iter, ctx)? {
+                            stmt_vec.push(node);
+                        }
                     }
                     ctx.s.leave(sc);
+                    // ブロック内で確保したVLAを解放する
+                    if let Some(free) = ctx.vla.free_to(depth) {
+                        stmt_vec.push(free);
+                        ctx.vla.sp.truncate(depth);
+                    }
 
                     return Ok(Node::new_none(NodeKind::Block(stmt_vec)));
                 }
@@ -1120,10 +1862,26 @@ pub fn stmt(iter: &mut TokenStream, ctx: &mut Context) -> Result<Node, Error> {
         }
     }
 
+    if consume_fallthrough(iter) {
+        return Ok(Node::new_leaf(NodeKind::Fallthrough));
+    }
+
     {
         let i_data = iter.save();
         if let Some(ident) = consume_ident(iter) {
+            let pos = iter.pos;
             if consume_colon(iter) {
+                if !ctx.l.labels.insert(ident.name.clone()) {
+                    ctx.diagnostics.push(Error::duplicate_label(
+                        iter.filepath.clone(),
+                        iter.input.clone(),
+                        pos,
+                        ident.clone(),
+                    ));
+                }
+                ctx.vla
+                    .labels
+                    .insert(ident.name.clone(), ctx.vla.sp.clone());
                 return Ok(Node::new_unary(NodeKind::Label(ident), stmt(iter, ctx)?));
             } else {
                 iter.restore(i_data);
@@ -1132,66 +1890,42 @@ pub fn stmt(iter: &mut TokenStream, ctx: &mut Context) -> Result<Node, Error> {
     }
 
     if is_typename(iter, ctx) {
-        let mut dec = declaration(iter, ctx)?;
-
-        // static local var
-        if dec.is_static {
-            if dec.ident.is_anonymous() || dec.is_typedef {
-                return Ok(Node::new_leaf(NodeKind::Null));
-            }
-            let size = dec.type_kind.size();
-            let mut label = ctx.make_label();
-            std::mem::swap(&mut dec.ident.name, &mut label);
-
-            let mut init = Vec::new();
-            let type_kind = Rc::new(RefCell::new(dec.type_kind));
-            if consume(iter, Operator::Assign) {
-                gvar_initializer(iter, ctx, &mut init, type_kind.clone())?;
-            }
-            dec.type_kind = type_kind.borrow().clone();
-
-            let gvar = Rc::new(Gvar::new(dec.clone(), size, init));
-            ctx.g.gvar_mp.insert(dec.ident.name.clone(), gvar.clone());
-            ctx.s.insert_v(Ident::new(label), Rc::new(Var::G(gvar)));
-            expect_semi(iter)?;
-            return Ok(Node::new_leaf(NodeKind::Declaration(dec)));
-        }
-
-        // todo re declaration err handling
-        if consume_semi(iter) {
-            if dec.ident.is_anonymous() || dec.is_typedef {
-                return Ok(Node::new_leaf(NodeKind::Null));
+        let (type_kind, storage) = type_specifier(iter, ctx)?;
+        let mut nodes = Vec::new();
+        loop {
+            let dec = declaration_of(iter, ctx, &type_kind, storage)?;
+            let ident = dec.ident.clone();
+            match local_declaration(iter, ctx, dec) {
+                Ok(node) => nodes.push(node),
+                Err(err) => {
+                    // 初期化子より前で失敗して、まだ登録していない変数だけを残す
+                    if !storage.0
+                        && !ident.is_anonymous()
+                        && ctx.s.find_cur_lvar(ident.clone()).is_none()
+                    {
+                        ctx.failed_decls.push(ident);
+                    }
+                    return Err(err);
+                }
             }
-            if dec.is_extern {
-                let size = dec.type_kind.size();
-                let gvar = Rc::new(Gvar::new(dec.clone(), size, vec![]));
-                ctx.s.insert_v(dec.ident.clone(), Rc::new(Var::G(gvar)));
-            } else {
-                ctx.push_front(dec.clone());
+            if !consume_comma(iter) {
+                break;
             }
-            return Ok(Node::new_leaf(NodeKind::Declaration(dec)));
+            expect_declarator(iter)?;
         }
-        expect(iter, Operator::Assign)?;
-
-        ctx.push_front(dec.clone());
-        let lvar = ctx.s.find_cur_lvar(dec.ident.clone()).unwrap();
-        let (node, type_kind) = lvar_initializer(
-            iter,
-            ctx,
-            lvar.clone(),
-            Rc::new(RefCell::new(lvar.borrow().dec.type_kind.clone())),
-            &mut None,
-        )?;
-        lvar.borrow_mut().dec.type_kind = type_kind.borrow().clone();
-        lvar.borrow_mut().offset += type_kind.borrow().size();
         expect_semi(iter)?;
-        return Ok(node);
+        if nodes.len() == 1 {
+            return Ok(nodes.pop().unwrap());
+        }
+        return Ok(Node::new_none(NodeKind::Block(nodes)));
     }
 
     if let Some(token) = iter.next() {
         if let TokenKind::Ident(x) = token.kind {
             if x.name == "va_start" && consume(iter, Operator::LParen) {
-                func_args(iter, ctx)?;
+                let mut va_start = FuncPrototype::new(TypeKind::Void, x.into(), Vec::new(), false);
+                va_start.has_prototype = false;
+                func_args(iter, ctx, &va_start)?;
                 expect_semi(iter)?;
                 let ipt = "*ap = (__va_elem)__va_area__;";
                 let _stmt = stmt(
@@ -1211,6 +1945,188 @@ pub fn stmt(iter: &mut TokenStream, ctx: &mut Context) -> Result<Node, Error> {
     Ok(node)
 }
 
+/// ブロックスコープの宣言子を1つ処理する
+/// 初期化子があれば代入文を返す。`;`や`,`は呼び出し側で読む
+fn local_declaration(
+    iter: &mut TokenStream,
+    ctx: &mut Context,
+    mut dec: Declaration,
+) -> Result<Node, Error> {
+    // 同じスコープの変数と同じ名前は使えない
+    if !dec.is_typedef && !dec.is_extern && !dec.ident.is_anonymous() {
+        if let Some(prev) = ctx.s.find_cur_lvar(dec.ident.clone()) {
+            let err = Error::re_declare(
+                iter.filepath.clone(),
+                iter.input.clone(),
+                dec.ident.clone(),
+                dec.span.pos(),
+                None,
+            );
+            return Err(with_previous(err, &prev.borrow().dec.span, "declaration"));
+        }
+    }
+    if let TypeKind::Vla(base, size) = dec.type_kind.clone() {
+        if !dec.is_typedef {
+            return vla_declaration(iter, ctx, dec, base, size);
+        }
+    }
+
+    // ブロックスコープの関数宣言
+    if !dec.is_typedef {
+        if let Some(mut func_prototype) =
+            FuncPrototype::from_type(&dec.type_kind, dec.ident.clone(), dec.is_static)
+        {
+            func_prototype.span = dec.span.clone();
+            let func_prototype =
+                check_func_prototype(iter, &ctx.g.func_prototype_mp, func_prototype)?;
+            ctx.g
+                .func_prototype_mp
+                .insert(dec.ident.name.clone(), Rc::new(func_prototype));
+            return Ok(Node::new_leaf(NodeKind::Null));
+        }
+    }
+
+    // static local var
+    if dec.is_static {
+        if dec.ident.is_anonymous() || dec.is_typedef {
+            return Ok(Node::new_leaf(NodeKind::Null));
+        }
+        if !is_next(iter, Operator::Assign) {
+            expect_complete_object(iter, ctx, &dec)?;
+        }
+        let size = dec.type_kind.size();
+        ctx.usage.static_locals.push(dec.clone());
+        let mut label = ctx.make_label();
+        std::mem::swap(&mut dec.ident.name, &mut label);
+
+        let mut init = Vec::new();
+        let type_kind = Rc::new(RefCell::new(dec.type_kind));
+        if consume(iter, Operator::Assign) {
+            gvar_initializer(iter, ctx, &mut init, type_kind.clone())?;
+        }
+        dec.type_kind = type_kind.borrow().clone();
+
+        let gvar = Rc::new(Gvar::new(dec.clone(), size, init));
+        ctx.g.gvar_mp.insert(dec.ident.name.clone(), gvar.clone());
+        ctx.s.insert_v(Ident::new(label), Rc::new(Var::G(gvar)));
+        return Ok(Node::new_leaf(NodeKind::Declaration(dec)));
+    }
+
+    // todo re declaration err handling
+    if !consume(iter, Operator::Assign) {
+        if dec.ident.is_anonymous() || dec.is_typedef {
+            return Ok(Node::new_leaf(NodeKind::Null));
+        }
+        if dec.is_extern {
+            let size = dec.type_kind.size();
+            let gvar = Rc::new(Gvar::new(dec.clone(), size, vec![]));
+            ctx.s.insert_v(dec.ident.clone(), Rc::new(Var::G(gvar)));
+        } else {
+            expect_complete_object(iter, ctx, &dec)?;
+            ctx.push_front(dec.clone());
+        }
+        return Ok(Node::new_leaf(NodeKind::Declaration(dec)));
+    }
+    // 要素数のない配列は初期化子から大きさが決まる
+    if !matches!(dec.type_kind, TypeKind::Array(_, _, false)) {
+        expect_complete_object(iter, ctx, &dec)?;
+    }
+
+    ctx.push_front(dec.clone());
+    let lvar = ctx.s.find_cur_lvar(dec.ident.clone()).unwrap();
+    let old_size = dec.type_kind.size();
+    let (node, type_kind) = lvar_initializer(
+        iter,
+        ctx,
+        lvar.clone(),
+        Rc::new(RefCell::new(lvar.borrow().dec.type_kind.clone())),
+    )?;
+    lvar.borrow_mut().dec.type_kind = type_kind.borrow().clone();
+    // 初期化子で要素数が決まった配列はその分だけ領域を広げる
+    let offset = lvar.borrow().offset + type_kind.borrow().size() - old_size;
+    let offset = base_types::align_to(offset, lvar.borrow().dec.align());
+    lvar.borrow_mut().offset = offset;
+    Ok(node)
+}
+
+/// 可変長配列の宣言
+/// バイト数を隠れた変数に保存して、その大きさの領域をスタックに確保する
+fn vla_declaration(
+    iter: &mut TokenStream,
+    ctx: &mut Context,
+    mut dec: Declaration,
+    base: Rc<RefCell<TypeKind>>,
+    size: Rc<Node>,
+) -> Result<Node, Error> {
+    if dec.is_static || dec.is_extern {
+        return Err(Error::invalid_vla(
+            iter.filepath.clone(),
+            iter.input.clone(),
+            iter.pos,
+            "variable length array cannot have static or extern storage",
+        ));
+    }
+    if is_next(iter, Operator::Assign) {
+        return Err(Error::invalid_vla(
+            iter.filepath.clone(),
+            iter.input.clone(),
+            iter.pos,
+            "variable-sized object may not be initialized",
+        ));
+    }
+
+    // sizeofや要素の位置の計算では保存しておいたバイト数を読む
+    let mut stmts = Vec::new();
+    dec.type_kind = save_vla_size(ctx, TypeKind::Vla(base, size), &mut stmts);
+    let size = match &dec.type_kind {
+        TypeKind::Vla(_, size) => size.as_ref().clone(),
+        _ => unreachable!(),
+    };
+    let sp_ident = Ident::new("__vla_sp__");
+    ctx.push_front(Declaration::new(TypeKind::Long, sp_ident.clone()));
+    let sp_var = ctx.s.find_cur_lvar(sp_ident).unwrap();
+    ctx.push_front(dec.clone());
+    let lvar = ctx.s.find_cur_lvar(dec.ident).unwrap();
+
+    stmts.push(Node::new(
+        NodeKind::VlaAlloc(sp_var.clone()),
+        Node::new_lvar(lvar),
+        size,
+    ));
+    ctx.vla.sp.push(sp_var);
+    Ok(Node::new_none(NodeKind::Block(stmts)))
+}
+
+/// 可変長配列とその要素の可変長配列のバイト数を、内側から順に隠れた変数に保存する
+fn save_vla_size(ctx: &mut Context, type_kind: TypeKind, stmts: &mut Vec<Node>) -> TypeKind {
+    let (base, size) = match type_kind {
+        TypeKind::Vla(base, size) => (base, size),
+        type_kind => return type_kind,
+    };
+    let base = save_vla_size(ctx, base.borrow().clone(), stmts);
+    // `type_suffix`で要素数と要素の大きさの積にしているので、要素の大きさを保存した値に置き換える
+    let size = match (&size.kind, &base) {
+        (NodeKind::Mul, TypeKind::Vla(_, elem_size)) => Node::new(
+            NodeKind::Mul,
+            size.lhs.as_deref().unwrap().clone(),
+            elem_size.as_ref().clone(),
+        ),
+        _ => size.as_ref().clone(),
+    };
+    let size_ident = Ident::new("__vla_size__");
+    ctx.push_front(Declaration::new(TypeKind::Long, size_ident.clone()));
+    let size_var = ctx.s.find_cur_lvar(size_ident).unwrap();
+    stmts.push(Node::new_expr_stmt(Node::new(
+        NodeKind::Assign,
+        Node::new_lvar(size_var.clone()),
+        size,
+    )));
+    TypeKind::Vla(
+        Rc::new(RefCell::new(base)),
+        Rc::new(Node::new_lvar(size_var)),
+    )
+}
+
 pub fn read_expr_stmt(iter: &mut TokenStream, ctx: &mut Context) -> Result<Node, Error> {
     Ok(Node::new_unary(NodeKind::ExprStmt, expr(iter, ctx)?))
 }
@@ -1225,146 +2141,246 @@ pub fn expr(iter: &mut TokenStream, ctx: &mut Context) -> Result<Node, Error> {
     Ok(node)
 }
 
-fn eval(node: &mut Node) -> Result<i64, Error> {
-    eval2(node, &mut None)
+/// 整数定数式を計算する
+fn eval(iter: &TokenStream, node: &Node) -> Result<i64, Error> {
+    let mut var = None;
+    let val = eval2(iter, node, &mut var)?;
+    if var.is_some() {
+        return Err(not_constant(iter));
+    }
+    Ok(val)
 }
 
-fn eval2(node: &mut Node, var: &mut Option<Gvar>) -> Result<i64, Error> {
+/// アドレス定数も計算する
+/// グローバル変数か関数のアドレスを含む場合は`var`にそのラベルを入れて、オフセットを返す
+fn eval2(iter: &TokenStream, node: &Node, var: &mut Option<Ident>) -> Result<i64, Error> {
     use NodeKind::*;
+    let lhs = || {
+        node.lhs
+            .as_ref()
+            .map(|n| n.as_ref())
+            .ok_or_else(|| not_constant(iter))
+    };
+    let rhs = || {
+        node.rhs
+            .as_ref()
+            .map(|n| n.as_ref())
+            .ok_or_else(|| not_constant(iter))
+    };
     match &node.kind {
-        Add => {
-            let lhs = eval2(node.lhs.as_mut().unwrap(), var)?;
-
-            return Ok(lhs + eval2(&mut node.rhs.as_mut().unwrap(), var)?);
-        }
-        Sub => {
-            let lhs = eval2(node.lhs.as_mut().unwrap(), var)?;
-            return Ok(lhs - eval(&mut node.rhs.as_mut().unwrap())?);
-        }
-        Mul => {
-            return Ok(
-                eval(&mut node.lhs.as_mut().unwrap())? * eval(&mut node.rhs.as_mut().unwrap())?
-            )
-        }
-        Div => {
-            return Ok(
-                eval(&mut node.lhs.as_mut().unwrap())? / eval(&mut node.rhs.as_mut().unwrap())?
-            )
-        }
-        BitAnd => {
-            return Ok(
-                eval(&mut node.lhs.as_mut().unwrap())? & eval(&mut node.rhs.as_mut().unwrap())?
-            )
-        }
-        BitOr => {
-            return Ok(
-                eval(&mut node.lhs.as_mut().unwrap())? | eval(&mut node.rhs.as_mut().unwrap())?
-            )
-        }
-        BitXor => {
-            return Ok(
-                eval(&mut node.lhs.as_mut().unwrap())? | eval(&mut node.rhs.as_mut().unwrap())?
-            )
-        }
-        LShift => {
-            return Ok(
-                eval(&mut node.lhs.as_mut().unwrap())? << eval(&mut node.rhs.as_mut().unwrap())?
-            )
-        }
-        RShift => {
-            return Ok(
-                eval(&mut node.lhs.as_mut().unwrap())? >> eval(&mut node.rhs.as_mut().unwrap())?
-            )
-        }
-        Equal => {
-            if eval(&mut node.lhs.as_mut().unwrap())? == eval(&mut node.rhs.as_mut().unwrap())? {
-                return Ok(1);
-            } else {
-                return Ok(0);
-            }
-        }
-        Neq => {
-            if eval(&mut node.lhs.as_mut().unwrap())? != eval(&mut node.rhs.as_mut().unwrap())? {
-                return Ok(1);
-            } else {
-                return Ok(0);
+        Num(num) => Ok(*num),
+        Add | Sub => {
+            let (lhs, rhs) = (lhs()?, rhs()?);
+            let mut lhs_var = None;
+            let mut rhs_var = None;
+            let mut l = eval2(iter, lhs, &mut lhs_var)?;
+            let mut r = eval2(iter, rhs, &mut rhs_var)?;
+            // ポインタの演算は要素の大きさ倍する
+            let l_size = pointee_size(lhs);
+            let r_size = pointee_size(rhs);
+            match (node.kind == Add, l_size, r_size) {
+                (true, Some(size), None) => r = r.wrapping_mul(size),
+                (true, None, Some(size)) => l = l.wrapping_mul(size),
+                (false, Some(size), None) => r = r.wrapping_mul(size),
+                // ポインタどうしの差は同じ変数を指しているときだけ定数になる
+                (false, Some(size), Some(_)) => {
+                    return match (lhs_var, rhs_var) {
+                        (Some(a), Some(b)) if a == b && size != 0 => Ok(l.wrapping_sub(r) / size),
+                        _ => Err(not_constant(iter)),
+                    };
+                }
+                (_, None, None) => (),
+                _ => return Err(not_constant(iter)),
             }
-        }
-        Lesser => {
-            if eval(&mut node.lhs.as_mut().unwrap())? < eval(&mut node.rhs.as_mut().unwrap())? {
-                return Ok(1);
+            *var = match (lhs_var, rhs_var) {
+                (Some(_), Some(_)) => return Err(not_constant(iter)),
+                (Some(label), None) => Some(label),
+                (None, Some(label)) if node.kind == Add => Some(label),
+                (None, Some(_)) => return Err(not_constant(iter)),
+                (None, None) => None,
+            };
+            let val = if node.kind == Add {
+                l.wrapping_add(r)
             } else {
-                return Ok(0);
+                l.wrapping_sub(r)
+            };
+            if var.is_some() {
+                return Ok(val);
             }
+            Ok(wrap_int(node, val))
         }
-        Leq => {
-            if eval(&mut node.lhs.as_mut().unwrap())? <= eval(&mut node.rhs.as_mut().unwrap())? {
-                return Ok(1);
-            } else {
-                return Ok(0);
+        Mul => Ok(wrap_int(
+            node,
+            eval(iter, lhs()?)?.wrapping_mul(eval(iter, rhs()?)?),
+        )),
+        Div => {
+            let l = eval(iter, lhs()?)?;
+            let r = eval(iter, rhs()?)?;
+            if r == 0 {
+                return Err(Error::invalid_constant_expression(
+                    iter.filepath.clone(),
+                    iter.input.clone(),
+                    iter.pos,
+                    "division by zero in constant expression",
+                ));
             }
+            Ok(wrap_int(node, l.wrapping_div(r)))
         }
+        BitAnd => Ok(eval(iter, lhs()?)? & eval(iter, rhs()?)?),
+        BitOr => Ok(eval(iter, lhs()?)? | eval(iter, rhs()?)?),
+        BitXor => Ok(eval(iter, lhs()?)? ^ eval(iter, rhs()?)?),
+        LShift => Ok(wrap_int(
+            node,
+            eval(iter, lhs()?)?.wrapping_shl(eval(iter, rhs()?)? as u32),
+        )),
+        RShift => Ok(eval(iter, lhs()?)?.wrapping_shr(eval(iter, rhs()?)? as u32)),
+        Equal => Ok((eval(iter, lhs()?)? == eval(iter, rhs()?)?) as i64),
+        Neq => Ok((eval(iter, lhs()?)? != eval(iter, rhs()?)?) as i64),
+        Lesser => Ok((eval(iter, lhs()?)? < eval(iter, rhs()?)?) as i64),
+        Leq => Ok((eval(iter, lhs()?)? <= eval(iter, rhs()?)?) as i64),
+        Greater => Ok((eval(iter, lhs()?)? > eval(iter, rhs()?)?) as i64),
+        Geq => Ok((eval(iter, lhs()?)? >= eval(iter, rhs()?)?) as i64),
         Ternary => {
-            if eval(&mut node.cond.as_mut().unwrap())? != 0 {
-                return eval(&mut node.then.as_mut().unwrap());
+            let cond = node.cond.as_ref().ok_or_else(|| not_constant(iter))?;
+            let then = node.then.as_ref().ok_or_else(|| not_constant(iter))?;
+            let els = node.els.as_ref().ok_or_else(|| not_constant(iter))?;
+            if eval(iter, cond)? != 0 {
+                eval2(iter, then, var)
             } else {
-                return eval(&mut node.els.as_mut().unwrap());
+                eval2(iter, els, var)
             }
         }
-        Comma => return eval(&mut node.rhs.as_mut().unwrap()),
-        Not => {
-            if eval(&mut node.lhs.as_mut().unwrap())? == 0 {
-                return Ok(1);
-            } else {
-                return Ok(0);
+        Comma => eval2(iter, rhs()?, var),
+        Not => Ok((eval(iter, lhs()?)? == 0) as i64),
+        BitNot => Ok(!eval(iter, lhs()?)?),
+        // 右辺は評価されないときは定数でなくてもいい
+        LogAnd => Ok((eval(iter, lhs()?)? != 0 && eval(iter, rhs()?)? != 0) as i64),
+        LogOr => Ok((eval(iter, lhs()?)? != 0 || eval(iter, rhs()?)? != 0) as i64),
+        Cast(type_kind) => {
+            let val = eval2(iter, lhs()?, var)?;
+            if var.is_some() {
+                // アドレスはポインタかlongにだけ変換できる
+                return match type_kind {
+                    base_types::TypeKind::Ptr(_) | base_types::TypeKind::Long => Ok(val),
+                    _ => Err(not_constant(iter)),
+                };
             }
+            Ok(match type_kind {
+                base_types::TypeKind::_Bool => (val != 0) as i64,
+                base_types::TypeKind::Char => val as i8 as i64,
+                base_types::TypeKind::Short => val as i16 as i64,
+                base_types::TypeKind::Int | base_types::TypeKind::Enum(_) => val as i32 as i64,
+                base_types::TypeKind::Long | base_types::TypeKind::Ptr(_) => val,
+                _ => return Err(not_constant(iter)),
+            })
         }
-        BitNot => return Ok(!eval(&mut node.lhs.as_mut().unwrap())?),
-        LogAnd => {
-            if (eval(&mut node.lhs.as_mut().unwrap())? != 0)
-                && (eval(&mut node.rhs.as_mut().unwrap())? != 0)
-            {
-                return Ok(1);
-            } else {
-                return Ok(0);
+        Addr => eval_addr(iter, lhs()?, var),
+        // 配列はポインタに変換される
+        Gvar(gvar) => match gvar.dec.type_kind {
+            base_types::TypeKind::Array(_, _, _) => {
+                *var = Some(gvar.dec.ident.clone());
+                Ok(0)
             }
+            _ => Err(not_constant(iter)),
+        },
+        // 関数は関数へのポインタに変換される
+        FuncName(func_prototype) => {
+            *var = Some(func_prototype.ident.clone());
+            Ok(0)
         }
-        LogOr => {
-            if (eval(&mut node.lhs.as_mut().unwrap())? != 0)
-                || (eval(&mut node.rhs.as_mut().unwrap())? != 0)
-            {
-                return Ok(1);
-            } else {
-                return Ok(0);
+        // `a[1]`が配列のときは`*(a + 1)`もポインタになる
+        // `*f`が関数のときも関数へのポインタになる
+        Deref => match node.get_type() {
+            Ok(base_types::TypeKind::Array(_, _, _)) | Ok(base_types::TypeKind::Func(_, _, _)) => {
+                eval2(iter, lhs()?, var)
             }
+            _ => Err(not_constant(iter)),
+        },
+        _ => Err(not_constant(iter)),
+    }
+}
+
+/// `&`のオペランドのアドレスを計算する
+fn eval_addr(iter: &TokenStream, node: &Node, var: &mut Option<Ident>) -> Result<i64, Error> {
+    use NodeKind::*;
+    match &node.kind {
+        Gvar(gvar) => {
+            *var = Some(gvar.dec.ident.clone());
+            Ok(0)
         }
-        Num(num) => return Ok(*num as i64),
-        Addr => match var {
-            Some(_) => todo!(),
-            None => {
-                if let NodeKind::Gvar(gvar) = &node.lhs.as_ref().unwrap().kind {
-                    *var = Some(gvar.as_ref().clone());
-                    return Ok(0);
-                }
-                todo!()
-            }
+        FuncName(func_prototype) => {
+            *var = Some(func_prototype.ident.clone());
+            Ok(0)
+        }
+        // `&*p`は`p`
+        Deref => match &node.lhs {
+            Some(lhs) => eval2(iter, lhs, var),
+            None => Err(not_constant(iter)),
         },
-        Gvar(gvar) => match var {
-            Some(_) => todo!(),
-            None => {
-                if let crate::base_types::TypeKind::Array(_, _, _) = gvar.dec.type_kind {
-                    *var = Some(gvar.as_ref().clone());
-                    return Ok(0);
-                }
-                todo!()
-            }
+        Member(_, member) => match &node.lhs {
+            Some(lhs) => Ok(eval_addr(iter, lhs, var)? + member.offset as i64),
+            None => Err(not_constant(iter)),
         },
-        _ => todo!(),
+        _ => Err(not_constant(iter)),
+    }
+}
+
+/// 演算の型がintなら結果を32bitに切り詰める
+fn wrap_int(node: &Node, val: i64) -> i64 {
+    match node.get_type() {
+        Ok(TypeKind::Int) => val as i32 as i64,
+        _ => val,
+    }
+}
+
+/// ポインタか配列ならその要素の大きさを返す
+fn pointee_size(node: &Node) -> Option<i64> {
+    match node.get_type() {
+        Ok(TypeKind::Ptr(t)) | Ok(TypeKind::Array(_, t, _)) => Some(t.borrow().size() as i64),
+        _ => None,
+    }
+}
+
+fn not_constant(iter: &TokenStream) -> Error {
+    Error::invalid_constant_expression(
+        iter.filepath.clone(),
+        iter.input.clone(),
+        iter.pos,
+        "expression is not a compile-time constant",
+    )
+}
+
+/// 空ポインタ定数かどうか
+/// 値が0の整数定数式か、それを`void *`にキャストしたもの
+pub(crate) fn is_null_pointer_constant(iter: &TokenStream, node: &Node) -> bool {
+    match node.get_type() {
+        Ok(type_kind) if type_kind.is_integer() || type_kind.is_void_ptr() => {
+            is_const_expr(node) && matches!(eval(iter, node), Ok(0))
+        }
+        _ => false,
+    }
+}
+
+/// `eval`で計算できる式かどうか
+fn is_const_expr(node: &Node) -> bool {
+    use NodeKind::*;
+    let is_const = |node: &Option<Box<Node>>| node.as_ref().is_some_and(|n| is_const_expr(n));
+    match &node.kind {
+        Num(_) => true,
+        Add | Sub | Mul | Div | BitAnd | BitOr | BitXor | LShift | RShift | Equal | Neq
+        | Lesser | Leq | Greater | Geq | LogAnd | LogOr => {
+            is_const(&node.lhs) && is_const(&node.rhs)
+        }
+        Not | BitNot | Cast(_) => is_const(&node.lhs),
+        Comma => is_const(&node.rhs),
+        Ternary => is_const(&node.cond) && is_const(&node.then) && is_const(&node.els),
+        _ => false,
     }
 }
 
 pub fn const_expr(iter: &mut TokenStream, ctx: &mut Context) -> Result<i64, Error> {
-    eval(&mut conditional(iter, ctx)?)
+    let node = conditional(iter, ctx)?;
+    eval(iter, &node)
 }
 
 // assign                  = conditional (assign-op assign)?
@@ -1372,26 +2388,11 @@ pub fn const_expr(iter: &mut TokenStream, ctx: &mut Context) -> Result<i64, Erro
 pub fn assign(iter: &mut TokenStream, ctx: &mut Context) -> Result<Node, Error> {
     let mut node = conditional(iter, ctx)?;
     if consume(iter, Operator::Assign) {
+        let pos = iter.pos;
         let rhs = assign(iter, ctx)?;
-        // 左右の型が違っても受け入れる
-
-        // let lhs_type = node
-        //     .get_type()
-        //     .unwrap_or(TypeKind::_Invalid("invalid lhs".to_string()));
-        // let rhs_type = rhs
-        //     .get_type()
-        //     .unwrap_or(TypeKind::_Invalid("invalid rhs".to_string()));
-
-        // 配列とポインタの比較が中途半端
-        // if lhs_type != rhs_type && !TypeKind::partial_comp(&lhs_type, &rhs_type) {
-        //     return Err(Error::invalid_assignment(
-        //         iter.filepath.clone(),
-        //         iter.input.clone(),
-        //         iter.pos,
-        //         lhs_type,
-        //         rhs_type,
-        //     ));
-        // }
+        if let Ok(lhs_type) = node.get_type() {
+            check_assignment(iter, ctx, pos, &lhs_type, &rhs, "assignment")?;
+        }
         node = Node::new(NodeKind::Assign, node, rhs);
     } else if consume(iter, Operator::APlus) {
         let rhs = assign(iter, ctx)?;
@@ -1426,6 +2427,7 @@ pub fn assign(iter: &mut TokenStream, ctx: &mut Context) -> Result<Node, Error>
 
 // conditional             = logor ("?" expr ":" conditional)?
 pub fn conditional(iter: &mut TokenStream, ctx: &mut Context) -> Result<Node, Error> {
+    let begin = iter.idx;
     let node = log_or(iter, ctx)?;
     if !consume_question(iter) {
         return Ok(node);
@@ -1435,7 +2437,7 @@ pub fn conditional(iter: &mut TokenStream, ctx: &mut Context) -> Result<Node, Er
     ternary.then = Some(Box::new(expr(iter, ctx)?));
     expect_colon(iter)?;
     ternary.els = Some(Box::new(conditional(iter, ctx)?));
-    return Ok(ternary);
+    return Ok(ternary.with_span(iter.span_from(begin)));
 }
 
 // logor                   = logand ("||" logand)*
@@ -1487,13 +2489,17 @@ pub fn bit_and(iter: &mut TokenStream, ctx: &mut Context) -> Result<Node, Error>
 pub fn equality(iter: &mut TokenStream, ctx: &mut Context) -> Result<Node, Error> {
     let mut node = relational(iter, ctx)?;
     loop {
-        if consume(iter, Operator::Equal) {
-            node = Node::new(NodeKind::Equal, node, relational(iter, ctx)?);
+        let kind = if consume(iter, Operator::Equal) {
+            NodeKind::Equal
         } else if consume(iter, Operator::Neq) {
-            node = Node::new(NodeKind::Neq, node, relational(iter, ctx)?);
+            NodeKind::Neq
         } else {
             return Ok(node);
-        }
+        };
+        let pos = iter.pos;
+        let rhs = relational(iter, ctx)?;
+        check_comparison(iter, ctx, pos, &node, &rhs);
+        node = Node::new(kind, node, rhs);
     }
 }
 
@@ -1501,19 +2507,26 @@ pub fn equality(iter: &mut TokenStream, ctx: &mut Context) -> Result<Node, Error
 pub fn relational(iter: &mut TokenStream, ctx: &mut Context) -> Result<Node, Error> {
     let mut node = shift(iter, ctx)?;
     loop {
-        if consume(iter, Operator::Lesser) {
-            node = Node::new(NodeKind::Lesser, node, shift(iter, ctx)?);
+        // `>`と`>=`は左右を入れ替えて読み変える
+        let (kind, swap) = if consume(iter, Operator::Lesser) {
+            (NodeKind::Lesser, false)
         } else if consume(iter, Operator::Leq) {
-            node = Node::new(NodeKind::Leq, node, shift(iter, ctx)?);
+            (NodeKind::Leq, false)
         } else if consume(iter, Operator::Greater) {
-            // 左右を入れ替えて読み変える
-            node = Node::new(NodeKind::Lesser, shift(iter, ctx)?, node);
+            (NodeKind::Lesser, true)
         } else if consume(iter, Operator::Geq) {
-            // 左右を入れ替えて読み変える
-            node = Node::new(NodeKind::Leq, shift(iter, ctx)?, node);
+            (NodeKind::Leq, true)
         } else {
             return Ok(node);
-        }
+        };
+        let pos = iter.pos;
+        let rhs = shift(iter, ctx)?;
+        check_comparison(iter, ctx, pos, &node, &rhs);
+        node = if swap {
+            Node::new(kind, rhs, node)
+        } else {
+            Node::new(kind, node, rhs)
+        };
     }
 }
 
@@ -1562,15 +2575,15 @@ pub fn mul(iter: &mut TokenStream, ctx: &mut Context) -> Result<Node, Error> {
 // cast                    = "(" type-name ")" cast | unary
 pub fn cast(iter: &mut TokenStream, ctx: &mut Context) -> Result<Node, Error> {
     let i_data = iter.save();
+    let begin = iter.idx;
     if consume(iter, Operator::LParen) {
         if is_typename(iter, ctx) {
             let ty = type_name(iter, ctx)?;
             expect(iter, Operator::RParen)?;
             if !consume_block(iter, Block::LParen) {
-                return Ok(Node::new_unary(
-                    NodeKind::Cast(ty.replace(TypeKind::Int)),
-                    cast(iter, ctx)?,
-                ));
+                let node =
+                    Node::new_unary(NodeKind::Cast(ty.replace(TypeKind::Int)), cast(iter, ctx)?);
+                return Ok(node.with_span(iter.span_from(begin)));
             }
         }
         // `(`をconsumeした分を戻す
@@ -1583,36 +2596,52 @@ pub fn cast(iter: &mut TokenStream, ctx: &mut Context) -> Result<Node, Error> {
 //             | ("++" | "--") unary
 //             | postfix
 pub fn unary(iter: &mut TokenStream, ctx: &mut Context) -> Result<Node, Error> {
-    if consume(iter, Operator::Plus) {
-        return cast(iter, ctx);
+    let begin = iter.idx;
+    let node = if consume(iter, Operator::Plus) {
+        cast(iter, ctx)?
     } else if consume(iter, Operator::Minus) {
-        return Ok(Node::new(NodeKind::Sub, Node::new_num(0), cast(iter, ctx)?));
+        Node::new(NodeKind::Sub, Node::new_num(0), cast(iter, ctx)?)
     } else if consume(iter, Operator::Mul) {
-        return Ok(Node::new_unary(NodeKind::Deref, cast(iter, ctx)?));
+        let node = Node::new_unary(NodeKind::Deref, cast(iter, ctx)?);
+        if let Ok(type_kind @ TypeKind::Struct(_)) = node.get_type() {
+            if type_kind.is_incomplete() {
+                return Err(Error::incomplete_type(
+                    iter.filepath.clone(),
+                    iter.input.clone(),
+                    iter.pos,
+                    "dereferencing pointer to incomplete type",
+                    suggest_incomplete_tag(ctx, &type_kind),
+                ));
+            }
+        }
+        node
     } else if consume(iter, Operator::Ampersand) {
-        return Ok(Node::new_unary(NodeKind::Addr, cast(iter, ctx)?));
+        Node::new_unary(NodeKind::Addr, cast(iter, ctx)?)
     } else if consume(iter, Operator::PlusPlus) {
-        return Ok(Node::new_unary(NodeKind::PreInc, unary(iter, ctx)?));
+        Node::new_unary(NodeKind::PreInc, unary(iter, ctx)?)
     } else if consume(iter, Operator::MinusMinus) {
-        return Ok(Node::new_unary(NodeKind::PreDec, unary(iter, ctx)?));
+        Node::new_unary(NodeKind::PreDec, unary(iter, ctx)?)
     } else if consume(iter, Operator::Not) {
-        return Ok(Node::new_unary(NodeKind::Not, unary(iter, ctx)?));
+        Node::new_unary(NodeKind::Not, unary(iter, ctx)?)
     } else if consume(iter, Operator::BitNot) {
-        return Ok(Node::new_unary(NodeKind::BitNot, unary(iter, ctx)?));
-    }
-
-    return postfix(iter, ctx);
+        Node::new_unary(NodeKind::BitNot, unary(iter, ctx)?)
+    } else {
+        postfix(iter, ctx)?
+    };
+    Ok(node.with_span(iter.span_from(begin)))
 }
 
 // postfix     = compound-literal
 //             | primary ("[" expr "]" | "." ident | "->" ident | "++" | "--")*
 pub fn postfix(iter: &mut TokenStream, ctx: &mut Context) -> Result<Node, Error> {
+    let begin = iter.idx;
     let node = compound_literal(iter, ctx)?;
     if let Some(node) = node {
-        return Ok(node);
+        return Ok(node.with_span(iter.span_from(begin)));
     }
     let mut pri = primary(iter, ctx)?;
     loop {
+        pri.span = iter.span_from(begin);
         if consume(iter, Operator::LArr) {
             let idx = expr(iter, ctx)?;
             expect(iter, Operator::RArr)?;
@@ -1620,6 +2649,32 @@ pub fn postfix(iter: &mut TokenStream, ctx: &mut Context) -> Result<Node, Error>
             continue;
         }
 
+        if consume(iter, Operator::LParen) {
+            // 関数か関数へのポインタだけを呼び出せる
+            let func_type = match pri.get_type() {
+                Ok(TypeKind::Ptr(base)) => base.borrow().clone(),
+                Ok(type_kind) => type_kind,
+                Err(_) => TypeKind::_Invalid("invalid".to_string()),
+            };
+            // 変数を通して呼び出すときは、その名前をエラーメッセージに使う
+            let ident = match &pri.kind {
+                NodeKind::Lvar(lvar) => lvar.borrow().dec.ident.clone(),
+                NodeKind::Gvar(gvar) => gvar.dec.ident.clone(),
+                _ => Ident::new_anonymous(),
+            };
+            let func_prototype =
+                FuncPrototype::from_type(&func_type, ident, false).ok_or_else(|| {
+                    Error::invalid_function_call(
+                        iter.filepath.clone(),
+                        iter.input.clone(),
+                        iter.pos,
+                        "called object is not a function or function pointer",
+                    )
+                })?;
+            pri = func_call(iter, ctx, Rc::new(func_prototype), Some(pri))?;
+            continue;
+        }
+
         if consume(iter, Operator::PlusPlus) {
             pri = Node::new_unary(NodeKind::PostInc, pri);
             continue;
@@ -1645,16 +2700,26 @@ pub fn postfix(iter: &mut TokenStream, ctx: &mut Context) -> Result<Node, Error>
                     let member_name = expect_ident(iter)?;
                     match &pri.get_type() {
                         Ok(type_kind) => match type_kind {
+                            TypeKind::Struct(_struct) if _struct.borrow().is_incomplete => {
+                                return Err(Error::incomplete_type(
+                                    iter.filepath.clone(),
+                                    iter.input.clone(),
+                                    iter.pos,
+                                    "dereferencing pointer to incomplete type",
+                                    suggest_incomplete_tag(ctx, type_kind),
+                                ));
+                            }
                             TypeKind::Struct(_struct) => {
-                                let member = _struct.borrow().find_field(&member_name).ok_or(
-                                    Error::undefined_member(
-                                        iter.filepath.clone(),
-                                        iter.input.clone(),
-                                        iter.pos,
-                                        member_name.clone(),
-                                        None,
-                                    ),
-                                )?;
+                                let member =
+                                    _struct.borrow().find_field(&member_name).ok_or_else(|| {
+                                        Error::undefined_member(
+                                            iter.filepath.clone(),
+                                            iter.input.clone(),
+                                            iter.pos,
+                                            member_name.clone(),
+                                            suggest_member(&_struct.borrow(), &member_name.name),
+                                        )
+                                    })?;
                                 pri = Node::new_unary(NodeKind::Member(member_name, member), pri);
                                 continue;
                             }
@@ -1681,489 +2746,1799 @@ pub fn postfix(iter: &mut TokenStream, ctx: &mut Context) -> Result<Node, Error>
             }
         }
 
-        return Ok(pri);
-    }
-}
-// compound-literal        = "(" type-name ")" "{" (gvar-initializer | lvar-initializer) "}"
-pub fn compound_literal(iter: &mut TokenStream, ctx: &mut Context) -> Result<Option<Node>, Error> {
-    let i_data = iter.save();
+        return Ok(pri);
+    }
+}
+// compound-literal        = "(" type-name ")" "{" initializer-list "}"
+pub fn compound_literal(iter: &mut TokenStream, ctx: &mut Context) -> Result<Option<Node>, Error> {
+    let i_data = iter.save();
+
+    if !consume(iter, Operator::LParen) || !is_typename(iter, ctx) {
+        iter.restore(i_data);
+        return Ok(None);
+    }
+
+    let type_kind = type_name(iter, ctx)?;
+    expect(iter, Operator::RParen)?;
+    if !consume_block(iter, Block::LParen) {
+        iter.restore(i_data);
+        return Ok(None);
+    }
+    iter.prev();
+    if ctx.s.get_depth() == 0 {
+        let mut dec = Declaration::new(type_kind.borrow().clone(), Ident::new(ctx.make_label()));
+        dec.is_static = true;
+        let mut init = Vec::new();
+        gvar_initializer(iter, ctx, &mut init, type_kind.clone())?;
+        let gvar = Rc::new(Gvar::new(dec, type_kind.borrow().size(), init));
+        ctx.insert_g(gvar.clone());
+
+        return Ok(Some(Node::new_gvar(gvar)));
+    }
+
+    let dec = Declaration::new(type_kind.borrow().clone(), Ident::new_anonymous());
+    ctx.push_front(dec);
+
+    let lvar = ctx.l.lvar.as_ref().unwrap().clone();
+    let (node, type_kind) = lvar_initializer(iter, ctx, lvar.clone(), type_kind)?;
+
+    lvar.borrow_mut().dec.type_kind = type_kind.borrow().clone();
+    lvar.borrow_mut().offset += type_kind.borrow().size();
+
+    let node = *node.lhs.unwrap();
+
+    Ok(Some(node))
+}
+
+// stmt-expr       = "(" "{" stmt stmt* "}" ")"
+pub fn stmt_expr(iter: &mut TokenStream, ctx: &mut Context) -> Result<Node, Error> {
+    // expect(iter, Operator::LParen)?;
+    // expect_block(iter, Block::LParen)?;
+    let sc = ctx.s.enter();
+    let depth = ctx.vla.sp.len();
+    let mut nodes = vec![stmt(iter, ctx)?];
+    while !consume_block(iter, Block::RParen) {
+        nodes.push(stmt(iter, ctx)?);
+    }
+    expect(iter, Operator::RParen)?;
+
+    if nodes.last().unwrap().kind != NodeKind::ExprStmt {
+        return Err(Error::invalid_stmt_expr(
+            iter.filepath.clone(),
+            iter.input.clone(),
+            iter.pos,
+        ));
+    }
+    *(nodes.last_mut().unwrap()) = std::mem::replace(
+        nodes.last_mut().unwrap().lhs.as_mut().unwrap(),
+        Node::new_num(0),
+    );
+    ctx.s.leave(sc);
+    // 最後の式の値を残したままVLAを解放する
+    if let Some(mut free) = ctx.vla.free_to(depth) {
+        free.lhs = nodes.pop().map(Box::new);
+        nodes.push(free);
+        ctx.vla.sp.truncate(depth);
+    }
+    Ok(Node::new_leaf(NodeKind::StmtExpr(nodes)))
+}
+
+// primary     = num
+//             | ident func-args?
+//             | "(" expr ")"
+//             | str
+//             | char
+//             | "(" "{" stmt-expr-tail
+//             | sizeof unary
+//             | sizeof "(" type-name ")"
+//             | "_Alignof" "(" (type-name | expr) ")"
+//             | generic-selection
+pub fn primary(iter: &mut TokenStream, ctx: &mut Context) -> Result<Node, Error> {
+    // "(" expr ")"
+    if consume(iter, Operator::LParen) {
+        if consume_block(iter, Block::LParen) {
+            return Ok(stmt_expr(iter, ctx)?);
+        }
+        let node = expr(iter, ctx)?;
+        expect(iter, Operator::RParen)?;
+        return Ok(node);
+    }
+
+    // ident func-args?
+    if let Some(ident) = consume_ident(iter) {
+        let lvar = ctx.s.find_upper_lvar(ident.clone());
+        let func_prototype = ctx.g.func_prototype_mp.get(&ident.name).cloned();
+        // 同じ名前のローカル変数があれば、その変数を関数ポインタとして呼び出す
+        if lvar.is_none() && is_next(iter, Operator::LParen) {
+            if let Some(func_prototype) = &func_prototype {
+                ctx.usage.mark_name(&ident.name);
+                iter.next();
+                return func_call(iter, ctx, func_prototype.clone(), None);
+            }
+            if ctx.s.find_upper_gvar(ident.clone()).is_none() {
+                let suggestion = suggest_var(ctx, &ident.name);
+                return Err(Error::undefined_function(
+                    iter.filepath.clone(),
+                    iter.input.clone(),
+                    ident,
+                    iter.pos,
+                    suggestion,
+                ));
+            }
+        }
+        if let Some(lvar) = lvar {
+            if let TypeKind::_Invalid(_) = lvar.borrow().dec.type_kind {
+                return Err(Error::invalid_declaration(
+                    iter.filepath.clone(),
+                    iter.input.clone(),
+                    ident,
+                    iter.pos,
+                ));
+            }
+            if lvar.borrow().dec.is_const.0 {
+                return Ok(Node::new_num(lvar.borrow().dec.is_const.1));
+            }
+            ctx.usage.mark_lvar(&lvar.borrow().dec);
+            return Ok(Node::new_leaf(NodeKind::Lvar(lvar)));
+        } else if let Some(x) = ctx.s.find_upper_gvar(ident.clone()) {
+            ctx.usage.mark_gvar(&x.dec);
+            return Ok(Node::new_leaf(NodeKind::Gvar(x.clone())));
+        } else if let Some(func_prototype) = func_prototype {
+            ctx.usage.mark_name(&ident.name);
+            return Ok(Node::new_leaf(NodeKind::FuncName(func_prototype)));
+        } else {
+            iter.prev();
+            let suggestion = suggest_var(ctx, &ident.name);
+            return Err(Error::undefined_variable(
+                iter.filepath.clone(),
+                iter.input.clone(),
+                ident,
+                iter.pos,
+                suggestion,
+            ));
+        }
+    }
+
+    // str
+    if let Some(string) = consume_string(iter) {
+        let string = Rc::new(string);
+        let idx = ctx.g.tk_string.len();
+        let label = format!(".LC{}", idx).to_string();
+        ctx.g
+            .tk_string
+            .push((string.clone(), Rc::new(label.clone())));
+        let mut init = Vec::new();
+        gvar_init_string(&mut init, string.as_ref().clone());
+        return Ok(Node::new_leaf(make_string_node(
+            label,
+            (string.len()) as u64,
+            init,
+        )));
+    }
+
+    // char
+    if let Some(c) = consume_char(iter) {
+        return Ok(Node::new_num(c as i64));
+    }
+
+    if consume(iter, Operator::Sizeof) {
+        if consume(iter, Operator::LParen) {
+            if is_typename(iter, ctx) {
+                let ty = type_name(iter, ctx)?;
+                expect(iter, Operator::RParen)?;
+                if ty.borrow().is_incomplete() {
+                    return Err(Error::incomplete_type(
+                        iter.filepath.clone(),
+                        iter.input.clone(),
+                        iter.pos,
+                        "invalid application of sizeof to incomplete type",
+                        suggest_incomplete_tag(ctx, &ty.borrow()),
+                    ));
+                }
+                if let TypeKind::Vla(_, size) = &*ty.borrow() {
+                    return Ok(size.as_ref().clone());
+                }
+                return Ok(Node::new_num(ty.borrow().size() as i64));
+            } else {
+                iter.prev();
+            }
+        }
+        let node = unary(iter, ctx)?;
+        match node.get_type() {
+            Ok(x) if x.is_incomplete() => {
+                return Err(Error::incomplete_type(
+                    iter.filepath.clone(),
+                    iter.input.clone(),
+                    iter.pos,
+                    "invalid application of sizeof to incomplete type",
+                    suggest_incomplete_tag(ctx, &x),
+                ))
+            }
+            // 可変長配列の大きさは実行時に決まる
+            Ok(TypeKind::Vla(_, size)) => return Ok(size.as_ref().clone()),
+            Ok(x) => return Ok(Node::new_num(x.size() as i64)),
+            Err(e) => {
+                dbg!("{}", e);
+                return Err(Error::todo(
+                    iter.filepath.clone(),
+                    iter.input.clone(),
+                    iter.pos,
+                ));
+            }
+        }
+    }
+
+    if is_next_keyword(iter, KeyWord::Generic) {
+        return generic_selection(iter, ctx);
+    }
+
+    // 式を取るのはgccの拡張
+    if consume_keyword(iter, KeyWord::Alignof) {
+        expect(iter, Operator::LParen)?;
+        let align = if is_typename(iter, ctx) {
+            type_name(iter, ctx)?.borrow().align()
+        } else {
+            let node = expr(iter, ctx)?;
+            match &node.kind {
+                NodeKind::Lvar(lvar) => lvar.borrow().dec.align(),
+                NodeKind::Gvar(gvar) => gvar.dec.align(),
+                _ => match node.get_type() {
+                    Ok(x) => x.align(),
+                    Err(_) => {
+                        return Err(Error::todo(
+                            iter.filepath.clone(),
+                            iter.input.clone(),
+                            iter.pos,
+                        ))
+                    }
+                },
+            }
+        };
+        expect(iter, Operator::RParen)?;
+        return Ok(Node::new_num(align as i64));
+    }
+
+    // num
+    return Ok(Node::new_num(expect_num(iter)?));
+}
+
+// generic-selection       = "_Generic" "(" assign ("," generic-assoc)+ ")"
+// generic-assoc           = (type-name | "default") ":" assign
+// 制御式は型を調べるだけで評価しない
+fn generic_selection(iter: &mut TokenStream, ctx: &mut Context) -> Result<Node, Error> {
+    expect_keyword(iter, KeyWord::Generic)?;
+    let pos = iter.pos;
+    expect(iter, Operator::LParen)?;
+    // 左辺値変換をするので配列や関数はポインタとして扱う
+    let ctl_type = match assign(iter, ctx)?.get_type() {
+        Ok(TypeKind::Array(_, type_kind, _)) | Ok(TypeKind::Vla(type_kind, _)) => {
+            TypeKind::Ptr(type_kind)
+        }
+        Ok(func @ TypeKind::Func(_, _, _)) => TypeKind::ptr_to(Rc::new(RefCell::new(func))),
+        Ok(x) => x,
+        Err(_) => {
+            return Err(Error::todo(
+                iter.filepath.clone(),
+                iter.input.clone(),
+                iter.pos,
+            ))
+        }
+    };
+
+    let mut selected = None;
+    let mut default = None;
+    let mut types: Vec<TypeKind> = Vec::new();
+    while consume_comma(iter) {
+        if consume_keyword(iter, KeyWord::Default) {
+            let default_pos = iter.pos;
+            expect_colon(iter)?;
+            let node = assign(iter, ctx)?;
+            if default.is_some() {
+                return Err(Error::invalid_generic_selection(
+                    iter.filepath.clone(),
+                    iter.input.clone(),
+                    default_pos,
+                    "duplicate default generic association",
+                ));
+            }
+            default = Some(node);
+            continue;
+        }
+
+        let type_pos = iter.peek().map_or(iter.pos, |x| x.pos);
+        let type_kind = type_name(iter, ctx)?.borrow().clone();
+        expect_colon(iter)?;
+        let node = assign(iter, ctx)?;
+        if types.iter().any(|x| TypeKind::is_compatible(x, &type_kind)) {
+            return Err(Error::invalid_generic_selection(
+                iter.filepath.clone(),
+                iter.input.clone(),
+                type_pos,
+                "type in generic association compatible with previously specified type",
+            ));
+        }
+        if TypeKind::is_compatible(&ctl_type, &type_kind) {
+            selected = Some(node);
+        }
+        types.push(type_kind);
+    }
+    expect(iter, Operator::RParen)?;
+
+    match selected.or(default) {
+        Some(node) => Ok(node),
+        None => Err(Error::invalid_generic_selection(
+            iter.filepath.clone(),
+            iter.input.clone(),
+            pos,
+            "controlling expression type not compatible with any generic association type",
+        )),
+    }
+}
+
+// func-args   = "(" (assign ("," assign)*)? ")"
+/// 関数呼び出し
+/// 関数ポインタを通して呼び出すときは、呼び出す関数のアドレスになる式をcalleeに渡す
+fn func_call(
+    iter: &mut TokenStream,
+    ctx: &mut Context,
+    func_prototype: Rc<FuncPrototype>,
+    callee: Option<Node>,
+) -> Result<Node, Error> {
+    let mut node = Node::new_leaf(NodeKind::Func(
+        func_prototype.clone(),
+        func_args(iter, ctx, &func_prototype)?,
+    ));
+    node.rhs = callee.map(Box::new);
+    // 構造体の戻り値は呼び出し元で確保した一時領域に置く
+    if let TypeKind::Struct(_) = func_prototype.type_kind {
+        let ident = Ident::new("__ret_buf__");
+        ctx.push_front(Declaration::new(
+            func_prototype.type_kind.clone(),
+            ident.clone(),
+        ));
+        let lvar = ctx.s.find_cur_lvar(ident).unwrap();
+        node.lhs = Some(Box::new(Node::new_lvar(lvar)));
+    }
+    Ok(node)
+}
+
+fn func_args(
+    iter: &mut TokenStream,
+    ctx: &mut Context,
+    func_prototype: &FuncPrototype,
+) -> Result<Vec<Node>, Error> {
+    let mut args = Vec::new();
+    if !consume(iter, Operator::RParen) {
+        loop {
+            let arg = assign(iter, ctx)?;
+            check_func_arg(iter, ctx, func_prototype, args.len(), &arg)?;
+            args.push(arg);
+            if !consume_comma(iter) {
+                break;
+            }
+        }
+        expect(iter, Operator::RParen)?;
+    }
+    check_func_arg_num(iter, func_prototype, args.len())?;
+    Ok(args)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::{GlobalContext, Ident, Lvar, NodeKind};
+    use crate::base_types::TypeKind;
+
+    use std::rc::Rc;
+
+    #[test]
+    fn test_expr() {
+        use crate::token;
+        let tests = [
+            ("1==10", make_test_node(NodeKind::Equal, 1, 10)),
+            ("1 != 10", make_test_node(NodeKind::Neq, 1, 10)),
+            ("1  <10", make_test_node(NodeKind::Lesser, 1, 10)),
+            ("1<=10", make_test_node(NodeKind::Leq, 1, 10)),
+            ("1>10", make_test_node(NodeKind::Lesser, 10, 1)), // Lesser,LeqはGreater.Geqを使って実装されてる
+            ("1>=10", make_test_node(NodeKind::Leq, 10, 1)),
+            ("1+10", make_test_node(NodeKind::Add, 1, 10)),
+            ("1-10", make_test_node(NodeKind::Sub, 1, 10)),
+            ("1*10", make_test_node(NodeKind::Mul, 1, 10)),
+            ("1/10", make_test_node(NodeKind::Div, 1, 10)),
+            ("+1", Node::new_num(1)),
+            ("-1", make_test_node(NodeKind::Sub, 0, 1)),
+            (
+                "2 * ( 3 + 4)",
+                Node::new(
+                    NodeKind::Mul,
+                    Node::new_num(2),
+                    make_test_node(NodeKind::Add, 3, 4),
+                ),
+            ),
+            ("42", Node::new_num(42)),
+        ];
+
+        for (s, expected) in &tests {
+            assert_eq!(
+                expected,
+                &expr(
+                    &mut token::tokenize(Rc::new(s.to_string()), Rc::new("".to_string())).unwrap(),
+                    &mut Context::new()
+                )
+                .unwrap()
+            )
+        }
+    }
+
+    #[test]
+    fn test_stmt() {
+        use crate::token;
+
+        let tests = [(
+            "int foo;",
+            vec![Node::new_leaf(NodeKind::Declaration(make_int_dec("foo")))],
+        )];
+
+        for (s, expected) in &tests {
+            let mut iter =
+                token::tokenize(Rc::new(s.to_string()), Rc::new("".to_string())).unwrap();
+            let mut actual = Vec::new();
+
+            let ctx = &mut Context::new();
+            while iter.peek() != None {
+                actual.push(stmt(&mut iter, ctx).unwrap());
+            }
+            assert_eq!(expected, &actual);
+        }
+    }
+
+    #[test]
+    fn test_unary() {
+        use crate::ast::Var;
+        use crate::token;
+        let expected = vec![
+            Node::new_num(1),
+            Node::new_num(1),
+            Node::new_leaf(NodeKind::Lvar(Rc::new(RefCell::new(Lvar::new_leaf(
+                make_int_dec("hoge"),
+                8,
+            ))))),
+            Node::new(NodeKind::Sub, Node::new_num(0), Node::new_num(1)),
+            Node::new(
+                NodeKind::Sub,
+                Node::new_num(0),
+                Node::new_leaf(NodeKind::Lvar(Rc::new(RefCell::new(Lvar::new_leaf(
+                    make_int_dec("hoge"),
+                    8,
+                ))))),
+            ),
+            Node::new_unary(NodeKind::Deref, Node::new_num(1)),
+            Node::new_unary(NodeKind::Addr, Node::new_num(1)),
+            Node::new_unary(
+                NodeKind::Deref,
+                Node::new_leaf(NodeKind::Lvar(Rc::new(RefCell::new(Lvar::new_leaf(
+                    make_int_dec("hoge"),
+                    8,
+                ))))),
+            ),
+            Node::new_unary(
+                NodeKind::Addr,
+                Node::new_leaf(NodeKind::Lvar(Rc::new(RefCell::new(Lvar::new_leaf(
+                    make_int_dec("hoge"),
+                    8,
+                ))))),
+            ),
+            Node::new_unary(
+                NodeKind::Deref,
+                Node::new_unary(
+                    NodeKind::Addr,
+                    Node::new_leaf(NodeKind::Lvar(Rc::new(RefCell::new(Lvar::new_leaf(
+                        make_int_dec("hoge"),
+                        8,
+                    ))))),
+                ),
+            ),
+        ];
+
+        let input = "1 +1 +hoge -1 -hoge *1 &1 *hoge &hoge *&hoge ";
+        let iter =
+            &mut token::tokenize(Rc::new(input.to_string()), Rc::new("".to_string())).unwrap();
+        for i in expected {
+            let ctx = &mut Context::new();
+            ctx.l.lvar = Some(Rc::new(RefCell::new(make_int_lvar("hoge", 8))));
+            ctx.s.insert_v(
+                Ident::new("hoge"),
+                Rc::new(Var::L(Rc::new(RefCell::new(make_int_lvar("hoge", 8))))),
+            );
+            assert_eq!(i, unary(iter, ctx).unwrap());
+        }
+
+        let expected = vec![
+            (Node::new_num(4), make_int_lvar("hoge", 8)),
+            (Node::new_num(4), make_int_lvar("hoge", 8)),
+            (Node::new_num(8), make_ptr_lvar("hoge", 8)),
+            (Node::new_num(4), make_ptr_lvar("hoge", 8)),
+        ];
+
+        let input = "sizeof 1 sizeof (hoge) sizeof (hoge) sizeof(*hoge)";
+        let iter =
+            &mut token::tokenize(Rc::new(input.to_string()), Rc::new("".to_string())).unwrap();
+        for i in expected {
+            let ctx = &mut Context::new();
+            ctx.l.lvar = Some(Rc::new(RefCell::new(i.1.clone())));
+            ctx.s.insert_v(
+                i.1.dec.ident.clone(),
+                Rc::new(Var::L(Rc::new(RefCell::new(i.1)))),
+            );
+
+            assert_eq!(i.0, unary(iter, ctx).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_declaration() {
+        use crate::token;
+        use TypeKind::*;
+        let tests = [
+            ("int hoge", Declaration::new(Int, Ident::new("hoge"))),
+            ("int *hoge", make_ptr_lvar("hoge", 8).dec),
+            (
+                "int **hoge",
+                Declaration::new(
+                    Ptr(Rc::new(RefCell::new(Ptr(Rc::new(RefCell::new(Int)))))),
+                    Ident::new("hoge"),
+                ),
+            ),
+            (
+                "int hoge[1]",
+                Declaration::new(
+                    Array(1, Rc::new(RefCell::new(Int)), true),
+                    Ident::new("hoge"),
+                ),
+            ),
+            (
+                "int *hoge[1]",
+                Declaration::new(
+                    Array(
+                        1,
+                        Rc::new(RefCell::new(Ptr(Rc::new(RefCell::new(Int))))),
+                        true,
+                    ),
+                    Ident::new("hoge"),
+                ),
+            ),
+            ("char hoge", Declaration::new(Char, Ident::new("hoge"))),
+            (
+                "char **hoge",
+                Declaration::new(
+                    Ptr(Rc::new(RefCell::new(Ptr(Rc::new(RefCell::new(Char)))))),
+                    Ident::new("hoge"),
+                ),
+            ),
+            (
+                "char hoge[1]",
+                Declaration::new(
+                    Array(1, Rc::new(RefCell::new(Char)), true),
+                    Ident::new("hoge"),
+                ),
+            ),
+            (
+                "char *hoge[1]",
+                Declaration::new(
+                    Array(
+                        1,
+                        Rc::new(RefCell::new(Ptr(Rc::new(RefCell::new(Char))))),
+                        true,
+                    ),
+                    Ident::new("hoge"),
+                ),
+            ),
+            (
+                "int hoge[2][2]",
+                Declaration::new(
+                    Array(
+                        2,
+                        Rc::new(RefCell::new(Array(2, Rc::new(RefCell::new(Int)), true))),
+                        true,
+                    ),
+                    Ident::new("hoge"),
+                ),
+            ),
+        ];
+
+        for (input, expected) in &tests {
+            assert_eq!(
+                *expected,
+                declaration(
+                    &mut token::tokenize(Rc::new(input.to_string()), Rc::new("".to_string()))
+                        .unwrap(),
+                    &mut Context::new()
+                )
+                .unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn test_struct_dec() {
+        use crate::token;
+        use TypeKind::{Char, Int, Ptr};
+
+        let members = Rc::new(vec![
+            Rc::new(make_member(Int, "first", 0)),
+            Rc::new(make_member(Int, "second", 4)),
+        ]);
+        let expected = Rc::new(RefCell::new(Struct::new(
+            Rc::new(Ident::new("hoge")),
+            members,
+        )));
+        let input = "struct hoge {int first; int second;}";
+        let actual = struct_dec(
+            &mut token::tokenize(Rc::new(input.to_string()), Rc::new("".to_string())).unwrap(),
+            &mut Context::new(),
+        )
+        .unwrap();
+        assert_eq!(expected, actual);
+        assert_eq!(8, actual.borrow().get_size());
+
+        let members = Rc::new(vec![
+            Rc::new(make_member(Int, "first", 0)),
+            Rc::new(make_member(Int, "second", 4)),
+            Rc::new(make_member(Char, "third", 8)),
+            Rc::new(make_member(Int, "four", 12)),
+        ]);
+        let expected = Rc::new(RefCell::new(Struct::new(
+            Rc::new(Ident::new("hoge")),
+            members,
+        )));
+        let input = "struct hoge {int first; int second; char third; int four;}";
+        let actual = struct_dec(
+            &mut token::tokenize(Rc::new(input.to_string()), Rc::new("".to_string())).unwrap(),
+            &mut Context::new(),
+        )
+        .unwrap();
+        assert_eq!(expected, actual);
+        assert_eq!(16, actual.borrow().get_size());
+
+        let members = Rc::new(vec![
+            Rc::new(make_member(Int, "first", 0)),
+            Rc::new(make_member(Ptr(Rc::new(RefCell::new(Int))), "second", 8)),
+            Rc::new(make_member(Int, "four", 16)),
+        ]);
+        let expected = Rc::new(RefCell::new(Struct::new(
+            Rc::new(Ident::new("hoge")),
+            members,
+        )));
+        let input = "struct hoge {int first; int *second; int four;}";
+        let actual = struct_dec(
+            &mut token::tokenize(Rc::new(input.to_string()), Rc::new("".to_string())).unwrap(),
+            &mut Context::new(),
+        )
+        .unwrap();
+        assert_eq!(expected, actual);
+        assert_eq!(24, actual.borrow().get_size());
 
-    if !consume(iter, Operator::LParen) || !is_typename(iter, ctx) {
-        iter.restore(i_data);
-        return Ok(None);
+        let members = Rc::new(vec![
+            Rc::new(make_member(Int, "first", 0)),
+            Rc::new(make_member(Ptr(Rc::new(RefCell::new(Int))), "second", 0)),
+            Rc::new(make_member(Char, "third", 0)),
+        ]);
+        let mut expected = Struct::new(Rc::new(Ident::new("hoge")), members);
+        expected.is_union = true;
+        let input = "union hoge {int first; int *second; char third;}";
+        let actual = struct_dec(
+            &mut token::tokenize(Rc::new(input.to_string()), Rc::new("".to_string())).unwrap(),
+            &mut Context::new(),
+        )
+        .unwrap();
+        assert_eq!(Rc::new(RefCell::new(expected)), actual);
+        assert_eq!(8, actual.borrow().get_size());
     }
 
-    let type_kind = type_name(iter, ctx)?;
-    expect(iter, Operator::RParen)?;
-    if !consume_block(iter, Block::LParen) {
-        iter.restore(i_data);
-        return Ok(None);
-    }
-    iter.prev();
-    if ctx.s.get_depth() == 0 {
-        let mut dec = Declaration::new(type_kind.borrow().clone(), Ident::new(ctx.make_label()));
-        dec.is_static = true;
-        let mut init = Vec::new();
-        gvar_initializer(iter, ctx, &mut init, type_kind.clone())?;
-        let gvar = Rc::new(Gvar::new(dec, type_kind.borrow().size(), init));
-        ctx.insert_g(gvar.clone());
+    #[test]
+    fn test_incomplete_struct() {
+        use crate::token;
 
-        return Ok(Some(Node::new_gvar(gvar)));
+        let tests = [
+            ("struct hoge {int n; int a[];}; int main(){return 0;}", true),
+            (
+                "struct hoge; struct hoge *p; struct hoge {int a;}; int main(){return sizeof(*p);}",
+                true,
+            ),
+            (
+                "struct hoge {struct hoge *next;}; int main(){return 0;}",
+                true,
+            ),
+            (
+                "struct hoge; int main(){return sizeof(struct hoge);}",
+                false,
+            ),
+            ("struct hoge *p; int main(){return p->a;}", false),
+            ("struct hoge; struct hoge x; int main(){return 0;}", false),
+            ("int main(){struct hoge x; return 0;}", false),
+            (
+                "struct hoge {int n; struct hoge x;}; int main(){return 0;}",
+                false,
+            ),
+            (
+                "struct hoge {int a[]; int n;}; int main(){return 0;}",
+                false,
+            ),
+            ("struct hoge {int a[];}; int main(){return 0;}", false),
+            ("union hoge {int n; int a[];}; int main(){return 0;}", false),
+            (
+                "struct hoge {int a;}; struct hoge {int b;}; int main(){return 0;}",
+                false,
+            ),
+        ];
+        for (input, ok) in &tests {
+            let actual = program(
+                &mut token::tokenize(Rc::new(input.to_string()), Rc::new("".to_string())).unwrap(),
+            );
+            assert_eq!(*ok, actual.is_ok(), "{}", input);
+        }
     }
 
-    let dec = Declaration::new(type_kind.borrow().clone(), Ident::new_anonymous());
-    ctx.push_front(dec);
+    #[test]
+    fn test_alignas() {
+        use crate::token;
+        use TypeKind::{Char, Int};
 
-    let lvar = ctx.l.lvar.as_ref().unwrap().clone();
-    let (node, type_kind) = lvar_initializer(iter, ctx, lvar.clone(), type_kind, &mut None)?;
+        let members = Rc::new(vec![
+            Rc::new(make_member(Char, "a", 0)),
+            Rc::new(make_member(Int, "b", 32)),
+        ]);
+        let mut expected = Struct::new(Rc::new(Ident::new("hoge")), members);
+        expected.align = 32;
+        let input = "struct hoge {char a; _Alignas(32) int b;}";
+        let actual = struct_dec(
+            &mut token::tokenize(Rc::new(input.to_string()), Rc::new("".to_string())).unwrap(),
+            &mut Context::new(),
+        )
+        .unwrap();
+        assert_eq!(Rc::new(RefCell::new(expected)), actual);
+        assert_eq!(64, actual.borrow().get_size());
 
-    lvar.borrow_mut().dec.type_kind = type_kind.borrow().clone();
-    lvar.borrow_mut().offset += type_kind.borrow().size();
+        let tests = [
+            ("_Alignas(16) int x; int main(){return 0;}", true),
+            ("int main(){_Alignas(long) char x; return 0;}", true),
+            ("_Alignas(3) int x; int main(){return 0;}", false),
+            ("int main(){_Alignas(0) int x; return 0;}", false),
+        ];
+        for (input, ok) in &tests {
+            let actual = program(
+                &mut token::tokenize(Rc::new(input.to_string()), Rc::new("".to_string())).unwrap(),
+            );
+            assert_eq!(*ok, actual.is_ok(), "{}", input);
+        }
+    }
 
-    let node = *node.lhs.unwrap();
+    #[test]
+    fn test_vla() {
+        use crate::token;
+        let tests = [
+            ("int main(){int n = 3; int a[n]; return sizeof(a);}", true),
+            (
+                "int f(int n, int a[n]){return a[0];} int main(){return 0;}",
+                true,
+            ),
+            ("int n = 3; int a[n]; int main(){return 0;}", false),
+            (
+                "int main(){int n = 3; int a[n] = {1, 2, 3}; return 0;}",
+                false,
+            ),
+            ("int main(){int n = 3; static int a[n]; return 0;}", false),
+            ("int main(){int n = 3; int a[3][n]; return 0;}", true),
+            (
+                "int main(){int n = 3; int m = 2; int a[n][m]; return sizeof(a[1]);}",
+                true,
+            ),
+            (
+                "int f(int n, int m, int a[n][m]){return a[1][1];} int main(){return 0;}",
+                true,
+            ),
+            ("int n = 3; int a[3][n]; int main(){return 0;}", false),
+            (
+                "int main(){int n = 3; struct {int a[n];} x; return 0;}",
+                false,
+            ),
+        ];
+        for (input, ok) in &tests {
+            let actual = program(
+                &mut token::tokenize(Rc::new(input.to_string()), Rc::new("".to_string())).unwrap(),
+            );
+            assert_eq!(*ok, actual.is_ok(), "{}", input);
+        }
+    }
 
-    Ok(Some(node))
-}
+    #[test]
+    fn test_static_assert() {
+        use crate::token;
+        let tests = [
+            ("_Static_assert(1, \"ok\"); int main(){return 0;}", true),
+            ("static_assert(sizeof(int) == 4); int main(){return 0;}", true),
+            ("struct s {int a; _Static_assert(1, \"ok\"); int b;}; int main(){return sizeof(struct s);}", true),
+            ("int main(){_Static_assert(2 > 1, \"ok\"); return 0;}", true),
+            ("_Static_assert(0, \"ng\"); int main(){return 0;}", false),
+            ("struct s {int a; _Static_assert(sizeof(int) == 8, \"ng\");}; int main(){return 0;}", false),
+            ("int main(){static_assert(1 == 2); return 0;}", false),
+            ("int main(){_Static_assert(1, 2); return 0;}", false),
+        ];
+        for (input, ok) in &tests {
+            let actual = program(
+                &mut token::tokenize(Rc::new(input.to_string()), Rc::new("".to_string())).unwrap(),
+            );
+            assert_eq!(*ok, actual.is_ok(), "{}", input);
+        }
 
-// stmt-expr       = "(" "{" stmt stmt* "}" ")"
-pub fn stmt_expr(iter: &mut TokenStream, ctx: &mut Context) -> Result<Node, Error> {
-    // expect(iter, Operator::LParen)?;
-    // expect_block(iter, Block::LParen)?;
-    let sc = ctx.s.enter();
-    let mut nodes = vec![stmt(iter, ctx)?];
-    while !consume_block(iter, Block::RParen) {
-        nodes.push(stmt(iter, ctx)?);
+        let input = "int main(){\n    _Static_assert(sizeof(long) == 4, \"long is 4 bytes\");\n}";
+        let err = program(
+            &mut token::tokenize(Rc::new(input.to_string()), Rc::new("".to_string())).unwrap(),
+        )
+        .unwrap_err();
+        let msg = err[0].to_string();
+        assert!(
+            msg.contains("static assertion failed: long is 4 bytes"),
+            "{}",
+            msg
+        );
+        assert!(msg.contains(":2:"), "{}", msg);
     }
-    expect(iter, Operator::RParen)?;
 
-    if nodes.last().unwrap().kind != NodeKind::ExprStmt {
-        return Err(Error::invalid_stmt_expr(
-            iter.filepath.clone(),
-            iter.input.clone(),
-            iter.pos,
-        ));
-    }
-    *(nodes.last_mut().unwrap()) = std::mem::replace(
-        nodes.last_mut().unwrap().lhs.as_mut().unwrap(),
-        Node::new_num(0),
-    );
-    ctx.s.leave(sc);
-    Ok(Node::new_leaf(NodeKind::StmtExpr(nodes)))
-}
+    #[test]
+    fn test_typeof() {
+        use crate::token;
+        use TypeKind::*;
+        let tests = [
+            ("typeof(int) x;", Int),
+            ("__typeof__(char) x;", Char),
+            ("typeof_unqual(long) x;", Long),
+            ("typeof(1) x;", Int),
+            ("typeof(int *) x;", Ptr(Rc::new(RefCell::new(Int)))),
+            (
+                "typeof(int[3]) x;",
+                Array(3, Rc::new(RefCell::new(Int)), true),
+            ),
+        ];
+        for (input, expected) in &tests {
+            let actual = declaration(
+                &mut token::tokenize(Rc::new(input.to_string()), Rc::new("".to_string())).unwrap(),
+                &mut Context::new(),
+            )
+            .unwrap();
+            assert_eq!(expected, &actual.type_kind, "{}", input);
+        }
 
-// primary     = num
-//             | ident func-args?
-//             | "(" expr ")"
-//             | str
-//             | char
-//             | "(" "{" stmt-expr-tail
-//             | sizeof unary
-//             | sizeof "(" type-name ")"
-pub fn primary(iter: &mut TokenStream, ctx: &mut Context) -> Result<Node, Error> {
-    // "(" expr ")"
-    if consume(iter, Operator::LParen) {
-        if consume_block(iter, Block::LParen) {
-            return Ok(stmt_expr(iter, ctx)?);
+        let tests = [
+            ("int main(){int a; typeof(a) b; return sizeof(b);}", true),
+            (
+                "int main(){int a; long l; typeof(a + l) b; return sizeof(b);}",
+                true,
+            ),
+            ("int main(){typeof(b) a; return 0;}", false),
+            ("int main(){typeof() a; return 0;}", false),
+        ];
+        for (input, ok) in &tests {
+            let actual = program(
+                &mut token::tokenize(Rc::new(input.to_string()), Rc::new("".to_string())).unwrap(),
+            );
+            assert_eq!(*ok, actual.is_ok(), "{}", input);
         }
-        let node = expr(iter, ctx)?;
-        expect(iter, Operator::RParen)?;
-        return Ok(node);
     }
 
-    // ident func-args?
-    if let Some(ident) = consume_ident(iter) {
-        if consume(iter, Operator::LParen) {
-            let func_prototype = ctx.g.func_prototype_mp.get(&ident.name).ok_or_else(|| {
-                iter.prev();
-                Error::undefined_function(
-                    iter.filepath.clone(),
-                    iter.input.clone(),
-                    ident,
-                    iter.pos,
-                    None,
-                )
-            })?;
-            return Ok(Node::new_leaf(NodeKind::Func(
-                func_prototype.clone(),
-                func_args(iter, ctx)?,
-            )));
+    #[test]
+    fn test_generic() {
+        use crate::token;
+        let tests = [
+            ("_Generic(1, char: 1, int: 2, long: 3)", Node::new_num(2)),
+            ("_Generic(1, char: 1, default: 4)", Node::new_num(4)),
+            ("_Generic(\"a\", char *: 5, int *: 6)", Node::new_num(5)),
+        ];
+        for (input, expected) in &tests {
+            let actual = primary(
+                &mut token::tokenize(Rc::new(input.to_string()), Rc::new("".to_string())).unwrap(),
+                &mut Context::new(),
+            )
+            .unwrap();
+            assert_eq!(expected, &actual, "{}", input);
         }
-        if let Some(lvar) = ctx.s.find_upper_lvar(ident.clone()) {
-            if lvar.borrow().dec.is_const.0 {
-                return Ok(Node::new_num(lvar.borrow().dec.is_const.1));
-            }
-            return Ok(Node::new_leaf(NodeKind::Lvar(lvar)));
-        } else if let Some(x) = ctx.s.find_upper_gvar(ident.clone()) {
-            return Ok(Node::new_leaf(NodeKind::Gvar(x.clone())));
-        } else {
-            iter.prev();
-            return Err(Error::undefined_variable(
-                iter.filepath.clone(),
-                iter.input.clone(),
-                ident,
-                iter.pos,
-                None,
-            ));
+
+        let tests = [
+            "_Generic(1, char: 1, long: 2)",
+            "_Generic(1, int: 1, int: 2)",
+            "_Generic(1, default: 1, default: 2)",
+            "_Generic(1)",
+        ];
+        for input in &tests {
+            let actual = primary(
+                &mut token::tokenize(Rc::new(input.to_string()), Rc::new("".to_string())).unwrap(),
+                &mut Context::new(),
+            );
+            assert!(actual.is_err(), "{}", input);
         }
     }
 
-    // str
-    if let Some(string) = consume_string(iter) {
-        let string = Rc::new(string);
-        let idx = ctx.g.tk_string.len();
-        let label = format!(".LC{}", idx).to_string();
-        ctx.g
-            .tk_string
-            .push((string.clone(), Rc::new(label.clone())));
-        let mut init = Vec::new();
-        gvar_init_string(&mut init, string.as_ref().clone());
-        return Ok(Node::new_leaf(make_string_node(
-            label,
-            (string.len()) as u64,
-            init,
-        )));
-    }
+    #[test]
+    fn test_const_expr() {
+        use crate::token;
+        let tests = [
+            ("1 + 2 * 3", 7),
+            ("(1 ? 2 : 3) + (0 ? 4 : 5)", 7),
+            ("6 ^ 3", 5),
+            ("1 << 4 >> 2", 4),
+            ("3 > 2 && 2 >= 2", 1),
+            ("0 || 1 < 0", 0),
+            ("(char)300", 44),
+            ("(short)65537", 1),
+            ("(_Bool)5 + !3 + ~0", 0),
+            ("sizeof(long) * 2 - -1", 17),
+            ("0 && 1 / 0", 0),
+        ];
+        for (input, expected) in &tests {
+            let actual = const_expr(
+                &mut token::tokenize(Rc::new(input.to_string()), Rc::new("".to_string())).unwrap(),
+                &mut Context::new(),
+            );
+            assert_eq!(Ok(*expected), actual, "{}", input);
+        }
 
-    // char
-    if let Some(c) = consume_char(iter) {
-        return Ok(Node::new_num(c as i64));
+        let tests = [
+            ("int g; int x = g; int main(){return 0;}", false),
+            ("int x = 1 / 0; int main(){return 0;}", false),
+            ("int g; int *p = &g * 2; int main(){return 0;}", false),
+            (
+                "int g; int h; long d = &g - &h; int main(){return 0;}",
+                false,
+            ),
+            (
+                "int main(){int x = 1; switch (1) {case x: return 1;} return 0;}",
+                false,
+            ),
+            ("int main(){int x; enum {A = x}; return 0;}", false),
+            ("int g; int *p = &g + 4; int main(){return 0;}", true),
+            (
+                "int a[4]; int *p = &a[3]; long d = &a[3] - a; int main(){return 0;}",
+                true,
+            ),
+            (
+                "struct s {int a; long b;} g; long *p = &g.b; int main(){return 0;}",
+                true,
+            ),
+            (
+                "int a[sizeof(int) * 2 + (1 ? 1 : 2)]; int main(){return 0;}",
+                true,
+            ),
+        ];
+        for (input, ok) in &tests {
+            let actual = program(
+                &mut token::tokenize(Rc::new(input.to_string()), Rc::new("".to_string())).unwrap(),
+            );
+            assert_eq!(*ok, actual.is_ok(), "{}", input);
+        }
     }
 
-    if consume(iter, Operator::Sizeof) {
-        if consume(iter, Operator::LParen) {
-            if is_typename(iter, ctx) {
-                let ty = type_name(iter, ctx)?;
-                expect(iter, Operator::RParen)?;
-                return Ok(Node::new_num(ty.borrow().size() as i64));
-            } else {
-                iter.prev();
-            }
+    #[test]
+    fn test_designated_initializer() {
+        use crate::token;
+        let tests = [
+            (
+                "int a[4] = {[2] = 5, 6};",
+                vec![(4, 0), (4, 0), (4, 5), (4, 6)],
+            ),
+            (
+                "int a[3] = {[0 ... 2] = 7, [1] = 1};",
+                vec![(4, 7), (4, 1), (4, 7)],
+            ),
+            ("int a[] = {[2] = 1};", vec![(4, 0), (4, 0), (4, 1)]),
+            (
+                "struct {char c; int n;} s = {.n = 2, .c = 1};",
+                vec![(1, 1), (1, 0), (1, 0), (1, 0), (4, 2)],
+            ),
+            (
+                "struct {int a[2]; int b;} s = {.a[1] = 3, .b = 4};",
+                vec![(4, 0), (4, 3), (4, 4), (1, 0), (1, 0), (1, 0), (1, 0)],
+            ),
+            (
+                "union {char c; int i;} u = {.i = 5};",
+                vec![(4, 5), (1, 0), (1, 0), (1, 0), (1, 0)],
+            ),
+            // `{}`で初期化し直すと、先に指示子で初期化した値は消える
+            (
+                "struct {struct {int a; int b;} s;} x = {.s.a = 5, .s = {3}};",
+                vec![(4, 3), (4, 0)],
+            ),
+            (
+                "struct {char c[3];} x = {.c[2] = 1, .c = \"a\"};",
+                vec![
+                    (1, 97),
+                    (1, 0),
+                    (1, 0),
+                    (1, 0),
+                    (1, 0),
+                    (1, 0),
+                    (1, 0),
+                    (1, 0),
+                ],
+            ),
+            (
+                "struct {int x; int y;} p[2] = {[0].y = 1, [0 ... 1].x = 2};",
+                vec![(4, 2), (4, 1), (4, 2), (4, 0)],
+            ),
+            // 指示子に続く初期化子は、指示した要素を含む集成体の次の要素から続く
+            (
+                "int m[2][3] = {[0][1] = 5, 6, 7};",
+                vec![(4, 0), (4, 5), (4, 6), (4, 7), (4, 0), (4, 0)],
+            ),
+            (
+                "struct {int a; int b[3]; struct {int x; int y;} p;} g = {.b[1] = 7, 1, 2, 3};",
+                vec![(4, 0), (4, 0), (4, 7), (4, 1), (4, 2), (4, 3)],
+            ),
+            (
+                "struct {int a; int b[3]; struct {int x; int y;} p;} g = {.p.y = 5, .b[1] = 7, 1};",
+                vec![(4, 0), (4, 0), (4, 7), (4, 1), (4, 0), (4, 5)],
+            ),
+        ];
+        for (input, expected) in &tests {
+            let mut ctx = Context::new();
+            let mut iter =
+                token::tokenize(Rc::new(input.to_string()), Rc::new("".to_string())).unwrap();
+            let dec = declaration(&mut iter, &mut ctx).unwrap();
+            expect(&mut iter, Operator::Assign).unwrap();
+            let mut init = Vec::new();
+            gvar_initializer(
+                &mut iter,
+                &mut ctx,
+                &mut init,
+                Rc::new(RefCell::new(dec.type_kind)),
+            )
+            .unwrap();
+            let expected: Vec<_> = expected
+                .iter()
+                .map(|(size, val)| Initializer::Val(*size, *val))
+                .collect();
+            assert_eq!(expected, init, "{}", input);
         }
-        let node = unary(iter, ctx)?;
-        match node.get_type() {
-            Ok(x) => return Ok(Node::new_num(x.size() as i64)),
-            Err(e) => {
-                dbg!("{}", e);
-                return Err(Error::todo(
-                    iter.filepath.clone(),
-                    iter.input.clone(),
-                    iter.pos,
-                ));
-            }
+
+        let tests = [
+            ("int a[3] = {[3] = 1}; int main(){return 0;}", false),
+            ("int main(){int a[3] = {[2 ... 1] = 1}; return 0;}", false),
+            ("struct {int x;} p = {.y = 1}; int main(){return 0;}", false),
+            (
+                "struct {int x;} p = {[0] = 1}; int main(){return 0;}",
+                false,
+            ),
+            ("int main(){int a[2] = {.x = 1}; return 0;}", false),
+            (
+                "int main(){struct {int x; int y;} p = {.y = 1, .x = 2}; return p.x;}",
+                true,
+            ),
+            (
+                "int main(){int a[] = {[0 ... 3] = 1}; return sizeof(a);}",
+                true,
+            ),
+        ];
+        for (input, ok) in &tests {
+            let actual = program(
+                &mut token::tokenize(Rc::new(input.to_string()), Rc::new("".to_string())).unwrap(),
+            );
+            assert_eq!(*ok, actual.is_ok(), "{}", input);
         }
     }
 
-    // num
-    return Ok(Node::new_num(expect_num(iter)?));
-}
+    #[test]
+    fn test_brace_elision() {
+        use crate::token;
+        let tests = [
+            (
+                "struct {int a[2]; struct {int x; int y;} p;} v = {1, 2, 3, 4};",
+                vec![(4, 1), (4, 2), (4, 3), (4, 4)],
+            ),
+            (
+                "int m[2][2] = {1, {2}, 3};",
+                vec![(4, 1), (4, 2), (4, 3), (4, 0)],
+            ),
+            (
+                "struct {int a[2]; int b;} s = {1, .b = 3};",
+                vec![(4, 1), (4, 0), (4, 3), (1, 0), (1, 0), (1, 0), (1, 0)],
+            ),
+            (
+                r#"char s[2][3] = {"a", "bc"};"#,
+                vec![(1, 97), (1, 0), (1, 0), (1, 98), (1, 99), (1, 0)],
+            ),
+            (
+                r#"struct {char c[2]; int n;} t[] = {"a", 1, 2};"#,
+                vec![
+                    (1, 97),
+                    (1, 0),
+                    (1, 0),
+                    (1, 0),
+                    (4, 1),
+                    (1, 2),
+                    (1, 0),
+                    (1, 0),
+                    (1, 0),
+                    (4, 0),
+                ],
+            ),
+            (
+                "struct {union {int i; char c;} u; int k;} s = {1, 2};",
+                vec![
+                    (4, 1),
+                    (1, 0),
+                    (1, 0),
+                    (1, 0),
+                    (1, 0),
+                    (4, 2),
+                    (1, 0),
+                    (1, 0),
+                    (1, 0),
+                    (1, 0),
+                ],
+            ),
+        ];
+        for (input, expected) in &tests {
+            let mut ctx = Context::new();
+            let mut iter =
+                token::tokenize(Rc::new(input.to_string()), Rc::new("".to_string())).unwrap();
+            let dec = declaration(&mut iter, &mut ctx).unwrap();
+            expect(&mut iter, Operator::Assign).unwrap();
+            let mut init = Vec::new();
+            gvar_initializer(
+                &mut iter,
+                &mut ctx,
+                &mut init,
+                Rc::new(RefCell::new(dec.type_kind)),
+            )
+            .unwrap();
+            let expected: Vec<_> = expected
+                .iter()
+                .map(|(size, val)| Initializer::Val(*size, *val))
+                .collect();
+            assert_eq!(expected, init, "{}", input);
+        }
 
-// func-args   = "(" (assign ("," assign)*)? ")"
-fn func_args(iter: &mut TokenStream, ctx: &mut Context) -> Result<Vec<Node>, Error> {
-    if consume(iter, Operator::RParen) {
-        return Ok(vec![]);
-    }
-    let mut args = vec![assign(iter, ctx)?];
-    while consume_comma(iter) {
-        args.push(assign(iter, ctx)?);
+        let tests = [
+            (
+                "int main(){struct {int a[2]; int b;} v = {1, 2, 3}; return v.b;}",
+                true,
+            ),
+            (
+                "int main(){struct P {int x; int y;}; struct P p = {1, 2}; struct {struct P p; int n;} t[2] = {p, 3, {p}}; return 0;}",
+                true,
+            ),
+            ("int main(){struct {} e = {}; return 0;}", true),
+            ("int m[2][2] = {1, 2, 3, 4, 5}; int main(){return 0;}", true),
+            (
+                "struct P {int x;} a; struct {struct P p; int n;} t = {a, 1}; int main(){return 0;}",
+                false,
+            ),
+        ];
+        for (input, ok) in &tests {
+            let actual = program(
+                &mut token::tokenize(Rc::new(input.to_string()), Rc::new("".to_string())).unwrap(),
+            );
+            assert_eq!(*ok, actual.is_ok(), "{}", input);
+        }
+
+        // `{}`を省略したメンバの式は一度だけ読むので、警告も一度だけ出る
+        assert_eq!(
+            1,
+            count_warnings("int main(){int *p = 0; struct P {int a; int b;}; struct {struct P s; int z;} q = {p == 1, 2, 3}; return q.z;}")
+        );
     }
-    expect(iter, Operator::RParen)?;
-    Ok(args)
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::ast::{GlobalContext, Ident, Lvar, NodeKind};
-    use crate::base_types::TypeKind;
+    #[test]
+    fn test_multiple_declarators() {
+        use crate::token;
+        use TypeKind::*;
+        let input = "int a = 1, *b = &a, c[3] = {0}, d; typedef int T, *PT; T e, f[2]; PT g;";
+        let actual = program(
+            &mut token::tokenize(Rc::new(input.to_string()), Rc::new("".to_string())).unwrap(),
+        )
+        .unwrap();
+        let int_ptr = Ptr(Rc::new(RefCell::new(Int)));
+        let tests = [
+            ("a", Int),
+            ("b", int_ptr.clone()),
+            ("c", Array(3, Rc::new(RefCell::new(Int)), true)),
+            ("d", Int),
+            ("e", Int),
+            ("f", Array(2, Rc::new(RefCell::new(Int)), true)),
+            ("g", int_ptr),
+        ];
+        for (name, expected) in &tests {
+            let gvar = actual.ctx.g.gvar_mp.get(*name).unwrap();
+            assert_eq!(expected, &gvar.dec.type_kind, "{}", name);
+        }
 
-    use std::rc::Rc;
+        let tests = [
+            (
+                "int main(){int a = 1, *b = &a, c[3] = {0}; return *b + c[2];}",
+                true,
+            ),
+            (
+                "int main(){int s = 0; for (int i = 0, j = 3; i < j; i++) s++; return s;}",
+                true,
+            ),
+            (
+                "struct {int x, *p, a[2];} s; int main(){return sizeof(s);}",
+                true,
+            ),
+            ("int f(int x), g = 1; int main(){return g;}", true),
+            ("int main(){int n = 2, v[n], w; return sizeof(v);}", true),
+            ("int main(){int a = 1 b; return 0;}", false),
+            ("int main(){int a, ; return 0;}", false),
+            ("int a, b int main(){return 0;}", false),
+            ("int f(), g(){return 0;}", false),
+        ];
+        for (input, ok) in &tests {
+            let actual = program(
+                &mut token::tokenize(Rc::new(input.to_string()), Rc::new("".to_string())).unwrap(),
+            );
+            assert_eq!(*ok, actual.is_ok(), "{}", input);
+        }
+    }
 
     #[test]
-    fn test_expr() {
+    fn test_function_declarator() {
         use crate::token;
+        use TypeKind::*;
+        let input =
+            "int (*fp)(int); int (*a)[3]; int (*ops[2])(int, int); typedef int F(int); F *g;";
+        let actual = program(
+            &mut token::tokenize(Rc::new(input.to_string()), Rc::new("".to_string())).unwrap(),
+        )
+        .unwrap();
+        let int = || Rc::new(RefCell::new(Int));
+        let param = || Declaration::new(Int, Ident::new_anonymous());
+        let func1 = Func(int(), Some(Rc::new(vec![param()])), false);
         let tests = [
-            ("1==10", make_test_node(NodeKind::Equal, 1, 10)),
-            ("1 != 10", make_test_node(NodeKind::Neq, 1, 10)),
-            ("1  <10", make_test_node(NodeKind::Lesser, 1, 10)),
-            ("1<=10", make_test_node(NodeKind::Leq, 1, 10)),
-            ("1>10", make_test_node(NodeKind::Lesser, 10, 1)), // Lesser,LeqはGreater.Geqを使って実装されてる
-            ("1>=10", make_test_node(NodeKind::Leq, 10, 1)),
-            ("1+10", make_test_node(NodeKind::Add, 1, 10)),
-            ("1-10", make_test_node(NodeKind::Sub, 1, 10)),
-            ("1*10", make_test_node(NodeKind::Mul, 1, 10)),
-            ("1/10", make_test_node(NodeKind::Div, 1, 10)),
-            ("+1", Node::new_num(1)),
-            ("-1", make_test_node(NodeKind::Sub, 0, 1)),
+            ("fp", Ptr(Rc::new(RefCell::new(func1.clone())))),
+            ("a", Ptr(Rc::new(RefCell::new(Array(3, int(), true))))),
             (
-                "2 * ( 3 + 4)",
-                Node::new(
-                    NodeKind::Mul,
-                    Node::new_num(2),
-                    make_test_node(NodeKind::Add, 3, 4),
+                "ops",
+                Array(
+                    2,
+                    Rc::new(RefCell::new(Ptr(Rc::new(RefCell::new(Func(
+                        int(),
+                        Some(Rc::new(vec![param(), param()])),
+                        false,
+                    )))))),
+                    true,
                 ),
             ),
-            ("42", Node::new_num(42)),
+            ("g", Ptr(Rc::new(RefCell::new(func1)))),
         ];
+        for (name, expected) in &tests {
+            let gvar = actual.ctx.g.gvar_mp.get(*name).unwrap();
+            assert_eq!(expected, &gvar.dec.type_kind, "{}", name);
+        }
 
-        for (s, expected) in &tests {
-            assert_eq!(
-                expected,
-                &expr(
-                    &mut token::tokenize(Rc::new(s.to_string()), Rc::new("".to_string())).unwrap(),
-                    &mut Context::new()
-                )
-                .unwrap()
-            )
+        let tests = [
+            (
+                "int f(int x){return x;} int main(){int (*p)(int) = f; return p(1) + (*p)(2);}",
+                true,
+            ),
+            (
+                "void (*signal(int sig, void (*h)(int)))(int){return h;} int main(){return 0;}",
+                true,
+            ),
+            (
+                "int f(int x){return x;} int (*tbl[])(int) = {f, &f}; int main(){return tbl[1](3);}",
+                true,
+            ),
+            ("int main(){int g(int); return g(1);}", true),
+            ("int main(){int x = 1; return x(2);}", false),
+            ("int main(){int (*p)(int; return 0;}", false),
+        ];
+        for (input, ok) in &tests {
+            let actual = program(
+                &mut token::tokenize(Rc::new(input.to_string()), Rc::new("".to_string())).unwrap(),
+            );
+            assert_eq!(*ok, actual.is_ok(), "{}", input);
         }
     }
 
     #[test]
-    fn test_stmt() {
+    fn test_implicit_conversion() {
         use crate::token;
+        let input = "char f(char c, long l){return c + l;}";
+        let actual = program(
+            &mut token::tokenize(Rc::new(input.to_string()), Rc::new("".to_string())).unwrap(),
+        )
+        .unwrap();
+        let ret = &actual.functions[0].nodes[0];
+        assert_eq!(NodeKind::Return, ret.kind);
+        // 戻り値はcharに、加算の左辺はlongに変換される
+        let cast = ret.lhs.as_ref().unwrap();
+        assert_eq!(NodeKind::Cast(TypeKind::Char), cast.kind);
+        let add = cast.lhs.as_ref().unwrap();
+        assert_eq!(NodeKind::Add, add.kind);
+        assert_eq!(
+            NodeKind::Cast(TypeKind::Long),
+            add.lhs.as_ref().unwrap().kind
+        );
+        assert_eq!(
+            TypeKind::Long,
+            add.rhs.as_ref().unwrap().get_type().unwrap()
+        );
+    }
 
-        let tests = [(
-            "int foo;",
-            vec![Node::new_leaf(NodeKind::Declaration(make_int_dec("foo")))],
-        )];
-
-        for (s, expected) in &tests {
-            let mut iter =
-                token::tokenize(Rc::new(s.to_string()), Rc::new("".to_string())).unwrap();
-            let mut actual = Vec::new();
-
-            let ctx = &mut Context::new();
-            while iter.peek() != None {
-                actual.push(stmt(&mut iter, ctx).unwrap());
-            }
-            assert_eq!(expected, &actual);
+    #[test]
+    fn test_func_call_check() {
+        use crate::token;
+        let tests = [
+            ("int f(int a, int b); int main(){return f(1, 2);}", true),
+            ("int f(); int main(){return f(1, 2, 3);}", true),
+            ("int f(int a, ...); int main(){return f(1, 2, 3);}", true),
+            ("int f(int); int f(int a){return a;} int f(int);", true),
+            ("int f(); int f(int a, char *b);", true),
+            ("int f(int a, int b); int main(){return f(1);}", false),
+            ("int f(int a); int main(){return f(1, 2);}", false),
+            ("int f(void); int main(){return f(1);}", false),
+            ("int f(int a, ...); int main(){return f();}", false),
+            ("int main(){int (*fp)(int); return fp();}", false),
+            ("int f(int); long f(char *);", false),
+            ("int f(int); int main(){long f(int); return 0;}", false),
+            ("int f(int a, ...); int f(int a);", false),
+            ("int f(){return 0;} int f(){return 1;}", false),
+            ("int main(){return g();}", false),
+            (
+                "struct A {int x;}; int f(struct A a); int main(){return f(1);}",
+                false,
+            ),
+            (
+                "void v(void); int f(int a); int main(){return f(v());}",
+                false,
+            ),
+        ];
+        for (input, ok) in &tests {
+            let actual = program(
+                &mut token::tokenize(Rc::new(input.to_string()), Rc::new("".to_string())).unwrap(),
+            );
+            assert_eq!(*ok, actual.is_ok(), "{}", input);
         }
     }
 
     #[test]
-    fn test_unary() {
-        use crate::ast::Var;
+    fn test_assignment_check() {
         use crate::token;
-        let expected = vec![
-            Node::new_num(1),
-            Node::new_num(1),
-            Node::new_leaf(NodeKind::Lvar(Rc::new(RefCell::new(Lvar::new_leaf(
-                make_int_dec("hoge"),
-                8,
-            ))))),
-            Node::new(NodeKind::Sub, Node::new_num(0), Node::new_num(1)),
-            Node::new(
-                NodeKind::Sub,
-                Node::new_num(0),
-                Node::new_leaf(NodeKind::Lvar(Rc::new(RefCell::new(Lvar::new_leaf(
-                    make_int_dec("hoge"),
-                    8,
-                ))))),
+        let tests = [
+            ("int main(){int x; void *v = &x; int *p = v; return *p;}", true),
+            ("int main(){int *p = 0; p = (void *)0; return p == 0;}", true),
+            ("int *f(void){return 0;} int main(){return 0;}", true),
+            ("int main(){int x; _Bool b = &x; return b;}", true),
+            // ポインタと整数の混同や互換でないポインタは警告だけ
+            ("int main(){int x; char *c = &x; int *p = 5; return p == c;}", true),
+            (
+                "struct A {int x;}; struct B {int y;}; int main(){struct A a; struct B b; a = b; return 0;}",
+                false,
             ),
-            Node::new_unary(NodeKind::Deref, Node::new_num(1)),
-            Node::new_unary(NodeKind::Addr, Node::new_num(1)),
-            Node::new_unary(
-                NodeKind::Deref,
-                Node::new_leaf(NodeKind::Lvar(Rc::new(RefCell::new(Lvar::new_leaf(
-                    make_int_dec("hoge"),
-                    8,
-                ))))),
+            (
+                "struct A {int x;}; int main(){struct A a; int x = a; return x;}",
+                false,
             ),
-            Node::new_unary(
-                NodeKind::Addr,
-                Node::new_leaf(NodeKind::Lvar(Rc::new(RefCell::new(Lvar::new_leaf(
-                    make_int_dec("hoge"),
-                    8,
-                ))))),
+            (
+                "struct A {int x;}; struct A f(void){return 1;} int main(){return 0;}",
+                false,
             ),
-            Node::new_unary(
-                NodeKind::Deref,
-                Node::new_unary(
-                    NodeKind::Addr,
-                    Node::new_leaf(NodeKind::Lvar(Rc::new(RefCell::new(Lvar::new_leaf(
-                        make_int_dec("hoge"),
-                        8,
-                    ))))),
-                ),
+            // 内側のスコープで定義し直した同じタグの構造体は別の型
+            (
+                "struct A {int x;}; int main(){struct A a; {struct A {int x;}; struct A b; b = a;} return 0;}",
+                false,
+            ),
+            (
+                "struct A {int x;}; int main(){struct A a; {struct A b; b = a;} return 0;}",
+                true,
             ),
         ];
-
-        let input = "1 +1 +hoge -1 -hoge *1 &1 *hoge &hoge *&hoge ";
-        let iter =
-            &mut token::tokenize(Rc::new(input.to_string()), Rc::new("".to_string())).unwrap();
-        for i in expected {
-            let ctx = &mut Context::new();
-            ctx.l.lvar = Some(Rc::new(RefCell::new(make_int_lvar("hoge", 8))));
-            ctx.s.insert_v(
-                Ident::new("hoge"),
-                Rc::new(Var::L(Rc::new(RefCell::new(make_int_lvar("hoge", 8))))),
+        for (input, ok) in &tests {
+            let actual = program(
+                &mut token::tokenize(Rc::new(input.to_string()), Rc::new("".to_string())).unwrap(),
             );
-            assert_eq!(i, unary(iter, ctx).unwrap());
+            assert_eq!(*ok, actual.is_ok(), "{}", input);
         }
+    }
 
-        let expected = vec![
-            (Node::new_num(4), make_int_lvar("hoge", 8)),
-            (Node::new_num(4), make_int_lvar("hoge", 8)),
-            (Node::new_num(8), make_ptr_lvar("hoge", 8)),
-            (Node::new_num(4), make_ptr_lvar("hoge", 8)),
-        ];
-
-        let input = "sizeof 1 sizeof (hoge) sizeof (hoge) sizeof(*hoge)";
-        let iter =
-            &mut token::tokenize(Rc::new(input.to_string()), Rc::new("".to_string())).unwrap();
-        for i in expected {
-            let ctx = &mut Context::new();
-            ctx.l.lvar = Some(Rc::new(RefCell::new(i.1.clone())));
-            ctx.s.insert_v(
-                i.1.dec.ident.clone(),
-                Rc::new(Var::L(Rc::new(RefCell::new(i.1)))),
-            );
+    #[test]
+    fn test_error_recovery() {
+        use crate::token;
+        let input = "int g = ; int f(int x){int y = x +; y = z; for (;;) {x = ;} return y;} int h(){return f(1, 2);}";
+        let errors = |limit: usize| {
+            let mut ctx = Context::new();
+            ctx.error_limit = limit;
+            program_with_context(
+                &mut token::tokenize(Rc::new(input.to_string()), Rc::new("".to_string())).unwrap(),
+                ctx,
+            )
+            .unwrap_err()
+        };
+        let all = errors(0);
+        assert_eq!(5, all.len());
+        assert!(all.iter().all(|err| !err.is_fatal()));
+
+        // 上限に達したら、それ以上は解析しない
+        let limited = errors(2);
+        assert_eq!(3, limited.len());
+        assert!(limited[2].is_fatal());
+
+        // 失敗した文や宣言から続くエラーや警告は報告しない
+        assert_eq!(1, count_warnings("int h(){return x;}"));
+        assert_eq!(1, count_warnings("int f(){int unused; return y;}"));
+        assert_eq!(1, count_warnings("int g = ; int main(){return g;}"));
+        assert_eq!(1, count_warnings("int main(){struct Q q; return q.a + 1;}"));
+        // エラーにした警告では後の解析を省かない
+        assert_eq!(
+            2,
+            count_warnings("int f(int a){char *p = 1; if (a) return *p;}")
+        );
+    }
 
-            assert_eq!(i.0, unary(iter, ctx).unwrap());
-        }
+    #[test]
+    fn test_label_and_case() {
+        use crate::token;
+        let errors = |input: &str| match program_with_context(
+            &mut token::tokenize(Rc::new(input.to_string()), Rc::new("".to_string())).unwrap(),
+            Context::new(),
+        ) {
+            Ok(_) => 0,
+            Err(errors) => errors.len(),
+        };
+        assert_eq!(0, errors("int main(){goto end; end: return 0;}"));
+        assert_eq!(0, errors("int main(){goto end; return 0; end: return 1;}"));
+        assert_eq!(1, errors("int main(){goto end; return 0;}"));
+        assert_eq!(1, errors("int main(){a: a: return 0;}"));
+        assert_eq!(0, errors("int f(){a: return 0;} int main(){a: return 0;}"));
+        assert_eq!(
+            0,
+            errors("int main(){switch (1){case 1 ... 3: case 4: default: break;} return 0;}")
+        );
+        assert_eq!(
+            1,
+            errors("int main(){switch (1){case 1: case 1: break;} return 0;}")
+        );
+        assert_eq!(
+            1,
+            errors("int main(){switch (1){case 1 ... 5: break; case 3: break;} return 0;}")
+        );
+        assert_eq!(
+            1,
+            errors("int main(){switch (1){case 5 ... 1: break;} return 0;}")
+        );
+        assert_eq!(
+            1,
+            errors("int main(){switch (1){default: break; default: break;} return 0;}")
+        );
+        assert_eq!(
+            0,
+            errors("int main(){switch (1){case 1: switch (2){case 1: break;}} return 0;}")
+        );
     }
 
     #[test]
-    fn test_declaration() {
+    fn test_suggestion() {
         use crate::token;
-        use TypeKind::*;
+        assert_eq!(0, edit_distance("width", "width"));
+        assert_eq!(2, edit_distance("widht", "width"));
+        assert_eq!(1, edit_distance("arg", "argc"));
+        assert_eq!(3, edit_distance("", "abc"));
+
+        let message = |input: &str| {
+            let errors = program_with_context(
+                &mut token::tokenize(Rc::new(input.to_string()), Rc::new("".to_string())).unwrap(),
+                Context::new(),
+            )
+            .unwrap_err();
+            errors[0].to_string()
+        };
         let tests = [
-            ("int hoge", Declaration::new(Int, Ident::new("hoge"))),
-            ("int *hoge", make_ptr_lvar("hoge", 8).dec),
+            ("int counter; int main(){return countr;}", Some("counter")),
             (
-                "int **hoge",
-                Declaration::new(
-                    Ptr(Rc::new(RefCell::new(Ptr(Rc::new(RefCell::new(Int)))))),
-                    Ident::new("hoge"),
-                ),
+                "int compute(){return 0;} int main(){return compte();}",
+                Some("compute"),
             ),
+            ("int main(int argc){{int x; return arg;}}", Some("argc")),
             (
-                "int hoge[1]",
-                Declaration::new(
-                    Array(1, Rc::new(RefCell::new(Int)), true),
-                    Ident::new("hoge"),
-                ),
+                "struct p {int width;}; int main(){struct p a; return a.widht;}",
+                Some("width"),
             ),
             (
-                "int *hoge[1]",
-                Declaration::new(
-                    Array(
-                        1,
-                        Rc::new(RefCell::new(Ptr(Rc::new(RefCell::new(Int))))),
-                        true,
-                    ),
-                    Ident::new("hoge"),
-                ),
+                "struct p {int alpha;}; int main(){struct p a = {.alhpa = 1}; return 0;}",
+                Some("alpha"),
             ),
-            ("char hoge", Declaration::new(Char, Ident::new("hoge"))),
             (
-                "char **hoge",
-                Declaration::new(
-                    Ptr(Rc::new(RefCell::new(Ptr(Rc::new(RefCell::new(Char)))))),
-                    Ident::new("hoge"),
-                ),
+                "enum color {RED}; int main(){enum colr c; return 0;}",
+                Some("color"),
             ),
             (
-                "char hoge[1]",
-                Declaration::new(
-                    Array(1, Rc::new(RefCell::new(Char)), true),
-                    Ident::new("hoge"),
-                ),
+                "struct Point {int x;}; int main(){struct Pont r; return 0;}",
+                Some("Point"),
             ),
             (
-                "char *hoge[1]",
-                Declaration::new(
-                    Array(
-                        1,
-                        Rc::new(RefCell::new(Ptr(Rc::new(RefCell::new(Char))))),
-                        true,
-                    ),
-                    Ident::new("hoge"),
-                ),
+                "struct Point {int x;}; int main(){return sizeof(struct Pnt);}",
+                Some("Point"),
             ),
             (
-                "int hoge[2][2]",
-                Declaration::new(
-                    Array(
-                        2,
-                        Rc::new(RefCell::new(Array(2, Rc::new(RefCell::new(Int)), true))),
-                        true,
-                    ),
-                    Ident::new("hoge"),
-                ),
+                "union Value {int i;}; int main(){union Vlue *p; return p->i;}",
+                Some("Value"),
+            ),
+            (
+                "struct Point {int x;}; struct Zzz; int main(){struct Zzz z; return 0;}",
+                None,
             ),
+            ("int main(){int value; return xyz;}", None),
+            ("int main(int a){{int b; return a + c;}}", None),
         ];
-
         for (input, expected) in &tests {
-            assert_eq!(
-                *expected,
-                declaration(
-                    &mut token::tokenize(Rc::new(input.to_string()), Rc::new("".to_string()))
-                        .unwrap(),
-                    &mut Context::new()
-                )
-                .unwrap()
-            );
+            let message = message(input);
+            match expected {
+                Some(name) => assert!(
+                    message.contains(&format!("did you mean '{}'?", name)),
+                    "{}",
+                    message
+                ),
+                None => assert!(!message.contains("did you mean"), "{}", message),
+            }
         }
     }
 
     #[test]
-    fn test_struct_dec() {
+    fn test_diagnostic_notes() {
         use crate::token;
-        use TypeKind::{Char, Int, Ptr};
-
-        let members = Rc::new(vec![
-            Rc::new(make_member(Int, "first", 0)),
-            Rc::new(make_member(Int, "second", 4)),
-        ]);
-        let expected = Rc::new(RefCell::new(Struct::new(
-            Rc::new(Ident::new("hoge")),
-            members,
-        )));
-        let input = "struct hoge {int first; int second;}";
-        let actual = struct_dec(
-            &mut token::tokenize(Rc::new(input.to_string()), Rc::new("".to_string())).unwrap(),
-            &mut Context::new(),
+        let message = |input: &str| {
+            let errors = program_with_context(
+                &mut token::tokenize(Rc::new(input.to_string()), Rc::new("a.c".to_string()))
+                    .unwrap(),
+                Context::new(),
+            )
+            .unwrap_err();
+            errors[0].to_string()
+        };
+
+        let actual = message("int main(){\n  int x;\n  int y, x;\n  return 0;\n}");
+        assert_eq!(
+            "a.c:3:10: error: variable or tag \"x\" is already defined\n  int y, x;\n         ^\n\
+             a.c:2:7: note: previous declaration is here\n  int x;\n      ^",
+            actual
+        );
+        let actual = message("struct s {int a;};\nstruct s {int b;};");
+        assert!(
+            actual.contains("a.c:2:8: error: ")
+                && actual.contains("a.c:1:8: note: previous definition is here"),
+            "{}",
+            actual
+        );
+        let actual = message("int f(){return 0;}\nint f(){return 1;}");
+        assert!(
+            actual.contains("a.c:2:5: error: ")
+                && actual.contains("a.c:1:1: note: previous definition is here"),
+            "{}",
+            actual
+        );
+        let actual = message("int f(int a);\nint f(int a, int b);");
+        assert!(
+            actual.contains("a.c:2:5: error: conflicting types for f")
+                && actual.contains("a.c:1:5: note: previous declaration is here"),
+            "{}",
+            actual
+        );
+        // 別のスコープなら隠してよい
+        assert!(program(
+            &mut token::tokenize(
+                Rc::new("int main(){int x; {int x; return x;}}".to_string()),
+                Rc::new("".to_string())
+            )
+            .unwrap()
         )
-        .unwrap();
-        assert_eq!(expected, actual);
-        assert_eq!(8, actual.borrow().get_size());
+        .is_ok());
 
-        let members = Rc::new(vec![
-            Rc::new(make_member(Int, "first", 0)),
-            Rc::new(make_member(Int, "second", 4)),
-            Rc::new(make_member(Char, "third", 8)),
-            Rc::new(make_member(Int, "four", 12)),
-        ]);
-        let expected = Rc::new(RefCell::new(Struct::new(
-            Rc::new(Ident::new("hoge")),
-            members,
-        )));
-        let input = "struct hoge {int first; int second; char third; int four;}";
-        let actual = struct_dec(
+        // 似た名前への置き換えを下に示す
+        let actual = message("int counter;\nint main(){return countr;}");
+        assert!(
+            actual.ends_with(
+                "int main(){return countr;}\n                  ^~~~~~\n                  counter"
+            ),
+            "{}",
+            actual
+        );
+    }
+
+    #[test]
+    fn test_werror() {
+        use crate::token;
+        let compile = |input: &str| {
+            let ctx = Context::new();
+            ctx.diag.borrow_mut().parse_option("-Werror").unwrap();
+            program_with_context(
+                &mut token::tokenize(Rc::new(input.to_string()), Rc::new("".to_string())).unwrap(),
+                ctx,
+            )
+        };
+        let errors = compile("int main(){int x; int *p = x; char *c = &x; return 0;}").unwrap_err();
+        assert_eq!(2, errors.len());
+
+        let input = "int main(){\nint x;\n#pragma GCC diagnostic push\n#pragma GCC diagnostic ignored \"-Wint-conversion\"\nint *p = x;\n#pragma GCC diagnostic pop\nreturn 0;}";
+        assert!(compile(input).is_ok());
+        let input = input.replace("return 0;", "p = x; return 0;");
+        assert_eq!(1, compile(&input).unwrap_err().len());
+    }
+
+    #[test]
+    fn test_diagnostic_order() {
+        use crate::token;
+        let compile = |input: &str| {
+            program_with_context(
+                &mut token::tokenize(Rc::new(input.to_string()), Rc::new("".to_string())).unwrap(),
+                Context::new(),
+            )
+        };
+        // 警告とエラーは出した順に並ぶ
+        let diagnostics =
+            compile("int main(){int x = 0; int *p = x; return y; char *c = 1;}").unwrap_err();
+        let is_warning: Vec<_> = diagnostics.iter().map(|d| d.is_warning()).collect();
+        assert_eq!(vec![true, false, true], is_warning);
+
+        // エラーがなければ、警告はプログラムと一緒に返す
+        let program = compile("int main(){int x = 0; int *p = x; return 0;}").unwrap();
+        assert_eq!(1, program.ctx.diagnostics.len());
+        assert!(program.ctx.diagnostics[0].is_warning());
+    }
+
+    /// `-Wall -Wextra -Werror`でエラーになった警告の数
+    fn count_warnings(input: &str) -> usize {
+        use crate::token;
+        let ctx = Context::new();
+        for option in ["-Wall", "-Wextra", "-Werror"] {
+            ctx.diag.borrow_mut().parse_option(option).unwrap();
+        }
+        match program_with_context(
             &mut token::tokenize(Rc::new(input.to_string()), Rc::new("".to_string())).unwrap(),
-            &mut Context::new(),
-        )
-        .unwrap();
-        assert_eq!(expected, actual);
-        assert_eq!(16, actual.borrow().get_size());
+            ctx,
+        ) {
+            Ok(_) => 0,
+            Err(errors) => errors.len(),
+        }
+    }
 
-        let members = Rc::new(vec![
-            Rc::new(make_member(Int, "first", 0)),
-            Rc::new(make_member(Ptr(Rc::new(RefCell::new(Int))), "second", 8)),
-            Rc::new(make_member(Int, "four", 16)),
-        ]);
-        let expected = Rc::new(RefCell::new(Struct::new(
-            Rc::new(Ident::new("hoge")),
-            members,
-        )));
-        let input = "struct hoge {int first; int *second; int four;}";
-        let actual = struct_dec(
+    #[test]
+    fn test_unused() {
+        let unused = count_warnings;
+        assert_eq!(0, unused("int main(int argc){int x = argc; return x;}"));
+        assert_eq!(1, unused("int main(){int x; return 0;}"));
+        assert_eq!(1, unused("int f(int a, int b){return b;}"));
+        assert_eq!(1, unused("int main(){int x = 1; {int x = 2;} return x;}"));
+        assert_eq!(1, unused("int main(){static int s; return 0;}"));
+        assert_eq!(1, unused("static int g; int main(){return 0;}"));
+        assert_eq!(0, unused("static int g; int main(){return g;}"));
+        assert_eq!(1, unused("static int f(){return 0;} int main(){return 0;}"));
+        assert_eq!(
+            0,
+            unused("static int f(); int main(){return f();} static int f(){return 0;}")
+        );
+        assert_eq!(1, unused("int main(){a: goto b; b: return 0;}"));
+        assert_eq!(
+            0,
+            unused("int main(){enum {A}; int x[2]; return sizeof(x);}")
+        );
+    }
+
+    #[test]
+    fn test_flow() {
+        let flow = count_warnings;
+        assert_eq!(1, flow("int f(int a){if (a) return 1;}"));
+        assert_eq!(0, flow("int f(int a){if (a) return 1; else return 2;}"));
+        assert_eq!(0, flow("int f(int a){while (1) if (a) return a;}"));
+        assert_eq!(0, flow("void exit(int); int f(int a){exit(a);}"));
+        assert_eq!(0, flow("int main(){}"));
+        assert_eq!(1, flow("int f(){return 1; f();}"));
+        assert_eq!(
+            0,
+            flow("int f(int a){if (a) goto end; a++; end: return a;}")
+        );
+        assert_eq!(
+            1,
+            flow("int f(int a){goto end; a++; return a; end: return 0;}")
+        );
+        assert_eq!(
+            1,
+            flow("int f(int a){switch (a){case 0: a++; case 1: return a;} return 0;}")
+        );
+        assert_eq!(
+            0,
+            flow("int f(int a){switch (a){case 0: case 1: return a; default: break;} return 0;}")
+        );
+        assert_eq!(
+            0,
+            flow("int f(int a){switch (a){case 0: a++; __attribute__((fallthrough)); case 1: return a;} return 0;}")
+        );
+    }
+
+    #[test]
+    fn test_uninit() {
+        let uninit = count_warnings;
+        assert_eq!(1, uninit("int f(){int x; return x;}"));
+        assert_eq!(1, uninit("int f(int a){int x; if (a) x = 1; return x;}"));
+        assert_eq!(
+            0,
+            uninit("int f(int a){int x; if (a) x = 1; else x = 2; return x;}")
+        );
+        assert_eq!(1, uninit("int f(){int x; x++; return x;}"));
+        assert_eq!(0, uninit("int g(int *p); int f(){int x; g(&x); return x;}"));
+        assert_eq!(
+            1,
+            uninit("int f(int a){int x; for (int i = 0; i < a; i++) x = i; return x;}")
+        );
+        assert_eq!(
+            0,
+            uninit("int f(int a){int x; while (1) if (a) {x = 1; break;} return x;}")
+        );
+        assert_eq!(1, uninit("int f(int a){int x; a && (x = 1); return x;}"));
+        assert_eq!(0, uninit("int f(int a){int x; (void)x; return a;}"));
+        assert_eq!(0, uninit("int f(){int x, y; x = y = 1; return x + y;}"));
+        assert_eq!(
+            0,
+            uninit("int f(){int x; x = ({int y = 1; y;}); return x;}")
+        );
+
+        // 短絡評価の条件の中の代入は、条件の値が決まる側の分岐でだけ数える
+        assert_eq!(
+            0,
+            uninit("int f(int c){int x; if (c && (x = 3)) return x; return 0;}")
+        );
+        assert_eq!(
+            0,
+            uninit("int f(int c){int x; if (!c || (x = 3) == 0) return 0; return x;}")
+        );
+        assert_eq!(
+            1,
+            uninit("int f(int c){int x; if (c && (x = 3)) return 0; return x;}")
+        );
+        assert_eq!(
+            1,
+            uninit("int f(int c){int x; if (c || (x = 3)) return x; return 0;}")
+        );
+        assert_eq!(
+            0,
+            uninit("int f(int c){int x; while (c && (x = c--)) if (x) return x; return 0;}")
+        );
+    }
+
+    #[test]
+    fn test_span() {
+        use crate::token;
+        let input = "int g;\nint main() {\n    int x = 1;\n    return g + x * 2;\n}\n";
+        let program = program(
             &mut token::tokenize(Rc::new(input.to_string()), Rc::new("".to_string())).unwrap(),
-            &mut Context::new(),
         )
         .unwrap();
-        assert_eq!(expected, actual);
-        assert_eq!(24, actual.borrow().get_size());
+        let text = |span: &Span| &input[span.begin..span.end];
+
+        let function = &program.functions[0];
+        assert_eq!(input[7..].trim_end(), text(&function.span));
+        assert_eq!((2, 1), (function.span.line, function.span.col));
+        let g = program.ctx.g.gvar_mp.get("g").unwrap();
+        assert_eq!("g", text(&g.dec.span));
+
+        let ret = &function.nodes[1];
+        assert_eq!("return g + x * 2;", text(&ret.span));
+        assert_eq!((4, 5), (ret.span.line, ret.span.col));
+        let add = ret.lhs.as_ref().unwrap();
+        assert_eq!("g + x * 2", text(&add.span));
+        assert_eq!("x * 2", text(&add.rhs.as_ref().unwrap().span));
+
+        // 暗黙に補ったノードも含めて、すべてのノードが位置を持つ
+        fn assert_spanned(node: &Node) {
+            assert!(!node.span.is_dummy(), "{:?}", node.kind);
+            for child in [&node.lhs, &node.rhs, &node.cond, &node.then, &node.els]
+                .into_iter()
+                .flatten()
+            {
+                assert_spanned(child);
+            }
+        }
+        for node in &function.nodes {
+            assert_spanned(node);
+        }
     }
 
     fn make_member(type_kind: TypeKind, name: impl Into<String>, offset: u64) -> Member {
@@ -0,0 +1,338 @@
+use super::{Function, Node, NodeKind};
+use std::collections::HashMap;
+
+/// 戻ってこない標準ライブラリの関数
+/// `__attribute__((noreturn))`は読めないので名前で判断する
+const NORETURN_FUNCS: [&str; 6] = [
+    "exit",
+    "_Exit",
+    "quick_exit",
+    "abort",
+    "longjmp",
+    "__assert_fail",
+];
+
+/// 基本ブロック。途中で分岐も合流もしない
+#[derive(Clone, Debug, Default)]
+pub struct BasicBlock<'a> {
+    /// 先頭から順に評価する式や宣言
+    pub nodes: Vec<&'a Node>,
+    pub succs: Vec<usize>,
+    pub preds: Vec<usize>,
+    /// 最後の式の値で分岐するとき、その条件と、真のときに進むブロック
+    /// 他の後続のブロックには偽のときに進む
+    pub branch: Option<(&'a Node, usize)>,
+}
+
+/// 関数の本体の制御フローグラフ
+/// 0番目のブロックが関数の入口
+#[derive(Clone, Debug)]
+pub struct Cfg<'a> {
+    pub blocks: Vec<BasicBlock<'a>>,
+    /// `return`を通らずに関数の終わりに達したときのブロック
+    pub exit: usize,
+    /// 文と、その文を実行し始めるブロック
+    pub stmts: Vec<(&'a Node, usize)>,
+    /// 前の文から流れ込む`case`, `default`と、流れ込んでくるブロック
+    pub fallthroughs: Vec<(&'a Node, usize)>,
+}
+
+impl<'a> Cfg<'a> {
+    pub fn new(function: &'a Function) -> Self {
+        let mut builder = Builder::new();
+        for node in &function.nodes {
+            builder.stmt(node);
+        }
+        builder.finish()
+    }
+
+    /// 入口からたどり着けるブロック
+    pub fn reachable(&self) -> Vec<bool> {
+        let mut reachable = vec![false; self.blocks.len()];
+        let mut stack = vec![0];
+        while let Some(block) = stack.pop() {
+            if reachable[block] {
+                continue;
+            }
+            reachable[block] = true;
+            stack.extend(&self.blocks[block].succs);
+        }
+        reachable
+    }
+}
+
+/// `switch`の本体を読んでいる間の情報
+struct SwitchScope {
+    /// 条件を評価して各`case`へ分岐するブロック
+    dispatch: usize,
+    has_default: bool,
+}
+
+struct Builder<'a> {
+    blocks: Vec<BasicBlock<'a>>,
+    cur: usize,
+    breaks: Vec<usize>,
+    continues: Vec<usize>,
+    switches: Vec<SwitchScope>,
+    labels: HashMap<String, usize>,
+    gotos: Vec<(usize, String)>,
+    stmts: Vec<(&'a Node, usize)>,
+    fallthroughs: Vec<(&'a Node, usize)>,
+    /// 直前の`case`から後に文があったか
+    has_stmt: bool,
+    /// 直前の文が`__attribute__((fallthrough));`か
+    annotated: bool,
+}
+
+impl<'a> Builder<'a> {
+    fn new() -> Self {
+        Self {
+            blocks: vec![BasicBlock::default()],
+            cur: 0,
+            breaks: Vec::new(),
+            continues: Vec::new(),
+            switches: Vec::new(),
+            labels: HashMap::new(),
+            gotos: Vec::new(),
+            stmts: Vec::new(),
+            fallthroughs: Vec::new(),
+            has_stmt: false,
+            annotated: false,
+        }
+    }
+
+    fn finish(mut self) -> Cfg<'a> {
+        for (from, label) in std::mem::take(&mut self.gotos) {
+            if let Some(&to) = self.labels.get(&label) {
+                self.edge(from, to);
+            }
+        }
+        for from in 0..self.blocks.len() {
+            for to in self.blocks[from].succs.clone() {
+                self.blocks[to].preds.push(from);
+            }
+        }
+        Cfg {
+            exit: self.cur,
+            blocks: self.blocks,
+            stmts: self.stmts,
+            fallthroughs: self.fallthroughs,
+        }
+    }
+
+    fn new_block(&mut self) -> usize {
+        self.blocks.push(BasicBlock::default());
+        self.blocks.len() - 1
+    }
+
+    fn edge(&mut self, from: usize, to: usize) {
+        self.blocks[from].succs.push(to);
+    }
+
+    /// `to`へ飛ぶ。その後に続く文はどこからも来ないブロックに入る
+    fn jump(&mut self, to: Option<usize>) {
+        if let Some(to) = to {
+            self.edge(self.cur, to);
+        }
+        self.cur = self.new_block();
+    }
+
+    /// 今のブロックから`to`へ進む
+    fn enter(&mut self, to: usize) {
+        self.edge(self.cur, to);
+        self.cur = to;
+    }
+
+    /// 式を今のブロックに加える。文式の中の文は先に読む
+    fn push(&mut self, node: &'a Node) {
+        self.stmt_exprs(node);
+        self.blocks[self.cur].nodes.push(node);
+    }
+
+    fn stmt_exprs(&mut self, node: &'a Node) {
+        if let NodeKind::StmtExpr(stmts) = &node.kind {
+            for stmt in stmts {
+                self.stmt(stmt);
+            }
+            return;
+        }
+        for child in node.children() {
+            self.stmt_exprs(child);
+        }
+    }
+
+    fn stmt(&mut self, node: &'a Node) {
+        use NodeKind::*;
+        self.stmts.push((node, self.cur));
+        match &node.kind {
+            Block(_) | Case(..) | DefaultCase | Label(_) | Null => (),
+            Fallthrough => self.annotated = true,
+            _ => {
+                self.has_stmt = true;
+                self.annotated = false;
+            }
+        }
+
+        match &node.kind {
+            Block(stmts) => {
+                for stmt in stmts {
+                    self.stmt(stmt);
+                }
+            }
+            Return => {
+                if let Some(lhs) = &node.lhs {
+                    self.push(lhs);
+                }
+                self.jump(None);
+            }
+            ExprStmt => {
+                self.push(node);
+                if is_noreturn_call(node.lhs.as_deref()) {
+                    self.jump(None);
+                }
+            }
+            If => {
+                self.push(node.cond.as_ref().unwrap());
+                let cond = self.cur;
+                let join = self.new_block();
+                let then = self.new_block();
+                self.blocks[cond].branch = Some((node.cond.as_ref().unwrap(), then));
+                self.enter(then);
+                self.stmt(node.then.as_ref().unwrap());
+                self.edge(self.cur, join);
+                match &node.els {
+                    Some(els) => {
+                        self.cur = cond;
+                        let els_block = self.new_block();
+                        self.enter(els_block);
+                        self.stmt(els);
+                        self.edge(self.cur, join);
+                    }
+                    None => self.edge(cond, join),
+                }
+                self.cur = join;
+            }
+            While | For => {
+                for init in node.init.iter().flatten() {
+                    self.stmt(init);
+                }
+                let cond = self.new_block();
+                let body = self.new_block();
+                let inc = self.new_block();
+                let after = self.new_block();
+                self.enter(cond);
+                if let Some(cond_node) = &node.cond {
+                    self.push(cond_node);
+                    self.blocks[self.cur].branch = Some((cond_node, body));
+                }
+                self.edge(self.cur, body);
+                if !is_const(node.cond.as_deref(), true) {
+                    self.edge(self.cur, after);
+                }
+                self.loop_body(node, body, inc, after);
+                self.enter(inc);
+                if let Some(inc_node) = &node.inc {
+                    self.push(inc_node);
+                }
+                self.edge(inc, cond);
+                self.cur = after;
+            }
+            Do => {
+                let body = self.new_block();
+                let cond = self.new_block();
+                let after = self.new_block();
+                self.enter(body);
+                self.loop_body(node, body, cond, after);
+                self.enter(cond);
+                self.push(node.cond.as_ref().unwrap());
+                self.blocks[self.cur].branch = Some((node.cond.as_ref().unwrap(), body));
+                if !is_const(node.cond.as_deref(), false) {
+                    self.edge(self.cur, body);
+                }
+                if !is_const(node.cond.as_deref(), true) {
+                    self.edge(self.cur, after);
+                }
+                self.cur = after;
+            }
+            Break => self.jump(self.breaks.last().copied()),
+            Continue => self.jump(self.continues.last().copied()),
+            Goto(ident) => {
+                self.gotos.push((self.cur, ident.name.clone()));
+                self.jump(None);
+            }
+            Label(ident) => {
+                let label = self.new_block();
+                self.enter(label);
+                self.labels.insert(ident.name.clone(), label);
+                self.stmt(node.lhs.as_ref().unwrap());
+            }
+            Switch(_) => {
+                self.push(node.cond.as_ref().unwrap());
+                let after = self.new_block();
+                self.switches.push(SwitchScope {
+                    dispatch: self.cur,
+                    has_default: false,
+                });
+                self.breaks.push(after);
+                let (has_stmt, annotated) = (self.has_stmt, self.annotated);
+                self.has_stmt = false;
+                // 最初の`case`より前の文には、どこからも来ない
+                self.jump(None);
+                self.stmt(node.then.as_ref().unwrap());
+                self.has_stmt = has_stmt;
+                self.annotated = annotated;
+                self.breaks.pop();
+                self.enter(after);
+                let switch = self.switches.pop().unwrap();
+                if !switch.has_default {
+                    self.edge(switch.dispatch, after);
+                }
+            }
+            Case(..) | DefaultCase => {
+                let label = self.new_block();
+                if let Some(switch) = self.switches.last_mut() {
+                    switch.has_default |= node.kind == DefaultCase;
+                    let dispatch = switch.dispatch;
+                    self.edge(dispatch, label);
+                }
+                if self.has_stmt && !self.annotated {
+                    self.fallthroughs.push((node, self.cur));
+                }
+                self.has_stmt = false;
+                self.enter(label);
+                self.stmt(node.lhs.as_ref().unwrap());
+            }
+            Null | Fallthrough => (),
+            _ => self.push(node),
+        }
+    }
+
+    fn loop_body(&mut self, node: &'a Node, body: usize, continue_to: usize, break_to: usize) {
+        self.cur = body;
+        self.breaks.push(break_to);
+        self.continues.push(continue_to);
+        self.stmt(node.then.as_ref().unwrap());
+        self.breaks.pop();
+        self.continues.pop();
+    }
+}
+
+/// 条件が省略されているか、常に`value`になる定数か
+fn is_const(cond: Option<&Node>, value: bool) -> bool {
+    match cond.map(|cond| &cond.kind) {
+        None => value,
+        Some(NodeKind::Num(num)) => (*num != 0) == value,
+        Some(NodeKind::Cast(_)) => is_const(cond.and_then(|cond| cond.lhs.as_deref()), value),
+        _ => false,
+    }
+}
+
+fn is_noreturn_call(node: Option<&Node>) -> bool {
+    match node.map(|node| &node.kind) {
+        Some(NodeKind::Func(func_prototype, _)) => {
+            NORETURN_FUNCS.contains(&func_prototype.ident.name.as_str())
+        }
+        Some(NodeKind::Cast(_)) => is_noreturn_call(node.and_then(|node| node.lhs.as_deref())),
+        _ => false,
+    }
+}
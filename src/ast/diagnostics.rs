@@ -0,0 +1,394 @@
+use super::error::{Error, Warn};
+use crate::preprocessor::{Pragma, PragmaKind};
+use std::collections::BTreeSet;
+use std::rc::Rc;
+
+/// 警告の種類
+/// `-W<name>`, `-Wno-<name>`, `-Werror=<name>`で個別に切り替える
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Debug)]
+pub enum Warning {
+    ExcessInitializers,
+    IntConversion,
+    IncompatiblePointerTypes,
+    CompareDistinctPointerTypes,
+    PointerIntegerCompare,
+    UnusedVariable,
+    UnusedParameter,
+    UnusedFunction,
+    UnusedLabel,
+    ReturnType,
+    UnreachableCode,
+    ImplicitFallthrough,
+    Uninitialized,
+    MaybeUninitialized,
+}
+
+/// どのオプションで有効になるか
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug)]
+pub enum WarnGroup {
+    /// 何も指定しなくても有効
+    Default,
+    /// `-Wall`で有効
+    All,
+    /// `-Wextra`で有効
+    Extra,
+}
+
+impl Warning {
+    pub const ALL: [Warning; 14] = [
+        Warning::ExcessInitializers,
+        Warning::IntConversion,
+        Warning::IncompatiblePointerTypes,
+        Warning::CompareDistinctPointerTypes,
+        Warning::PointerIntegerCompare,
+        Warning::UnusedVariable,
+        Warning::UnusedParameter,
+        Warning::UnusedFunction,
+        Warning::UnusedLabel,
+        Warning::ReturnType,
+        Warning::UnreachableCode,
+        Warning::ImplicitFallthrough,
+        Warning::Uninitialized,
+        Warning::MaybeUninitialized,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        use Warning::*;
+        match self {
+            ExcessInitializers => "excess-initializers",
+            IntConversion => "int-conversion",
+            IncompatiblePointerTypes => "incompatible-pointer-types",
+            CompareDistinctPointerTypes => "compare-distinct-pointer-types",
+            PointerIntegerCompare => "pointer-integer-compare",
+            UnusedVariable => "unused-variable",
+            UnusedParameter => "unused-parameter",
+            UnusedFunction => "unused-function",
+            UnusedLabel => "unused-label",
+            ReturnType => "return-type",
+            UnreachableCode => "unreachable-code",
+            ImplicitFallthrough => "implicit-fallthrough",
+            Uninitialized => "uninitialized",
+            MaybeUninitialized => "maybe-uninitialized",
+        }
+    }
+
+    pub fn group(&self) -> WarnGroup {
+        use Warning::*;
+        match self {
+            ExcessInitializers
+            | IntConversion
+            | IncompatiblePointerTypes
+            | CompareDistinctPointerTypes
+            | PointerIntegerCompare
+            | ReturnType => WarnGroup::Default,
+            UnusedVariable | UnusedFunction | UnusedLabel | UnreachableCode | Uninitialized
+            | MaybeUninitialized => WarnGroup::All,
+            UnusedParameter | ImplicitFallthrough => WarnGroup::Extra,
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Warning> {
+        Warning::ALL
+            .iter()
+            .find(|warning| warning.name() == name)
+            .copied()
+    }
+
+    /// オプションの名前が指す警告
+    /// `unused`は`unused-`で始まる警告をまとめて指す
+    pub fn from_option(name: &str) -> Vec<Warning> {
+        if name == "unused" {
+            return Warning::ALL
+                .iter()
+                .filter(|warning| warning.name().starts_with("unused-"))
+                .copied()
+                .collect();
+        }
+        Warning::from_name(name).into_iter().collect()
+    }
+}
+
+/// ある位置での警告の設定
+#[derive(PartialEq, Eq, Clone, Debug)]
+struct State {
+    enabled: BTreeSet<Warning>,
+    /// `-Werror=<name>`か`#pragma GCC diagnostic error`でエラーにする警告
+    errors: BTreeSet<Warning>,
+    /// `-Wno-error=<name>`か`#pragma GCC diagnostic warning`で`-Werror`から外す警告
+    no_errors: BTreeSet<Warning>,
+    werror: bool,
+}
+
+impl State {
+    fn new() -> Self {
+        Self {
+            enabled: Warning::ALL
+                .iter()
+                .filter(|warning| warning.group() == WarnGroup::Default)
+                .copied()
+                .collect(),
+            errors: BTreeSet::new(),
+            no_errors: BTreeSet::new(),
+            werror: false,
+        }
+    }
+
+    fn enable_group(&mut self, group: WarnGroup) {
+        self.enabled.extend(
+            Warning::ALL
+                .iter()
+                .filter(|warning| warning.group() == group),
+        );
+    }
+
+    fn is_error(&self, warning: Warning) -> bool {
+        self.errors.contains(&warning) || (self.werror && !self.no_errors.contains(&warning))
+    }
+}
+
+/// 警告をどう扱うか
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Severity {
+    Ignored,
+    Warning,
+    Error,
+}
+
+/// コマンドラインと`#pragma GCC diagnostic`による警告の設定
+#[derive(Clone, Debug)]
+pub struct Diagnostics {
+    state: State,
+    /// `-w`ですべての警告を出さない
+    suppress_all: bool,
+    pragmas: Vec<Pragma>,
+}
+
+impl Default for Diagnostics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Diagnostics {
+    pub fn new() -> Self {
+        Self {
+            state: State::new(),
+            suppress_all: false,
+            pragmas: Vec::new(),
+        }
+    }
+
+    /// `-W`で始まるコマンドラインオプションを読む
+    /// 知らない警告を有効にしようとしたらエラーにする。`-Wno-`は知らなくても無視する
+    pub fn parse_option(&mut self, option: &str) -> Result<(), String> {
+        let state = &mut self.state;
+        let find = |name: &str| match Warning::from_option(name) {
+            warnings if warnings.is_empty() => Err(format!("unknown warning option '{}'", option)),
+            warnings => Ok(warnings),
+        };
+        match option {
+            "-w" => self.suppress_all = true,
+            "-Wall" => state.enable_group(WarnGroup::All),
+            "-Wextra" => state.enable_group(WarnGroup::Extra),
+            "-Werror" => state.werror = true,
+            "-Wno-error" => state.werror = false,
+            _ => {
+                if let Some(name) = option.strip_prefix("-Werror=") {
+                    for warning in find(name)? {
+                        state.enabled.insert(warning);
+                        state.errors.insert(warning);
+                        state.no_errors.remove(&warning);
+                    }
+                } else if let Some(name) = option.strip_prefix("-Wno-error=") {
+                    for warning in Warning::from_option(name) {
+                        state.errors.remove(&warning);
+                        state.no_errors.insert(warning);
+                    }
+                } else if let Some(name) = option.strip_prefix("-Wno-") {
+                    for warning in Warning::from_option(name) {
+                        state.enabled.remove(&warning);
+                    }
+                } else if let Some(name) = option.strip_prefix("-W") {
+                    state.enabled.extend(find(name)?);
+                } else {
+                    return Err(format!("unknown warning option '{}'", option));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn add_pragmas(&mut self, pragmas: &[Pragma]) {
+        self.pragmas.extend_from_slice(pragmas);
+    }
+
+    /// `filepath`の`bytes`の位置で`warning`をどう扱うか
+    /// 同じファイルでそれより前に書かれた`#pragma`を順に当てはめて決める
+    pub fn severity(&self, warning: Warning, filepath: &Rc<String>, bytes: usize) -> Severity {
+        if self.suppress_all {
+            return Severity::Ignored;
+        }
+        let mut state = self.state.clone();
+        let mut stack = Vec::new();
+        for pragma in self
+            .pragmas
+            .iter()
+            .filter(|pragma| &pragma.filepath == filepath && pragma.bytes < bytes)
+        {
+            let target = |name: &String| Warning::from_option(name).contains(&warning);
+            match &pragma.kind {
+                PragmaKind::Push => stack.push(state.clone()),
+                PragmaKind::Pop => {
+                    // 対応するpushがなければコマンドラインの設定に戻す
+                    state = stack.pop().unwrap_or_else(|| self.state.clone());
+                }
+                PragmaKind::Ignored(name) if target(name) => {
+                    state.enabled.remove(&warning);
+                }
+                PragmaKind::Warning(name) if target(name) => {
+                    state.enabled.insert(warning);
+                    state.errors.remove(&warning);
+                    state.no_errors.insert(warning);
+                }
+                PragmaKind::Error(name) if target(name) => {
+                    state.enabled.insert(warning);
+                    state.errors.insert(warning);
+                }
+                _ => (),
+            }
+        }
+        if !state.enabled.contains(&warning) {
+            Severity::Ignored
+        } else if state.is_error(warning) {
+            Severity::Error
+        } else {
+            Severity::Warning
+        }
+    }
+
+    /// 設定に従って、警告を報告する形にして返す
+    /// 出さない警告は`None`、エラーとして扱う警告はエラーにする
+    pub fn report(&self, warn: Warn) -> Option<Error> {
+        match self.severity(warn.warning(), warn.filepath(), warn.pos().bytes) {
+            Severity::Ignored => None,
+            Severity::Warning => Some(warn.into_warning()),
+            Severity::Error => Some(warn.into_error()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pragma(bytes: usize, kind: PragmaKind) -> Pragma {
+        Pragma {
+            filepath: Rc::new("test.c".to_string()),
+            bytes,
+            kind,
+        }
+    }
+
+    #[test]
+    fn test_options() {
+        let filepath = Rc::new("test.c".to_string());
+        let severity = |options: &[&str], warning| {
+            let mut diag = Diagnostics::new();
+            for option in options {
+                diag.parse_option(option).unwrap();
+            }
+            diag.severity(warning, &filepath, 0)
+        };
+        assert_eq!(Severity::Warning, severity(&[], Warning::IntConversion));
+        assert_eq!(
+            Severity::Ignored,
+            severity(&["-Wno-int-conversion"], Warning::IntConversion)
+        );
+        assert_eq!(Severity::Ignored, severity(&["-w"], Warning::IntConversion));
+        assert_eq!(
+            Severity::Error,
+            severity(&["-Werror"], Warning::IntConversion)
+        );
+        assert_eq!(
+            Severity::Warning,
+            severity(
+                &["-Werror", "-Wno-error=int-conversion"],
+                Warning::IntConversion
+            )
+        );
+        assert_eq!(
+            Severity::Error,
+            severity(&["-Werror=int-conversion"], Warning::IntConversion)
+        );
+        assert_eq!(
+            Severity::Warning,
+            severity(
+                &["-Werror=int-conversion"],
+                Warning::IncompatiblePointerTypes
+            )
+        );
+        assert_eq!(
+            Severity::Error,
+            severity(
+                &["-Wno-int-conversion", "-Werror=int-conversion"],
+                Warning::IntConversion
+            )
+        );
+
+        assert_eq!(Severity::Ignored, severity(&[], Warning::UnusedVariable));
+        assert_eq!(
+            Severity::Warning,
+            severity(&["-Wall"], Warning::UnusedVariable)
+        );
+        assert_eq!(
+            Severity::Ignored,
+            severity(&["-Wall"], Warning::UnusedParameter)
+        );
+        assert_eq!(
+            Severity::Warning,
+            severity(&["-Wall", "-Wextra"], Warning::UnusedParameter)
+        );
+        assert_eq!(
+            Severity::Ignored,
+            severity(&["-Wall", "-Wno-unused"], Warning::UnusedLabel)
+        );
+
+        let mut diag = Diagnostics::new();
+        assert!(diag.parse_option("-Wunknown-warning").is_err());
+        assert!(diag.parse_option("-Wno-unknown-warning").is_ok());
+    }
+
+    #[test]
+    fn test_pragmas() {
+        let filepath = Rc::new("test.c".to_string());
+        let mut diag = Diagnostics::new();
+        diag.parse_option("-Werror").unwrap();
+        diag.add_pragmas(&[
+            pragma(10, PragmaKind::Push),
+            pragma(20, PragmaKind::Ignored("int-conversion".to_string())),
+            pragma(
+                30,
+                PragmaKind::Warning("incompatible-pointer-types".to_string()),
+            ),
+            pragma(40, PragmaKind::Pop),
+        ]);
+        let severity = |warning, bytes| diag.severity(warning, &filepath, bytes);
+        assert_eq!(Severity::Error, severity(Warning::IntConversion, 15));
+        assert_eq!(Severity::Ignored, severity(Warning::IntConversion, 25));
+        assert_eq!(Severity::Ignored, severity(Warning::IntConversion, 35));
+        assert_eq!(
+            Severity::Warning,
+            severity(Warning::IncompatiblePointerTypes, 35)
+        );
+        assert_eq!(Severity::Error, severity(Warning::IntConversion, 45));
+        assert_eq!(
+            Severity::Error,
+            severity(Warning::IncompatiblePointerTypes, 45)
+        );
+        // 別のファイルの`#pragma`は当てはめない
+        assert_eq!(
+            Severity::Error,
+            diag.severity(Warning::IntConversion, &Rc::new("other.c".to_string()), 25)
+        );
+    }
+}
@@ -1,8 +1,9 @@
 use self::ErrorKind::*;
+use super::diagnostics::Warning;
 use super::{Ident, Lvar};
 use crate::base_types::TypeKind;
+use crate::render::{Diagnostic, FixIt, Level, Location, Note};
 use crate::token::{Token, TokenKind, TokenPos};
-use crate::util;
 use std::fmt;
 use std::rc::Rc;
 
@@ -22,7 +23,32 @@ pub enum ErrorKind {
     InvalidAssignment(TypeKind, TypeKind),
     InvalidInitialization(Rc<Lvar>, String),
     InvalidStmtExpr,
+    IncompleteType(String),
+    InvalidFlexibleArray,
+    InvalidAlignment(i64),
+    InvalidVla(String),
+    StaticAssertion(String),
+    InvalidGenericSelection(String),
+    InvalidConstantExpression(String),
+    InvalidInitializer(String),
+    InvalidFunctionCall(String),
+    ConflictingTypes(Ident),
+    Redefinition(Ident),
     StrayCase,
+    /// `goto`の飛び先のラベルが関数の中にない
+    UndefinedLabel(Ident),
+    DuplicateLabel(Ident),
+    /// 同じ`switch`の中で値が重なる`case`。重なった値を持つ
+    DuplicateCase(i64),
+    MultipleDefault,
+    EmptyCaseRange,
+    TooManyErrors,
+    /// 宣言に失敗した変数を使った。先に報告したエラーの続きなので表示しない
+    InvalidDeclaration(Ident),
+    /// `-Werror`などでエラーにした警告
+    WarningAsError(Warning, String),
+    /// 警告。エラーと同じ列に、出した順に並べる
+    Warned(Warning, String),
     EOF(TokenKind),
     Todo,
     Unimplemented,
@@ -31,10 +57,14 @@ pub enum ErrorKind {
 #[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Debug)]
 pub struct Error {
     filepath: Rc<String>,
-    kind: ErrorKind,
+    /// `Result<_, Error>`を小さく保つために箱に入れる
+    kind: Box<ErrorKind>,
     pos: TokenPos,
     input: Rc<String>,
     msg: Option<String>,
+    /// 前の宣言の場所など
+    notes: Vec<Note>,
+    fixits: Vec<FixIt>,
 }
 
 impl Error {
@@ -46,13 +76,15 @@ impl Error {
     ) -> Error {
         Error {
             filepath,
-            kind: UnexpectedToken {
+            kind: Box::new(UnexpectedToken {
                 expected,
                 actual: token.kind.clone(),
-            },
+            }),
             pos: token.pos,
             input,
             msg: None,
+            notes: Vec::new(),
+            fixits: Vec::new(),
         }
     }
 
@@ -65,10 +97,12 @@ impl Error {
     ) -> Error {
         Error {
             filepath,
-            kind: EOF(expected),
+            kind: Box::new(EOF(expected)),
             pos,
             input,
             msg,
+            notes: Vec::new(),
+            fixits: Vec::new(),
         }
     }
 
@@ -79,12 +113,15 @@ impl Error {
         pos: TokenPos,
         msg: Option<String>,
     ) -> Error {
+        let fixits = suggestion_fixit(pos, &ident, &msg);
         Error {
             filepath,
-            kind: UndefinedVariable(ident),
+            kind: Box::new(UndefinedVariable(ident)),
             pos,
             input,
             msg,
+            notes: Vec::new(),
+            fixits,
         }
     }
 
@@ -95,12 +132,15 @@ impl Error {
         pos: TokenPos,
         msg: Option<String>,
     ) -> Error {
+        let fixits = suggestion_fixit(pos, &ident, &msg);
         Error {
             filepath,
-            kind: UndefinedFunction(ident),
+            kind: Box::new(UndefinedFunction(ident)),
             pos,
             input,
             msg,
+            notes: Vec::new(),
+            fixits,
         }
     }
 
@@ -111,12 +151,15 @@ impl Error {
         ident: Ident,
         msg: Option<String>,
     ) -> Error {
+        let fixits = suggestion_fixit(pos, &ident, &msg);
         Error {
             filepath,
-            kind: UndefinedMember(ident),
+            kind: Box::new(UndefinedMember(ident)),
             pos,
             input,
             msg,
+            notes: Vec::new(),
+            fixits,
         }
     }
 
@@ -127,12 +170,15 @@ impl Error {
         ident: Ident,
         msg: Option<String>,
     ) -> Error {
+        let fixits = suggestion_fixit(pos, &ident, &msg);
         Error {
             filepath,
-            kind: UndefinedTag(ident),
+            kind: Box::new(UndefinedTag(ident)),
             pos,
             input,
             msg,
+            notes: Vec::new(),
+            fixits,
         }
     }
 
@@ -145,10 +191,12 @@ impl Error {
     ) -> Error {
         Error {
             filepath,
-            kind: ReDeclare(ident),
+            kind: Box::new(ReDeclare(ident)),
             pos,
             input,
             msg,
+            notes: Vec::new(),
+            fixits: Vec::new(),
         }
     }
 
@@ -161,10 +209,12 @@ impl Error {
     ) -> Error {
         Error {
             filepath,
-            kind: InvalidVariableDereference(lvar, actual_deref_count),
+            kind: Box::new(InvalidVariableDereference(lvar, actual_deref_count)),
             pos,
             input,
             msg: None,
+            notes: Vec::new(),
+            fixits: Vec::new(),
         }
     }
 
@@ -176,10 +226,12 @@ impl Error {
     ) -> Error {
         Error {
             filepath,
-            kind: InvalidValueDereference(type_name),
+            kind: Box::new(InvalidValueDereference(type_name)),
             pos,
             input,
             msg: None,
+            notes: Vec::new(),
+            fixits: Vec::new(),
         }
     }
 
@@ -192,10 +244,12 @@ impl Error {
     ) -> Error {
         Error {
             filepath,
-            kind: InvalidAssignment(lhs_type, rhs_type),
+            kind: Box::new(InvalidAssignment(lhs_type, rhs_type)),
             pos,
             input,
             msg: None,
+            notes: Vec::new(),
+            fixits: Vec::new(),
         }
     }
 
@@ -208,57 +262,385 @@ impl Error {
     ) -> Error {
         Error {
             filepath,
-            kind: InvalidInitialization(lhs, rhs),
+            kind: Box::new(InvalidInitialization(lhs, rhs)),
             pos,
             input,
             msg: None,
+            notes: Vec::new(),
+            fixits: Vec::new(),
         }
     }
 
     pub fn invalid_stmt_expr(filepath: Rc<String>, input: Rc<String>, pos: TokenPos) -> Error {
         Error {
             filepath,
-            kind: InvalidStmtExpr,
+            kind: Box::new(InvalidStmtExpr),
             pos,
             input,
             msg: None,
+            notes: Vec::new(),
+            fixits: Vec::new(),
+        }
+    }
+
+    /// `msg` describes how the incomplete type was used,
+    /// e.g. "invalid application of sizeof to incomplete type"
+    /// `suggestion` is a similar tag name when the tag may be misspelled
+    pub fn incomplete_type(
+        filepath: Rc<String>,
+        input: Rc<String>,
+        pos: TokenPos,
+        msg: impl Into<String>,
+        suggestion: Option<String>,
+    ) -> Error {
+        Error {
+            filepath,
+            kind: Box::new(IncompleteType(msg.into())),
+            pos,
+            input,
+            msg: suggestion,
+            notes: Vec::new(),
+            fixits: Vec::new(),
+        }
+    }
+
+    pub fn invalid_flexible_array(filepath: Rc<String>, input: Rc<String>, pos: TokenPos) -> Error {
+        Error {
+            filepath,
+            kind: Box::new(InvalidFlexibleArray),
+            pos,
+            input,
+            msg: None,
+            notes: Vec::new(),
+            fixits: Vec::new(),
+        }
+    }
+
+    pub fn invalid_alignment(
+        filepath: Rc<String>,
+        input: Rc<String>,
+        pos: TokenPos,
+        align: i64,
+    ) -> Error {
+        Error {
+            filepath,
+            kind: Box::new(InvalidAlignment(align)),
+            pos,
+            input,
+            msg: None,
+            notes: Vec::new(),
+            fixits: Vec::new(),
+        }
+    }
+
+    pub fn invalid_vla(
+        filepath: Rc<String>,
+        input: Rc<String>,
+        pos: TokenPos,
+        msg: impl Into<String>,
+    ) -> Error {
+        Error {
+            filepath,
+            kind: Box::new(InvalidVla(msg.into())),
+            pos,
+            input,
+            msg: None,
+            notes: Vec::new(),
+            fixits: Vec::new(),
+        }
+    }
+
+    pub fn static_assertion(
+        filepath: Rc<String>,
+        input: Rc<String>,
+        pos: TokenPos,
+        msg: impl Into<String>,
+    ) -> Error {
+        Error {
+            filepath,
+            kind: Box::new(StaticAssertion(msg.into())),
+            pos,
+            input,
+            msg: None,
+            notes: Vec::new(),
+            fixits: Vec::new(),
+        }
+    }
+
+    pub fn invalid_generic_selection(
+        filepath: Rc<String>,
+        input: Rc<String>,
+        pos: TokenPos,
+        msg: impl Into<String>,
+    ) -> Error {
+        Error {
+            filepath,
+            kind: Box::new(InvalidGenericSelection(msg.into())),
+            pos,
+            input,
+            msg: None,
+            notes: Vec::new(),
+            fixits: Vec::new(),
+        }
+    }
+
+    pub fn invalid_constant_expression(
+        filepath: Rc<String>,
+        input: Rc<String>,
+        pos: TokenPos,
+        msg: impl Into<String>,
+    ) -> Error {
+        Error {
+            filepath,
+            kind: Box::new(InvalidConstantExpression(msg.into())),
+            pos,
+            input,
+            msg: None,
+            notes: Vec::new(),
+            fixits: Vec::new(),
+        }
+    }
+
+    pub fn invalid_initializer(
+        filepath: Rc<String>,
+        input: Rc<String>,
+        pos: TokenPos,
+        msg: impl Into<String>,
+    ) -> Error {
+        Error {
+            filepath,
+            kind: Box::new(InvalidInitializer(msg.into())),
+            pos,
+            input,
+            msg: None,
+            notes: Vec::new(),
+            fixits: Vec::new(),
+        }
+    }
+
+    pub fn invalid_function_call(
+        filepath: Rc<String>,
+        input: Rc<String>,
+        pos: TokenPos,
+        msg: impl Into<String>,
+    ) -> Error {
+        Error {
+            filepath,
+            kind: Box::new(InvalidFunctionCall(msg.into())),
+            pos,
+            input,
+            msg: None,
+            notes: Vec::new(),
+            fixits: Vec::new(),
+        }
+    }
+
+    pub fn conflicting_types(
+        filepath: Rc<String>,
+        input: Rc<String>,
+        pos: TokenPos,
+        ident: Ident,
+    ) -> Error {
+        Error {
+            filepath,
+            kind: Box::new(ConflictingTypes(ident)),
+            pos,
+            input,
+            msg: None,
+            notes: Vec::new(),
+            fixits: Vec::new(),
+        }
+    }
+
+    pub fn redefinition(
+        filepath: Rc<String>,
+        input: Rc<String>,
+        pos: TokenPos,
+        ident: Ident,
+    ) -> Error {
+        Error {
+            filepath,
+            kind: Box::new(Redefinition(ident)),
+            pos,
+            input,
+            msg: None,
+            notes: Vec::new(),
+            fixits: Vec::new(),
         }
     }
 
     pub fn stray_case(filepath: Rc<String>, input: Rc<String>, pos: TokenPos) -> Error {
         Error {
             filepath,
-            kind: StrayCase,
+            kind: Box::new(StrayCase),
             pos,
             input,
             msg: None,
+            notes: Vec::new(),
+            fixits: Vec::new(),
         }
     }
 
+    pub fn undefined_label(
+        filepath: Rc<String>,
+        input: Rc<String>,
+        pos: TokenPos,
+        ident: Ident,
+    ) -> Error {
+        Error {
+            filepath,
+            kind: Box::new(UndefinedLabel(ident)),
+            pos,
+            input,
+            msg: None,
+            notes: Vec::new(),
+            fixits: Vec::new(),
+        }
+    }
+
+    pub fn duplicate_label(
+        filepath: Rc<String>,
+        input: Rc<String>,
+        pos: TokenPos,
+        ident: Ident,
+    ) -> Error {
+        Error {
+            filepath,
+            kind: Box::new(DuplicateLabel(ident)),
+            pos,
+            input,
+            msg: None,
+            notes: Vec::new(),
+            fixits: Vec::new(),
+        }
+    }
+
+    pub fn duplicate_case(
+        filepath: Rc<String>,
+        input: Rc<String>,
+        pos: TokenPos,
+        val: i64,
+    ) -> Error {
+        Error {
+            filepath,
+            kind: Box::new(DuplicateCase(val)),
+            pos,
+            input,
+            msg: None,
+            notes: Vec::new(),
+            fixits: Vec::new(),
+        }
+    }
+
+    pub fn multiple_default(filepath: Rc<String>, input: Rc<String>, pos: TokenPos) -> Error {
+        Error {
+            filepath,
+            kind: Box::new(MultipleDefault),
+            pos,
+            input,
+            msg: None,
+            notes: Vec::new(),
+            fixits: Vec::new(),
+        }
+    }
+
+    /// `case 5 ... 1:`のように下限が上限より大きい
+    pub fn empty_case_range(filepath: Rc<String>, input: Rc<String>, pos: TokenPos) -> Error {
+        Error {
+            filepath,
+            kind: Box::new(EmptyCaseRange),
+            pos,
+            input,
+            msg: None,
+            notes: Vec::new(),
+            fixits: Vec::new(),
+        }
+    }
+
+    /// エラーの数が上限に達したので解析をやめる
+    pub fn too_many_errors(filepath: Rc<String>, input: Rc<String>, pos: TokenPos) -> Error {
+        Error {
+            filepath,
+            kind: Box::new(TooManyErrors),
+            pos,
+            input,
+            msg: None,
+            notes: Vec::new(),
+            fixits: Vec::new(),
+        }
+    }
+
+    /// 宣言に失敗した変数を使った
+    pub fn invalid_declaration(
+        filepath: Rc<String>,
+        input: Rc<String>,
+        ident: Ident,
+        pos: TokenPos,
+    ) -> Error {
+        Error {
+            filepath,
+            kind: Box::new(InvalidDeclaration(ident)),
+            pos,
+            input,
+            msg: None,
+            notes: Vec::new(),
+            fixits: Vec::new(),
+        }
+    }
+
+    pub fn with_note(mut self, note: Note) -> Error {
+        self.notes.push(note);
+        self
+    }
+
+    pub fn with_fixit(mut self, fixit: FixIt) -> Error {
+        self.fixits.push(fixit);
+        self
+    }
+
+    /// これ以上解析を続けられないエラーかどうか
+    pub fn is_fatal(&self) -> bool {
+        *self.kind == TooManyErrors
+    }
+
+    /// エラーではなく警告かどうか
+    pub fn is_warning(&self) -> bool {
+        matches!(*self.kind, Warned(_, _))
+    }
+
+    /// 先に報告したエラーから続くだけで、報告しなくてよいエラーかどうか
+    pub fn is_silent(&self) -> bool {
+        matches!(*self.kind, InvalidDeclaration(_))
+    }
+
     pub fn todo(filepath: Rc<String>, input: Rc<String>, pos: TokenPos) -> Error {
         Error {
             filepath,
-            kind: Todo,
+            kind: Box::new(Todo),
             pos,
             input,
             msg: None,
+            notes: Vec::new(),
+            fixits: Vec::new(),
         }
     }
 
     pub fn unimplemented(filepath: Rc<String>, input: Rc<String>, pos: TokenPos) -> Error {
         Error {
             filepath,
-            kind: Unimplemented,
+            kind: Box::new(Unimplemented),
             pos,
             input,
             msg: None,
+            notes: Vec::new(),
+            fixits: Vec::new(),
         }
     }
 }
 
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match &self.kind {
+        match &*self.kind {
             UnexpectedToken { expected, actual } => {
                 unexpected_token_err_format(expected.clone(), actual.clone(), &self, f)
             }
@@ -283,7 +665,60 @@ impl fmt::Display for Error {
                 invalid_initialization_err_format(&self, lhs, rhs, f)
             }
             InvalidStmtExpr => invalid_stmt_expr_err_format(&self, f),
+            IncompleteType(msg) => err_format(&self, with_suggestion(self, msg.clone()), f),
+            InvalidFlexibleArray => err_format(
+                &self,
+                "flexible array member must be the last member of a struct with other named members",
+                f,
+            ),
+            InvalidAlignment(align) => err_format(
+                &self,
+                format!("requested alignment {} is not a positive power of 2", align),
+                f,
+            ),
+            InvalidVla(msg) => err_format(&self, msg, f),
+            StaticAssertion(msg) if msg.is_empty() => {
+                err_format(&self, "static assertion failed", f)
+            }
+            StaticAssertion(msg) => {
+                err_format(&self, format!("static assertion failed: {}", msg), f)
+            }
+            InvalidGenericSelection(msg) => err_format(&self, msg, f),
+            InvalidConstantExpression(msg) => err_format(&self, msg, f),
+            InvalidInitializer(msg) => err_format(&self, msg, f),
+            InvalidFunctionCall(msg) => err_format(&self, msg, f),
+            ConflictingTypes(ident) => {
+                err_format(&self, format!("conflicting types for {}", ident.name), f)
+            }
+            Redefinition(ident) => err_format(&self, format!("redefinition of {}", ident.name), f),
             StrayCase => err_format(&self, "stray case", f),
+            UndefinedLabel(ident) => {
+                err_format(&self, format!("use of undeclared label '{}'", ident.name), f)
+            }
+            DuplicateLabel(ident) => {
+                err_format(&self, format!("redefinition of label '{}'", ident.name), f)
+            }
+            DuplicateCase(val) => err_format(&self, format!("duplicate case value '{}'", val), f),
+            MultipleDefault => err_format(&self, "multiple default labels in one switch", f),
+            EmptyCaseRange => err_format(&self, "empty case range specified", f),
+            TooManyErrors => err_format(&self, "too many errors emitted, stopping now", f),
+            InvalidDeclaration(ident) => err_format(
+                self,
+                format!("variable {} has an invalid declaration", ident.name),
+                f,
+            ),
+            WarningAsError(warning, msg) => err_format(
+                &self,
+                format!("{} [-Werror={}]", msg, warning.name()),
+                f,
+            ),
+            Warned(warning, msg) => {
+                let msg = format!("{} [-W{}]", msg, warning.name());
+                let loc = Location::new(self.filepath.clone(), self.input.clone(), self.pos.bytes);
+                let mut diagnostic = Diagnostic::new(Level::Warning, &loc, &msg);
+                diagnostic.fixits = &self.fixits;
+                write!(f, "{}", diagnostic)
+            }
             Todo => err_format(&self, "todo", f),
             Unimplemented => err_format(&self, "not yet implemented", f),
         }
@@ -291,8 +726,12 @@ impl fmt::Display for Error {
 }
 
 fn err_format(err: &Error, msg: impl Into<String>, f: &mut fmt::Formatter) -> fmt::Result {
-    let err = util::err_format(err.input.clone(), err.filepath.clone(), err.pos, msg)?;
-    writeln!(f, "{}", err)
+    let loc = Location::new(err.filepath.clone(), err.input.clone(), err.pos.bytes);
+    let msg = msg.into();
+    let mut diagnostic = Diagnostic::new(Level::Error, &loc, &msg);
+    diagnostic.notes = &err.notes;
+    diagnostic.fixits = &err.fixits;
+    write!(f, "{}", diagnostic)
 }
 
 fn unexpected_token_err_format(
@@ -313,12 +752,32 @@ fn unexpected_token_err_format(
     err_format(err, msg, f)
 }
 
+/// 似た名前への置き換えを提案する
+fn suggestion_fixit(pos: TokenPos, ident: &Ident, suggestion: &Option<String>) -> Vec<FixIt> {
+    suggestion
+        .iter()
+        .map(|name| FixIt::replace(pos.bytes, pos.bytes + ident.name.len(), name.clone()))
+        .collect()
+}
+
+/// 似た名前が見つかっていれば、候補としてメッセージに加える
+fn with_suggestion(err: &Error, msg: String) -> String {
+    match &err.msg {
+        Some(name) => format!("{}; did you mean '{}'?", msg, name),
+        None => msg,
+    }
+}
+
 fn undefined_variable_err_format(
     err: &Error,
     ident: &Ident,
     f: &mut fmt::Formatter,
 ) -> fmt::Result {
-    err_format(err, format!("variable {} is not defined", ident.name), f)
+    err_format(
+        err,
+        with_suggestion(err, format!("variable {} is not defined", ident.name)),
+        f,
+    )
 }
 
 fn undefined_function_err_format(
@@ -326,13 +785,20 @@ fn undefined_function_err_format(
     ident: &Ident,
     f: &mut fmt::Formatter,
 ) -> fmt::Result {
-    err_format(err, format!("function {} is not defined", ident.name), f)
+    err_format(
+        err,
+        with_suggestion(
+            err,
+            format!("implicit declaration of function {}", ident.name),
+        ),
+        f,
+    )
 }
 
 fn undefined_member_err_format(err: &Error, ident: &Ident, f: &mut fmt::Formatter) -> fmt::Result {
     err_format(
         err,
-        format!("struct member {} is not defined", ident.name),
+        with_suggestion(err, format!("struct member {} is not defined", ident.name)),
         f,
     )
 }
@@ -340,7 +806,7 @@ fn undefined_member_err_format(err: &Error, ident: &Ident, f: &mut fmt::Formatte
 fn undefined_tag_err_format(err: &Error, ident: &Ident, f: &mut fmt::Formatter) -> fmt::Result {
     err_format(
         err,
-        format!("the tag named {} is not defined", ident.name),
+        with_suggestion(err, format!("the tag named {} is not defined", ident.name)),
         f,
     )
 }
@@ -410,37 +876,290 @@ fn invalid_stmt_expr_err_format(err: &Error, f: &mut fmt::Formatter) -> fmt::Res
 #[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Debug)]
 pub enum WarnKind {
     ExcessInitializer,
+    IntConversion(String),
+    IncompatiblePointerTypes(String),
+    CompareDistinctPointerTypes(String),
+    PointerIntegerCompare(String),
+    UnusedVariable(String),
+    UnusedStaticVariable(String),
+    UnusedParameter(String),
+    UnusedFunction(String),
+    UnusedLabel(String),
+    ReturnType(String),
+    UnreachableCode,
+    ImplicitFallthrough,
+    Uninitialized(String),
+    MaybeUninitialized(String),
 }
 
+impl WarnKind {
+    /// どの`-W`オプションで切り替える警告か
+    pub fn warning(&self) -> Warning {
+        use WarnKind::*;
+        match self {
+            ExcessInitializer => Warning::ExcessInitializers,
+            IntConversion(_) => Warning::IntConversion,
+            IncompatiblePointerTypes(_) => Warning::IncompatiblePointerTypes,
+            CompareDistinctPointerTypes(_) => Warning::CompareDistinctPointerTypes,
+            PointerIntegerCompare(_) => Warning::PointerIntegerCompare,
+            UnusedVariable(_) | UnusedStaticVariable(_) => Warning::UnusedVariable,
+            UnusedParameter(_) => Warning::UnusedParameter,
+            UnusedFunction(_) => Warning::UnusedFunction,
+            UnusedLabel(_) => Warning::UnusedLabel,
+            ReturnType(_) => Warning::ReturnType,
+            UnreachableCode => Warning::UnreachableCode,
+            ImplicitFallthrough => Warning::ImplicitFallthrough,
+            Uninitialized(_) => Warning::Uninitialized,
+            MaybeUninitialized(_) => Warning::MaybeUninitialized,
+        }
+    }
+
+    fn msg(&self) -> String {
+        use WarnKind::*;
+        match self {
+            ExcessInitializer => "excess elements initializer".to_string(),
+            IntConversion(msg)
+            | IncompatiblePointerTypes(msg)
+            | CompareDistinctPointerTypes(msg)
+            | PointerIntegerCompare(msg) => msg.clone(),
+            UnusedVariable(name) => format!("unused variable '{}'", name),
+            UnusedParameter(name) => format!("unused parameter '{}'", name),
+            UnusedStaticVariable(name) | UnusedFunction(name) => {
+                format!("'{}' defined but not used", name)
+            }
+            UnusedLabel(name) => format!("label '{}' defined but not used", name),
+            ReturnType(name) => format!(
+                "non-void function '{}' does not return a value in all control paths",
+                name
+            ),
+            UnreachableCode => "code will never be executed".to_string(),
+            ImplicitFallthrough => "unannotated fall-through between switch labels".to_string(),
+            Uninitialized(name) => format!("'{}' is used uninitialized", name),
+            MaybeUninitialized(name) => format!("'{}' may be used uninitialized", name),
+        }
+    }
+}
+
+/// 警告。表示するかどうかは`Diagnostics::report`が決める
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Debug)]
 pub struct Warn {
     filepath: Rc<String>,
     kind: WarnKind,
     pos: TokenPos,
     input: Rc<String>,
+    fixits: Vec<FixIt>,
 }
 
 impl Warn {
-    pub fn excess_initializer(filepath: Rc<String>, input: Rc<String>, pos: TokenPos) {
-        let warn = Warn {
+    pub fn excess_initializer(filepath: Rc<String>, input: Rc<String>, pos: TokenPos) -> Warn {
+        Warn::new(filepath, input, pos, WarnKind::ExcessInitializer)
+    }
+
+    /// 整数とポインタをキャストなしで変換した
+    pub fn int_conversion(
+        filepath: Rc<String>,
+        input: Rc<String>,
+        pos: TokenPos,
+        msg: impl Into<String>,
+    ) -> Warn {
+        Warn::new(filepath, input, pos, WarnKind::IntConversion(msg.into()))
+    }
+
+    /// 互換性のないポインタ型に変換した
+    pub fn incompatible_pointer_types(
+        filepath: Rc<String>,
+        input: Rc<String>,
+        pos: TokenPos,
+        msg: impl Into<String>,
+    ) -> Warn {
+        Warn::new(
             filepath,
-            kind: WarnKind::ExcessInitializer,
+            input,
             pos,
+            WarnKind::IncompatiblePointerTypes(msg.into()),
+        )
+    }
+
+    /// 型の違うポインタどうしを比較した
+    pub fn compare_distinct_pointer_types(
+        filepath: Rc<String>,
+        input: Rc<String>,
+        pos: TokenPos,
+        msg: impl Into<String>,
+    ) -> Warn {
+        Warn::new(
+            filepath,
             input,
-        };
-        eprintln!("{}", warn);
+            pos,
+            WarnKind::CompareDistinctPointerTypes(msg.into()),
+        )
     }
-}
 
-impl fmt::Display for Warn {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        use WarnKind::*;
-        match &self.kind {
-            ExcessInitializer => warn_format(&self, "excess elements initializer", f),
+    /// ポインタと整数を比較した
+    pub fn pointer_integer_compare(
+        filepath: Rc<String>,
+        input: Rc<String>,
+        pos: TokenPos,
+        msg: impl Into<String>,
+    ) -> Warn {
+        Warn::new(
+            filepath,
+            input,
+            pos,
+            WarnKind::PointerIntegerCompare(msg.into()),
+        )
+    }
+
+    /// 宣言した変数を使っていない
+    pub fn unused_variable(
+        filepath: Rc<String>,
+        input: Rc<String>,
+        pos: TokenPos,
+        name: impl Into<String>,
+    ) -> Warn {
+        Warn::new(filepath, input, pos, WarnKind::UnusedVariable(name.into()))
+    }
+
+    /// 定義したstatic変数を使っていない
+    pub fn unused_static_variable(
+        filepath: Rc<String>,
+        input: Rc<String>,
+        pos: TokenPos,
+        name: impl Into<String>,
+    ) -> Warn {
+        Warn::new(
+            filepath,
+            input,
+            pos,
+            WarnKind::UnusedStaticVariable(name.into()),
+        )
+    }
+
+    /// 関数の引数を使っていない
+    pub fn unused_parameter(
+        filepath: Rc<String>,
+        input: Rc<String>,
+        pos: TokenPos,
+        name: impl Into<String>,
+    ) -> Warn {
+        Warn::new(filepath, input, pos, WarnKind::UnusedParameter(name.into()))
+    }
+
+    /// 定義したstatic関数を使っていない
+    pub fn unused_function(
+        filepath: Rc<String>,
+        input: Rc<String>,
+        pos: TokenPos,
+        name: impl Into<String>,
+    ) -> Warn {
+        Warn::new(filepath, input, pos, WarnKind::UnusedFunction(name.into()))
+    }
+
+    /// どのgotoからも飛ばないラベル
+    pub fn unused_label(
+        filepath: Rc<String>,
+        input: Rc<String>,
+        pos: TokenPos,
+        name: impl Into<String>,
+    ) -> Warn {
+        Warn::new(filepath, input, pos, WarnKind::UnusedLabel(name.into()))
+    }
+
+    /// 値を返す関数の終わりに`return`なしで達する
+    pub fn return_type(
+        filepath: Rc<String>,
+        input: Rc<String>,
+        pos: TokenPos,
+        name: impl Into<String>,
+    ) -> Warn {
+        Warn::new(filepath, input, pos, WarnKind::ReturnType(name.into()))
+    }
+
+    /// 実行されることのない文
+    pub fn unreachable_code(filepath: Rc<String>, input: Rc<String>, pos: TokenPos) -> Warn {
+        Warn::new(filepath, input, pos, WarnKind::UnreachableCode)
+    }
+
+    /// 前の`case`の文から、注釈なしに次の`case`へ流れ込む
+    /// `case`の前に注釈を入れる修正を提案する
+    pub fn implicit_fallthrough(filepath: Rc<String>, input: Rc<String>, pos: TokenPos) -> Warn {
+        Warn::new(filepath, input, pos, WarnKind::ImplicitFallthrough)
+            .with_fixit(FixIt::insert(pos.bytes, "__attribute__((fallthrough)); "))
+    }
+
+    /// どの経路でも代入されていない変数を読む
+    pub fn uninitialized(
+        filepath: Rc<String>,
+        input: Rc<String>,
+        pos: TokenPos,
+        name: impl Into<String>,
+    ) -> Warn {
+        Warn::new(filepath, input, pos, WarnKind::Uninitialized(name.into()))
+    }
+
+    /// 代入されていない経路がある変数を読む
+    pub fn maybe_uninitialized(
+        filepath: Rc<String>,
+        input: Rc<String>,
+        pos: TokenPos,
+        name: impl Into<String>,
+    ) -> Warn {
+        Warn::new(
+            filepath,
+            input,
+            pos,
+            WarnKind::MaybeUninitialized(name.into()),
+        )
+    }
+
+    fn new(filepath: Rc<String>, input: Rc<String>, pos: TokenPos, kind: WarnKind) -> Warn {
+        Warn {
+            filepath,
+            kind,
+            pos,
+            input,
+            fixits: Vec::new(),
         }
     }
-}
 
-fn warn_format(err: &Warn, msg: impl Into<String>, f: &mut fmt::Formatter) -> fmt::Result {
-    let err = util::err_format(err.input.clone(), err.filepath.clone(), err.pos, msg)?;
-    writeln!(f, "{}", err)
+    pub fn with_fixit(mut self, fixit: FixIt) -> Warn {
+        self.fixits.push(fixit);
+        self
+    }
+
+    pub fn warning(&self) -> Warning {
+        self.kind.warning()
+    }
+
+    pub fn filepath(&self) -> &Rc<String> {
+        &self.filepath
+    }
+
+    pub fn pos(&self) -> TokenPos {
+        self.pos
+    }
+
+    /// エラーとして扱う
+    pub fn into_error(self) -> Error {
+        let kind = WarningAsError(self.kind.warning(), self.kind.msg());
+        self.into_diagnostic(kind)
+    }
+
+    /// 警告のまま、エラーと同じ列に並べられる形にする
+    pub fn into_warning(self) -> Error {
+        let kind = Warned(self.kind.warning(), self.kind.msg());
+        self.into_diagnostic(kind)
+    }
+
+    fn into_diagnostic(self, kind: ErrorKind) -> Error {
+        Error {
+            filepath: self.filepath,
+            kind: Box::new(kind),
+            pos: self.pos,
+            input: self.input,
+            msg: None,
+            notes: Vec::new(),
+            fixits: self.fixits,
+        }
+    }
 }
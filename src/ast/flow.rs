@@ -0,0 +1,86 @@
+use super::cfg::Cfg;
+use super::error::Warn;
+use super::uninit;
+use super::{Context, Function, Node, NodeKind};
+use crate::base_types::TypeKind;
+use crate::token::TokenPos;
+use std::collections::HashSet;
+
+/// 制御フローグラフから、戻り値のない終わり方, 実行されない文, `case`への流れ込み,
+/// 代入前の変数の読み出しを警告する
+/// `main`の終わりに達したときは0を返すようにする
+pub fn check_function(function: &mut Function, ctx: &mut Context) {
+    let falls_off = {
+        let cfg = Cfg::new(function);
+        let reachable = cfg.reachable();
+        let mut warns = unreachable_code(&cfg, &reachable);
+        warns.append(&mut implicit_fallthrough(&cfg, &reachable));
+        warns.append(&mut uninit::check(&cfg, &reachable));
+        ctx.warn_all(warns);
+        reachable[cfg.exit]
+    };
+    if !falls_off || function.def.type_kind == TypeKind::Void {
+        return;
+    }
+    let span = function.span.clone();
+    if function.def.ident.name == "main" {
+        let ret = Node::new_unary(NodeKind::Return, Node::new_num(0)).with_span(span);
+        function.nodes.push(ret);
+        return;
+    }
+    // 関数の最後の`}`を指す
+    let pos = TokenPos {
+        tk: 0,
+        bytes: span.end.saturating_sub(1),
+    };
+    ctx.warn(Warn::return_type(
+        span.filepath.clone(),
+        span.input.clone(),
+        pos,
+        function.def.ident.name.clone(),
+    ));
+}
+
+/// どこからも来ない文の並びごとに、先頭の文だけを警告する
+fn unreachable_code(cfg: &Cfg, reachable: &[bool]) -> Vec<Warn> {
+    let mut warns = Vec::new();
+    let mut reported = HashSet::new();
+    for (stmt, block) in &cfg.stmts {
+        if reachable[*block] || !cfg.blocks[*block].preds.is_empty() || !is_code(stmt) {
+            continue;
+        }
+        if reported.insert(*block) {
+            let span = &stmt.span;
+            warns.push(Warn::unreachable_code(
+                span.filepath.clone(),
+                span.input.clone(),
+                span.pos(),
+            ));
+        }
+    }
+    warns
+}
+
+/// 実行する中身のある文か
+/// `return`の後の`break`はよく書くので警告しない
+fn is_code(stmt: &Node) -> bool {
+    use NodeKind::*;
+    !matches!(
+        stmt.kind,
+        Block(_) | Null | Break | Fallthrough | Declaration(_) | Label(_) | Case(..) | DefaultCase
+    )
+}
+
+fn implicit_fallthrough(cfg: &Cfg, reachable: &[bool]) -> Vec<Warn> {
+    cfg.fallthroughs
+        .iter()
+        .filter(|(_, from)| reachable[*from])
+        .map(|(label, _)| {
+            Warn::implicit_fallthrough(
+                label.span.filepath.clone(),
+                label.span.input.clone(),
+                label.span.pos(),
+            )
+        })
+        .collect()
+}
@@ -1,10 +1,12 @@
 use self::NodeKind::*;
+use super::diagnostics::Diagnostics;
+use super::error::{Error, Warn};
 
 use crate::base_types;
 use crate::base_types::{Member, TagTypeKind, TypeKind};
-use crate::token::Operator;
+use crate::token::{Operator, Span, TokenPos};
 use std::cell::RefCell;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::rc::Rc;
 
 #[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Debug)]
@@ -39,6 +41,8 @@ pub enum NodeKind {
     Deref,
     Block(Vec<Node>),
     Func(Rc<FuncPrototype>, Vec<Node>), // (func_name,args)
+    /// 関数名。値は関数のアドレス
+    FuncName(Rc<FuncPrototype>),
     Num(i64),
     // Ident(Ident),
     Lvar(Rc<RefCell<Lvar>>), // usize はベースポインタからのオフセット
@@ -68,14 +72,27 @@ pub enum NodeKind {
     Goto(Ident),
     Label(Ident),
     Switch(Vec<Node>),
-    Case(i64),
+    /// 値の範囲。`case 1 ... 5:`のような範囲でなければ両端は同じ
+    Case(i64, i64),
     DefaultCase,
+    /// `__attribute__((fallthrough));`。次の`case`へ流れ込むことを明示する
+    Fallthrough,
     LShift,
     RShift,
     Ternary,
+    /// 可変長配列の領域をスタックに確保する。lhsは変数、rhsはバイト数
+    /// 確保する前のrspを保持しているLvarに退避する
+    VlaAlloc(Rc<RefCell<Lvar>>),
+    /// 可変長配列の領域を解放する。lhsがあればその値を結果として残す
+    VlaFree(VlaSp),
     Null,
 }
 
+/// 可変長配列を確保する前のrspを退避した変数
+/// gotoの場合は飛び先が決まるまで分からないので後から埋める
+pub type VlaStack = Vec<Rc<RefCell<Lvar>>>;
+pub type VlaSp = Rc<RefCell<Option<Rc<RefCell<Lvar>>>>>;
+
 impl NodeKind {
     // todo
     // String is ok?
@@ -111,6 +128,7 @@ impl NodeKind {
             Deref => "*".to_string(),
             Block(_) => "block".to_string(),
             Func(func_prototype, _) => format!("function: {}", func_prototype.ident.name), // (func_name,args)
+            FuncName(func_prototype) => func_prototype.ident.name.clone(),
             Num(num) => format!("{}", num),
             // Ident(Ident),
             Lvar(lvar) => format!("{:?}", lvar), // usize はベースポインタからのオフセット
@@ -140,11 +158,15 @@ impl NodeKind {
             Goto(_) => "goto".to_string(),
             Label(_) => "label".to_string(),
             Switch(_) => "switch".to_string(),
-            Case(num) => format!("case{}", num).to_string(),
+            Case(low, high) if low == high => format!("case{}", low),
+            Case(low, high) => format!("case{}...{}", low, high),
             DefaultCase => "default case".to_string(),
+            Fallthrough => "fallthrough".to_string(),
             LShift => "<<".to_string(),
             RShift => ">>".to_string(),
             Ternary => "ternary".to_string(),
+            VlaAlloc(_) => "vla alloc".to_string(),
+            VlaFree(_) => "vla free".to_string(),
             Null => "null".to_string(),
         }
     }
@@ -226,11 +248,14 @@ pub struct Node {
     pub els: Option<Box<Node>>,
     pub init: Option<Vec<Node>>,
     pub inc: Option<Box<Node>>,
+    /// ノードに対応するソース上の範囲
+    pub span: Span,
 }
 
 impl Node {
     pub fn new(kind: NodeKind, lhs: Node, rhs: Node) -> Node {
         let mut node = Node::new_none(kind);
+        node.span = lhs.span.merge(&rhs.span);
         node.lhs = Some(Box::new(lhs));
         node.rhs = Some(Box::new(rhs));
         node
@@ -255,6 +280,7 @@ impl Node {
             els,
             init,
             inc,
+            span: Span::default(),
         }
     }
 
@@ -268,12 +294,14 @@ impl Node {
 
     pub fn new_unary(kind: NodeKind, lhs: Node) -> Node {
         let mut node = Node::new_none(kind);
+        node.span = lhs.span.clone();
         node.lhs = Some(Box::new(lhs));
         node
     }
 
     pub fn new_cond(kind: NodeKind, cond: Node) -> Node {
         let mut node = Node::new_none(kind);
+        node.span = cond.span.clone();
         node.cond = Some(Box::new(cond));
         node
     }
@@ -318,20 +346,108 @@ impl Node {
             els: None,
             init: None,
             inc: None,
+            span: Span::default(),
+        }
+    }
+
+    pub fn with_span(mut self, span: Span) -> Node {
+        self.span = span;
+        self
+    }
+
+    /// 子のノード。`Switch`が持つ`case`は本体の中にもあるので含めない
+    pub fn children(&self) -> Vec<&Node> {
+        let nodes = match &self.kind {
+            Block(nodes) | StmtExpr(nodes) | Func(_, nodes) => Some(nodes),
+            _ => None,
+        };
+        [
+            &self.lhs, &self.rhs, &self.cond, &self.then, &self.els, &self.inc,
+        ]
+        .into_iter()
+        .flatten()
+        .map(|child| child.as_ref())
+        .chain(nodes.into_iter().flatten())
+        .chain(self.init.iter().flatten())
+        .collect()
+    }
+
+    /// 自分と子孫のノードを順に`f`に渡す
+    pub fn walk(&self, f: &mut impl FnMut(&Node)) {
+        f(self);
+        for child in self.children() {
+            child.walk(f);
+        }
+    }
+
+    /// 位置を持たない子孫のノードに、それを含むノードの位置を引き継ぐ
+    /// 構文木を組み立てるときに補ったノードも、どこかのソースを指すようにする
+    pub fn fill_span(&mut self) {
+        let span = self.span.clone();
+        let nodes = match &mut self.kind {
+            Block(nodes) | StmtExpr(nodes) | Func(_, nodes) | Switch(nodes) => Some(nodes),
+            _ => None,
+        };
+        let children = [
+            &mut self.lhs,
+            &mut self.rhs,
+            &mut self.cond,
+            &mut self.then,
+            &mut self.els,
+            &mut self.inc,
+        ]
+        .into_iter()
+        .flatten()
+        .map(|child| child.as_mut())
+        .chain(nodes.into_iter().flatten())
+        .chain(self.init.iter_mut().flatten());
+        for child in children {
+            if child.span.is_dummy() {
+                child.span = span.clone();
+            }
+            child.fill_span();
         }
     }
 
     pub fn get_type(&self) -> Result<TypeKind, &'static str> {
         match &self.kind {
-            Assign | Add | Sub | Mul | Div | PostDec | PostInc | PreDec | PreInc | AAdd | ASub
-            | AMul | ADiv | ARShift | ALShift | ABitAnd | ABitOr | ABitXor | Equal | Neq
-            | Lesser | Leq | Greater | Geq => {
+            Assign | PostDec | PostInc | PreDec | PreInc | AAdd | ASub | AMul | ADiv | ARShift
+            | ALShift | ABitAnd | ABitOr | ABitXor => {
                 if let Some(ref x) = self.lhs {
                     x.get_type()
                 } else {
-                    Err("assign add sub mul")
+                    Err("assign")
+                }
+            }
+            Add | Sub | Mul | Div | BitAnd | BitOr | BitXor => {
+                if let (Some(ref lhs), Some(ref rhs)) = (&self.lhs, &self.rhs) {
+                    let lhs = lhs.get_type()?;
+                    let rhs = match rhs.get_type() {
+                        Ok(rhs) => rhs,
+                        Err(_) => return Ok(lhs),
+                    };
+                    match (lhs.is_pointer(), rhs.is_pointer()) {
+                        // ポインタどうしの差は要素数
+                        (true, true) if self.kind == Sub => Ok(TypeKind::Long),
+                        (false, true) if self.kind == Add => Ok(rhs),
+                        _ if lhs.is_integer() && rhs.is_integer() => {
+                            Ok(TypeKind::common_type(&lhs, &rhs))
+                        }
+                        _ => Ok(lhs),
+                    }
+                } else {
+                    Err("add sub mul div")
                 }
             }
+            // シフトの結果は左辺を整数拡張した型
+            LShift | RShift | BitNot => {
+                if let Some(ref lhs) = self.lhs {
+                    Ok(lhs.get_type()?.promote())
+                } else {
+                    Err("shift")
+                }
+            }
+            Equal | Neq | Lesser | Leq | Greater | Geq | LogAnd | LogOr | Not => Ok(TypeKind::Int),
             Deref => {
                 if let Some(ref lhs) = self.lhs {
                     Ok(lhs.get_type()?.get_deref_type().borrow().clone())
@@ -349,6 +465,7 @@ impl Node {
             Lvar(lvar) => Ok(lvar.borrow().get_type()),
             Gvar(gvar) => Ok(gvar.get_type()),
             Func(func_prototype, _) => Ok(func_prototype.type_kind.clone()),
+            FuncName(func_prototype) => Ok(func_prototype.func_type()),
             Num(num) => {
                 if num > &(i32::MAX as i64) {
                     Ok(TypeKind::Long)
@@ -363,6 +480,46 @@ impl Node {
                     Err("expr stmt")
                 }
             }
+            Ternary => {
+                if let (Some(ref then), Some(ref els)) = (&self.then, &self.els) {
+                    let then = then.get_type()?;
+                    let els = match els.get_type() {
+                        Ok(els) => els,
+                        Err(_) => return Ok(then),
+                    };
+                    if then.is_integer() && els.is_integer() {
+                        Ok(TypeKind::common_type(&then, &els))
+                    } else if then.is_integer() && els.is_pointer() {
+                        // `c ? 0 : p`はポインタになる
+                        Ok(els)
+                    } else {
+                        Ok(then)
+                    }
+                } else {
+                    Err("ternary")
+                }
+            }
+            Comma => {
+                if let Some(ref rhs) = self.rhs {
+                    rhs.get_type()
+                } else {
+                    Err("comma")
+                }
+            }
+            StmtExpr(nodes) => {
+                if let Some(last) = nodes.last() {
+                    last.get_type()
+                } else {
+                    Err("stmt expr")
+                }
+            }
+            VlaFree(_) => {
+                if let Some(ref lhs) = self.lhs {
+                    lhs.get_type()
+                } else {
+                    Err("vla free")
+                }
+            }
             Member(_, member) => Ok(member.get_type().as_ref().clone()), // todo他のところもrcにしていく
             Cast(type_kind) => Ok(type_kind.clone()),
             Declaration(dec) => Ok(dec.type_kind.clone()),
@@ -441,6 +598,9 @@ impl Gvar {
 
 pub type GvarMp = HashMap<String, Rc<Gvar>>;
 
+/// 1回の実行で報告するエラーの数の既定の上限
+pub const DEFAULT_ERROR_LIMIT: usize = 20;
+
 #[derive(Clone, Debug)]
 pub struct Context {
     pub g: GlobalContext,
@@ -449,6 +609,20 @@ pub struct Context {
     pub static_counter: u32,
     pub cur_switch: Option<Vec<Node>>,
     pub cur_default: Option<Node>,
+    /// 読んでいる関数。戻り値の型を確かめるのに使う
+    pub cur_func: Option<Rc<FuncPrototype>>,
+    pub vla: VlaContext,
+    /// 解析を続けるために記録しておいたエラーと警告。出した順に並ぶ
+    pub diagnostics: Vec<Error>,
+    /// 記録するエラーの上限。0なら上限なし
+    pub error_limit: usize,
+    /// 回復した文や宣言のエラーの数
+    pub recovered_errors: usize,
+    /// 宣言に失敗した変数。回復したあとで型の分からない変数として登録する
+    pub failed_decls: Vec<Ident>,
+    /// 警告の設定。警告の数を呼び出し側で読めるように共有する
+    pub diag: Rc<RefCell<Diagnostics>>,
+    pub usage: UsageContext,
 }
 
 impl Context {
@@ -460,6 +634,37 @@ impl Context {
             static_counter: 0,
             cur_switch: None,
             cur_default: None,
+            cur_func: None,
+            vla: VlaContext::new(),
+            diagnostics: Vec::new(),
+            error_limit: DEFAULT_ERROR_LIMIT,
+            recovered_errors: 0,
+            failed_decls: Vec::new(),
+            diag: Rc::new(RefCell::new(Diagnostics::new())),
+            usage: UsageContext::new(),
+        }
+    }
+
+    /// 警告を報告する。エラーとして扱う警告はエラーとして記録する
+    pub fn warn(&mut self, warn: Warn) {
+        if let Some(err) = self.diag.borrow().report(warn) {
+            self.diagnostics.push(err);
+        }
+    }
+
+    /// 記録したエラーの数。警告は含まない
+    pub fn error_count(&self) -> usize {
+        self.diagnostics
+            .iter()
+            .filter(|err| !err.is_warning())
+            .count()
+    }
+
+    /// まとめて見つけた警告を、ソースに現れた順に報告する
+    pub fn warn_all(&mut self, mut warns: Vec<Warn>) {
+        warns.sort_by_key(|warn| (warn.filepath().clone(), warn.pos().bytes));
+        for warn in warns {
+            self.warn(warn);
         }
     }
 
@@ -499,6 +704,131 @@ impl Context {
     }
 }
 
+/// 使われていない宣言を見つけるために、参照された変数と関数を記録する
+#[derive(Clone, Debug)]
+pub struct UsageContext {
+    /// 参照された変数の宣言の位置
+    vars: HashSet<(Rc<String>, usize)>,
+    /// 参照されたファイルスコープの変数と関数の名前
+    names: HashSet<String>,
+    /// 読んでいる関数の中のstatic変数。元の名前のまま持つ
+    pub static_locals: Vec<Declaration>,
+    /// ファイルスコープのstatic変数
+    pub statics: Vec<Declaration>,
+}
+
+impl Default for UsageContext {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl UsageContext {
+    pub fn new() -> Self {
+        Self {
+            vars: HashSet::new(),
+            names: HashSet::new(),
+            static_locals: Vec::new(),
+            statics: Vec::new(),
+        }
+    }
+
+    pub fn mark_lvar(&mut self, dec: &Declaration) {
+        self.vars
+            .insert((dec.span.filepath.clone(), dec.span.begin));
+    }
+
+    /// static変数は名前がラベルに置き換わっているので、位置と名前の両方で記録する
+    pub fn mark_gvar(&mut self, dec: &Declaration) {
+        self.mark_lvar(dec);
+        self.mark_name(&dec.ident.name);
+    }
+
+    pub fn mark_name(&mut self, name: &str) {
+        self.names.insert(name.to_string());
+    }
+
+    pub fn is_used(&self, dec: &Declaration) -> bool {
+        self.vars
+            .contains(&(dec.span.filepath.clone(), dec.span.begin))
+    }
+
+    pub fn is_name_used(&self, name: &str) -> bool {
+        self.names.contains(name)
+    }
+}
+
+/// 可変長配列の解放に必要な情報
+/// スコープを抜けるときは、そのスコープで最初に確保したVLAの直前のrspに戻す
+#[derive(Clone, Debug)]
+pub struct VlaContext {
+    /// 有効なVLAのrspの退避先。確保した順に並ぶ
+    pub sp: VlaStack,
+    /// break, continueの飛び先に入ったときの`sp`の長さ
+    pub break_depth: Vec<usize>,
+    pub continue_depth: Vec<usize>,
+    pub labels: HashMap<String, VlaStack>,
+    pub gotos: Vec<(String, VlaStack, VlaSp)>,
+}
+
+impl Default for VlaContext {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl VlaContext {
+    pub fn new() -> Self {
+        Self {
+            sp: Vec::new(),
+            break_depth: Vec::new(),
+            continue_depth: Vec::new(),
+            labels: HashMap::new(),
+            gotos: Vec::new(),
+        }
+    }
+
+    /// `depth`より後に確保したVLAを解放するノードを作る
+    pub fn free_to(&self, depth: usize) -> Option<Node> {
+        self.sp
+            .get(depth)
+            .map(|sp| Node::new_leaf(NodeKind::VlaFree(Rc::new(RefCell::new(Some(sp.clone()))))))
+    }
+
+    pub fn enter_loop(&mut self) {
+        self.break_depth.push(self.sp.len());
+        self.continue_depth.push(self.sp.len());
+    }
+
+    pub fn leave_loop(&mut self) {
+        self.break_depth.pop();
+        self.continue_depth.pop();
+    }
+
+    /// スコープの外へ飛ぶ`jump`の前に、そのスコープで確保したVLAを解放する
+    pub fn free_before(&self, depth: Option<usize>, jump: Node) -> Node {
+        match depth.and_then(|depth| self.free_to(depth)) {
+            Some(free) => Node::new_none(NodeKind::Block(vec![free, jump])),
+            None => jump,
+        }
+    }
+
+    /// gotoの飛び先のスコープに合わせて、解放するVLAを決める
+    pub fn resolve_gotos(&mut self) {
+        for (label, sp, target) in self.gotos.drain(..) {
+            if let Some(label_sp) = self.labels.get(&label) {
+                let common = sp
+                    .iter()
+                    .zip(label_sp)
+                    .take_while(|(a, b)| Rc::ptr_eq(a, b))
+                    .count();
+                *target.borrow_mut() = sp.get(common).cloned();
+            }
+        }
+        self.labels.clear();
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct GlobalContext {
     pub gvar_mp: GvarMp,
@@ -520,6 +850,10 @@ impl GlobalContext {
 pub struct LocalContext {
     pub lvar: Option<Rc<RefCell<Lvar>>>,
     pub(crate) lvar_count: usize,
+    /// 関数の中で定義したラベル
+    pub labels: HashSet<String>,
+    /// `goto`の飛び先と`goto`の位置。関数の終わりでラベルがあるか確かめる
+    pub gotos: Vec<(Ident, TokenPos)>,
 }
 
 impl LocalContext {
@@ -527,13 +861,15 @@ impl LocalContext {
         Self {
             lvar: None,
             lvar_count: 0,
+            labels: HashSet::new(),
+            gotos: Vec::new(),
         }
     }
 
     pub fn push_front(&mut self, dec: Declaration, offset: u64) {
         self.lvar_count += 1;
         let offset = offset + dec.type_kind.size();
-        let offset = base_types::align_to(offset, dec.type_kind.align());
+        let offset = base_types::align_to(offset, dec.align());
         self.lvar = Some(Rc::new(RefCell::new(Lvar {
             next: self.lvar.take(),
             dec,
@@ -551,6 +887,17 @@ impl LocalContext {
     //     }))
     // }
 
+    /// 関数の中の変数の宣言。後に宣言したものから並ぶ
+    pub fn decs(&self) -> Vec<Declaration> {
+        let mut decs = Vec::new();
+        let mut lvar = self.lvar.clone();
+        while let Some(cur) = lvar {
+            decs.push(cur.borrow().dec.clone());
+            lvar = cur.borrow().next.clone();
+        }
+        decs
+    }
+
     pub fn find_lvar(&self, name: impl Into<String>) -> Option<Rc<RefCell<Lvar>>> {
         if let Some(ref lvar) = self.lvar {
             Self::_find_lvar(lvar, name)
@@ -695,6 +1042,26 @@ impl Scope {
     pub fn insert_t(&mut self, ident: Rc<Ident>, tag: TagTypeKind) -> Option<Rc<TagTypeKind>> {
         self.t.insert((ident, self.depth), Rc::new(tag))
     }
+
+    /// 見えている変数と列挙定数の名前
+    pub fn visible_vars(&self) -> Vec<&str> {
+        self.v
+            .keys()
+            .filter(|(_, depth)| *depth <= self.depth)
+            .map(|(ident, _)| ident.name.as_str())
+            .collect()
+    }
+
+    /// 見えている構造体, 共用体, 列挙型のタグ。typedefの名前は含めない
+    pub fn visible_tags(&self) -> Vec<&str> {
+        self.t
+            .iter()
+            .filter(|((_, depth), tag)| {
+                *depth <= self.depth && !matches!(tag.as_ref(), TagTypeKind::Typedef(_))
+            })
+            .map(|((ident, _), _)| ident.name.as_str())
+            .collect()
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -720,6 +1087,10 @@ pub struct Function {
     pub nodes: Vec<Node>,
     pub is_static: bool,
     pub va_area: Option<Lvar>,
+    /// 戻り値の構造体を書き込む先のアドレスを保存しておく変数
+    pub ret_ptr: Option<Lvar>,
+    /// 関数定義全体のソース上の範囲
+    pub span: Span,
 }
 
 impl From<Function> for FuncPrototype {
@@ -730,6 +1101,9 @@ impl From<Function> for FuncPrototype {
             params: from.def.params.clone(),
             param_num: from.def.param_num,
             is_static: from.is_static,
+            is_variadic: from.def.is_variadic,
+            has_prototype: from.def.has_prototype,
+            span: from.def.span.clone(),
         }
     }
 }
@@ -750,6 +1124,8 @@ impl Function {
             nodes,
             is_static,
             va_area: va_area,
+            ret_ptr: None,
+            span: Span::default(),
         }
     }
 
@@ -765,11 +1141,30 @@ impl Function {
         }
     }
 
-    pub fn get_param_size(&self) -> u64 {
-        let mut result = 0;
-        for dec in &self.def.params {
-            result += dec.type_kind.size();
-            result = base_types::align_to(result, dec.type_kind.align());
+    /// 引数の変数の位置を引数の順に返す
+    /// 引数は最後に変数の並びに加えているので、先頭から引数の数だけ取り出す
+    pub fn param_offsets(&self) -> Vec<u64> {
+        let mut result = Vec::new();
+        let mut lvar = self.all_vars.clone();
+        while let Some(v) = lvar {
+            if result.len() == self.def.param_num {
+                break;
+            }
+            result.push(v.borrow().offset);
+            lvar = v.borrow().next.clone();
+        }
+        result.reverse();
+        result
+    }
+
+    /// ローカル変数のアライメントの最大値
+    /// 16を超える場合はプロローグでスタックフレームを揃え直す必要がある
+    pub fn get_max_align(&self) -> u64 {
+        let mut result = 1;
+        let mut lvar = self.all_vars.clone();
+        while let Some(v) = lvar {
+            result = result.max(v.borrow().dec.align());
+            lvar = v.borrow().next.clone();
         }
         result
     }
@@ -782,6 +1177,11 @@ pub struct FuncPrototype {
     pub params: Vec<Declaration>,
     pub param_num: usize,
     pub is_static: bool,
+    pub is_variadic: bool,
+    /// `int f()`のように引数の型が宣言されていなければfalse
+    pub has_prototype: bool,
+    /// 宣言子のソース上の範囲
+    pub span: Span,
 }
 
 impl FuncPrototype {
@@ -798,8 +1198,44 @@ impl FuncPrototype {
             params,
             param_num,
             is_static,
+            is_variadic: false,
+            has_prototype: true,
+            span: Span::default(),
+        }
+    }
+
+    /// 関数型から関数の宣言を作る。関数型でなければNone
+    pub fn from_type(type_kind: &TypeKind, ident: Ident, is_static: bool) -> Option<Self> {
+        match type_kind {
+            TypeKind::Func(ret, params, is_variadic) => {
+                let mut func_prototype = Self::new(
+                    ret.borrow().clone(),
+                    ident,
+                    params.as_deref().cloned().unwrap_or_default(),
+                    is_static,
+                );
+                func_prototype.is_variadic = *is_variadic;
+                func_prototype.has_prototype = params.is_some();
+                Some(func_prototype)
+            }
+            _ => None,
         }
     }
+
+    /// 関数を値として使うときの型
+    pub fn func_type(&self) -> TypeKind {
+        TypeKind::Func(
+            Rc::new(RefCell::new(self.type_kind.clone())),
+            Some(Rc::new(self.params.clone())).filter(|_| self.has_prototype),
+            self.is_variadic,
+        )
+    }
+
+    /// 16byteを超える構造体を返す関数は、呼び出し元が確保した領域のアドレスを
+    /// 隠れた第1引数(rdi)として受け取り、そこに戻り値を書き込む
+    pub fn has_ret_ptr(&self) -> bool {
+        matches!(self.type_kind, TypeKind::Struct(_)) && self.type_kind.size() > 16
+    }
 }
 pub type FuncPrototypeMp = HashMap<String, Rc<FuncPrototype>>;
 
@@ -811,6 +1247,9 @@ pub struct Declaration {
     pub is_static: bool,
     pub is_extern: bool,
     pub is_const: (bool, i64), // for enum
+    pub alignas: Option<u64>,  // _Alignas で指定されたアライメント
+    /// 宣言子のソース上の範囲
+    pub span: Span,
 }
 
 impl Declaration {
@@ -822,6 +1261,8 @@ impl Declaration {
             is_static: false,
             is_extern: false,
             is_const: (false, 0),
+            alignas: None,
+            span: Span::default(),
         }
     }
 
@@ -833,9 +1274,17 @@ impl Declaration {
             is_static: false,
             is_extern: false,
             is_const: (true, val),
+            alignas: None,
+            span: Span::default(),
         }
     }
 
+    /// `_Alignas`による指定を含めたアライメント
+    pub fn align(&self) -> u64 {
+        let align = self.type_kind.align();
+        self.alignas.map_or(align, |x| x.max(align))
+    }
+
     // todo: remove clone
     fn get_type(&self) -> TypeKind {
         self.type_kind.clone()
@@ -854,6 +1303,85 @@ impl Designator {
     }
 }
 
+/// 初期化子を型に沿って並べた木
+/// 指示子で順番が入れ替わったり上書きされたりするので、一度木にしてから展開する
+#[derive(Debug, Clone)]
+pub struct InitTree {
+    pub type_kind: TypeKind,
+    /// スカラー、または構造体の値で初期化するときの式
+    pub expr: Option<Node>,
+    /// 配列の要素、構造体のメンバ
+    pub children: Vec<InitTree>,
+    /// unionで初期化するメンバ
+    pub member: Option<usize>,
+    /// `{}`や文字列で丸ごと初期化し直したか
+    pub replaced: bool,
+    /// 要素を初期化する前に評価する式。範囲指定の初期化子を一度だけ評価するのに使う
+    pub prelude: Vec<Node>,
+}
+
+impl InitTree {
+    pub fn new(type_kind: TypeKind) -> Self {
+        let children = match &type_kind {
+            TypeKind::Array(size, base, _) => (0..*size)
+                .map(|_| InitTree::new(base.borrow().clone()))
+                .collect(),
+            TypeKind::Struct(_struct) => _struct
+                .borrow()
+                .members
+                .iter()
+                .map(|m| InitTree::new(m.type_kind.as_ref().clone()))
+                .collect(),
+            _ => Vec::new(),
+        };
+        Self {
+            type_kind,
+            expr: None,
+            children,
+            member: None,
+            replaced: false,
+            prelude: Vec::new(),
+        }
+    }
+
+    /// 前の初期化子を捨てて、丸ごと初期化し直す
+    pub fn replace(&mut self) {
+        *self = InitTree::new(self.type_kind.clone());
+        self.replaced = true;
+    }
+
+    /// `patch`で初期化した所だけを上書きする
+    pub fn overlay(&mut self, patch: &InitTree) {
+        if patch.replaced {
+            *self = patch.clone();
+            return;
+        }
+        if patch.expr.is_some() {
+            self.expr = patch.expr.clone();
+        }
+        if let Some(member) = patch.member {
+            if self.member != Some(member) {
+                self.children[member] = InitTree::new(self.children[member].type_kind.clone());
+                self.member = Some(member);
+            }
+        }
+        self.prelude.extend(patch.prelude.iter().cloned());
+        for (i, child) in patch.children.iter().enumerate() {
+            self.child_mut(i).overlay(child);
+        }
+    }
+
+    /// 要素数のない配列は必要な分だけ要素を増やす
+    pub fn child_mut(&mut self, idx: usize) -> &mut InitTree {
+        if let TypeKind::Array(_, base, false) = &self.type_kind {
+            while self.children.len() <= idx {
+                self.children.push(InitTree::new(base.borrow().clone()));
+            }
+        }
+        &mut self.children[idx]
+    }
+}
+
 /// global var initializer
 #[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Debug)]
 pub enum Initializer {
@@ -890,6 +1418,60 @@ mod tests {
             &mut ctx,
         )
         .unwrap();
-        assert_eq!(TypeKind::Int, node.get_type().unwrap())
+        assert_eq!(TypeKind::Int, node.get_type().unwrap());
+
+        let y_type = TypeKind::Ptr(Rc::new(RefCell::new(TypeKind::Int)));
+        for input in &["(1, y);", "1 ? y : y;", "({1; y;});"] {
+            let mut ctx = Context::new();
+            ctx.push_front(Declaration::new(y_type.clone(), Ident::new("y")));
+            let node = ast::stmt(
+                &mut token::tokenize(Rc::new(input.to_string()), Rc::new("".to_string())).unwrap(),
+                &mut ctx,
+            )
+            .unwrap();
+            assert_eq!(y_type, node.get_type().unwrap());
+        }
+    }
+
+    #[test]
+    fn test_get_type_arithmetic_conversion() {
+        use crate::ast::ast;
+        use crate::base_types::TypeKind;
+        use crate::token;
+        use std::cell::RefCell;
+
+        let int_ptr = TypeKind::Ptr(Rc::new(RefCell::new(TypeKind::Int)));
+        let tests = [
+            ("c + c;", TypeKind::Int),
+            ("c * l;", TypeKind::Long),
+            ("s - c;", TypeKind::Int),
+            ("-c;", TypeKind::Int),
+            ("~s;", TypeKind::Int),
+            ("c << l;", TypeKind::Int),
+            ("l >> c;", TypeKind::Long),
+            ("c < l;", TypeKind::Int),
+            ("!l;", TypeKind::Int),
+            ("c & l;", TypeKind::Long),
+            ("1 ? c : l;", TypeKind::Long),
+            ("1 ? c : s;", TypeKind::Int),
+            ("1 ? 0 : p;", int_ptr.clone()),
+            ("1 + p;", int_ptr.clone()),
+            ("p - p;", TypeKind::Long),
+            ("c = l;", TypeKind::Char),
+            ("c += l;", TypeKind::Char),
+        ];
+        for (input, expected) in &tests {
+            let mut ctx = Context::new();
+            ctx.push_front(Declaration::new(TypeKind::Char, Ident::new("c")));
+            ctx.push_front(Declaration::new(TypeKind::Short, Ident::new("s")));
+            ctx.push_front(Declaration::new(TypeKind::Long, Ident::new("l")));
+            ctx.push_front(Declaration::new(int_ptr.clone(), Ident::new("p")));
+            let node = ast::stmt(
+                &mut token::tokenize(Rc::new(input.to_string()), Rc::new("".to_string())).unwrap(),
+                &mut ctx,
+            )
+            .unwrap();
+            assert_eq!(expected, &node.get_type().unwrap(), "{}", input);
+        }
     }
 }
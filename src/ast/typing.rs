@@ -0,0 +1,119 @@
+use super::{Function, Node, NodeKind};
+use crate::base_types::TypeKind;
+
+/// 関数の本体に暗黙の型変換を表す`NodeKind::Cast`を挿入する
+/// 整数拡張, 通常の算術変換, 代入, 引数, 戻り値の変換を明示的にすることで、
+/// コード生成は`Cast`を見るだけで型に合った値を作れる
+pub fn add_conversions(function: &mut Function) {
+    let ret = function.def.type_kind.clone();
+    for node in &mut function.nodes {
+        convert(node, &ret);
+    }
+}
+
+fn convert(node: &mut Node, ret: &TypeKind) {
+    use NodeKind::*;
+
+    for child in [
+        &mut node.lhs,
+        &mut node.rhs,
+        &mut node.cond,
+        &mut node.then,
+        &mut node.els,
+        &mut node.inc,
+    ]
+    .into_iter()
+    .flatten()
+    {
+        convert(child, ret);
+    }
+    if let Some(init) = &mut node.init {
+        for i in init {
+            convert(i, ret);
+        }
+    }
+
+    match &mut node.kind {
+        Block(stmts) | StmtExpr(stmts) => {
+            for stmt in stmts {
+                convert(stmt, ret);
+            }
+        }
+        Func(func_prototype, args) => {
+            for (i, arg) in args.iter_mut().enumerate() {
+                convert(arg, ret);
+                // プロトタイプのない引数は整数拡張だけする
+                match func_prototype.params.get(i) {
+                    Some(param) => cast_node(arg, &param.type_kind),
+                    None => {
+                        if let Ok(type_kind) = arg.get_type() {
+                            cast_node(arg, &type_kind.promote());
+                        }
+                    }
+                }
+            }
+        }
+        // 両辺を共通の型に揃える
+        Add | Sub | Mul | Div | BitAnd | BitOr | BitXor | Equal | Neq | Lesser | Leq | Greater
+        | Geq => {
+            if let Some(type_kind) = common_type(&node.lhs, &node.rhs) {
+                cast(&mut node.lhs, &type_kind);
+                cast(&mut node.rhs, &type_kind);
+            }
+        }
+        // シフトは両辺を別々に整数拡張する
+        LShift | RShift => {
+            promote(&mut node.lhs);
+            promote(&mut node.rhs);
+        }
+        BitNot => promote(&mut node.lhs),
+        Ternary => {
+            if let Some(type_kind) = common_type(&node.then, &node.els) {
+                cast(&mut node.then, &type_kind);
+                cast(&mut node.els, &type_kind);
+            }
+        }
+        // 右辺を左辺の型に変換する
+        Assign => {
+            if let Some(Ok(type_kind)) = node.lhs.as_ref().map(|lhs| lhs.get_type()) {
+                cast(&mut node.rhs, &type_kind);
+            }
+        }
+        Return => cast(&mut node.lhs, ret),
+        _ => (),
+    }
+}
+
+/// 両辺が整数型ならその共通の型を返す
+fn common_type(lhs: &Option<Box<Node>>, rhs: &Option<Box<Node>>) -> Option<TypeKind> {
+    let lhs = lhs.as_ref()?.get_type().ok()?;
+    let rhs = rhs.as_ref()?.get_type().ok()?;
+    if lhs.is_integer() && rhs.is_integer() {
+        Some(TypeKind::common_type(&lhs, &rhs))
+    } else {
+        None
+    }
+}
+
+fn promote(node: &mut Option<Box<Node>>) {
+    if let Some(Ok(type_kind)) = node.as_ref().map(|node| node.get_type()) {
+        cast(node, &type_kind.promote());
+    }
+}
+
+fn cast(node: &mut Option<Box<Node>>, type_kind: &TypeKind) {
+    if let Some(node) = node {
+        cast_node(node, type_kind);
+    }
+}
+
+/// 整数型どうしで型が違うときだけ`Cast`で包む
+fn cast_node(node: &mut Node, type_kind: &TypeKind) {
+    match node.get_type() {
+        Ok(from) if from.is_integer() && type_kind.is_integer() && &from != type_kind => {
+            let inner = std::mem::replace(node, Node::new_leaf(NodeKind::Null));
+            *node = Node::new_unary(NodeKind::Cast(type_kind.clone()), inner);
+        }
+        _ => (),
+    }
+}
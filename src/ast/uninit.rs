@@ -0,0 +1,309 @@
+use super::cfg::Cfg;
+use super::error::Warn;
+use super::{Declaration, Node, NodeKind};
+use crate::base_types::TypeKind;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+/// 変数を宣言した位置
+type VarKey = (Rc<String>, usize);
+
+/// 調べる変数ごとの代入の状況
+#[derive(Clone, PartialEq, Eq, Debug)]
+struct State {
+    /// どの経路でも代入されている
+    must: Vec<bool>,
+    /// 代入されている経路がある
+    may: Vec<bool>,
+}
+
+impl State {
+    fn new(len: usize) -> Self {
+        Self {
+            must: vec![false; len],
+            may: vec![false; len],
+        }
+    }
+
+    /// 合流点での状況
+    fn join(&mut self, other: &State) {
+        for i in 0..self.must.len() {
+            self.must[i] &= other.must[i];
+            self.may[i] |= other.may[i];
+        }
+    }
+
+    fn assign(&mut self, var: usize) {
+        self.must[var] = true;
+        self.may[var] = true;
+    }
+
+    fn declare(&mut self, var: usize) {
+        self.must[var] = false;
+        self.may[var] = false;
+    }
+}
+
+/// 代入されていないかもしれない変数の読み出し
+struct Read<'a> {
+    var: usize,
+    node: &'a Node,
+    /// 代入されている経路もあるか
+    maybe: bool,
+}
+
+/// 初期化子なしで宣言した局所変数を、代入する前に読んでいる所を警告する
+/// 変数ごとにソース上で最初の読み出しだけを警告する
+pub fn check(cfg: &Cfg, reachable: &[bool]) -> Vec<Warn> {
+    let mut vars = HashMap::new();
+    for block in &cfg.blocks {
+        for node in &block.nodes {
+            node.walk(&mut |node| {
+                if let NodeKind::Declaration(dec) = &node.kind {
+                    if is_target(dec) {
+                        let len = vars.len();
+                        vars.entry(key(dec)).or_insert(len);
+                    }
+                }
+            });
+        }
+    }
+    if vars.is_empty() {
+        return Vec::new();
+    }
+
+    let mut outs: Vec<Option<Outs>> = vec![None; cfg.blocks.len()];
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for block in 0..cfg.blocks.len() {
+            let state = match in_state(cfg, reachable, &outs, block, vars.len()) {
+                Some(state) => state,
+                None => continue,
+            };
+            let out = transfer(cfg, block, &vars, state, &mut Vec::new());
+            if outs[block].as_ref() != Some(&out) {
+                outs[block] = Some(out);
+                changed = true;
+            }
+        }
+    }
+
+    let mut first: HashMap<usize, Read> = HashMap::new();
+    for block in 0..cfg.blocks.len() {
+        let state = match in_state(cfg, reachable, &outs, block, vars.len()) {
+            Some(state) => state,
+            None => continue,
+        };
+        let mut reads = Vec::new();
+        transfer(cfg, block, &vars, state, &mut reads);
+        for read in reads {
+            match first.get(&read.var) {
+                Some(prev) if prev.node.span.begin <= read.node.span.begin => (),
+                _ => {
+                    first.insert(read.var, read);
+                }
+            }
+        }
+    }
+    first
+        .into_values()
+        .map(|read| {
+            let span = &read.node.span;
+            let name = match &read.node.kind {
+                NodeKind::Lvar(lvar) => lvar.borrow().dec.ident.name.clone(),
+                _ => unreachable!(),
+            };
+            let warn = if read.maybe {
+                Warn::maybe_uninitialized
+            } else {
+                Warn::uninitialized
+            };
+            warn(span.filepath.clone(), span.input.clone(), span.pos(), name)
+        })
+        .collect()
+}
+
+/// ブロックの出口での状況。条件が真のときと偽のときに分ける
+/// 分岐しないブロックでは同じ状況になる
+type Outs = (State, State);
+
+/// ブロックの中の式を順に評価する。分岐するブロックの条件は真と偽に分けて評価する
+fn transfer<'a>(
+    cfg: &Cfg<'a>,
+    block: usize,
+    vars: &HashMap<VarKey, usize>,
+    mut state: State,
+    reads: &mut Vec<Read<'a>>,
+) -> Outs {
+    let block = &cfg.blocks[block];
+    let (nodes, cond) = match block.branch {
+        Some((cond, _)) => (&block.nodes[..block.nodes.len() - 1], Some(cond)),
+        None => (&block.nodes[..], None),
+    };
+    for node in nodes {
+        eval(node, vars, &mut state, reads);
+    }
+    match cond {
+        Some(cond) => eval_cond(cond, vars, state, reads),
+        None => (state.clone(), state),
+    }
+}
+
+/// ブロックの入口での状況。入口からたどり着けないブロックや、まだ計算していないブロックはNone
+fn in_state(
+    cfg: &Cfg,
+    reachable: &[bool],
+    outs: &[Option<Outs>],
+    block: usize,
+    len: usize,
+) -> Option<State> {
+    if !reachable[block] {
+        return None;
+    }
+    let mut result = if block == 0 {
+        Some(State::new(len))
+    } else {
+        None
+    };
+    for pred in &cfg.blocks[block].preds {
+        if let Some((then, els)) = &outs[*pred] {
+            let out = match cfg.blocks[*pred].branch {
+                Some((_, to)) if to != block => els,
+                _ => then,
+            };
+            match &mut result {
+                Some(state) => state.join(out),
+                None => result = Some(out.clone()),
+            }
+        }
+    }
+    result
+}
+
+/// 条件を評価した後の、真のときと偽のときの状況を計算する
+/// `&&`と`||`の右辺は、左辺で値が決まらなかったときだけ評価する
+fn eval_cond<'a>(
+    node: &'a Node,
+    vars: &HashMap<VarKey, usize>,
+    state: State,
+    reads: &mut Vec<Read<'a>>,
+) -> Outs {
+    match &node.kind {
+        NodeKind::LogAnd => {
+            let (then, mut els) = eval_cond(node.lhs.as_ref().unwrap(), vars, state, reads);
+            let (then, rhs_els) = eval_cond(node.rhs.as_ref().unwrap(), vars, then, reads);
+            els.join(&rhs_els);
+            (then, els)
+        }
+        NodeKind::LogOr => {
+            let (mut then, els) = eval_cond(node.lhs.as_ref().unwrap(), vars, state, reads);
+            let (rhs_then, els) = eval_cond(node.rhs.as_ref().unwrap(), vars, els, reads);
+            then.join(&rhs_then);
+            (then, els)
+        }
+        NodeKind::Not => {
+            let (then, els) = eval_cond(node.lhs.as_ref().unwrap(), vars, state, reads);
+            (els, then)
+        }
+        _ => {
+            let mut state = state;
+            eval(node, vars, &mut state, reads);
+            (state.clone(), state)
+        }
+    }
+}
+
+/// 式を評価した後の状況を計算し、代入前かもしれない読み出しを`reads`に加える
+fn eval<'a>(
+    node: &'a Node,
+    vars: &HashMap<VarKey, usize>,
+    state: &mut State,
+    reads: &mut Vec<Read<'a>>,
+) {
+    use NodeKind::*;
+    let var = |node: Option<&Node>| match node.map(|node| &node.kind) {
+        Some(Lvar(lvar)) => vars.get(&key(&lvar.borrow().dec)).copied(),
+        _ => None,
+    };
+    match &node.kind {
+        Declaration(dec) => {
+            if let Some(&var) = vars.get(&key(dec)) {
+                state.declare(var);
+            }
+        }
+        // 初期化子を持つのは宣言した変数への代入
+        Lvar(_) if node.init.is_some() => {
+            for init in node.init.iter().flatten() {
+                eval(init, vars, state, reads);
+            }
+        }
+        Lvar(_) => {
+            if let Some(var) = var(Some(node)) {
+                if !state.must[var] {
+                    reads.push(Read {
+                        var,
+                        node,
+                        maybe: state.may[var],
+                    });
+                }
+            }
+        }
+        Assign => {
+            eval(node.rhs.as_ref().unwrap(), vars, state, reads);
+            match var(node.lhs.as_deref()) {
+                Some(var) => state.assign(var),
+                None => eval(node.lhs.as_ref().unwrap(), vars, state, reads),
+            }
+        }
+        AAdd | ASub | AMul | ADiv | ALShift | ARShift | ABitAnd | ABitOr | ABitXor | PreInc
+        | PreDec | PostInc | PostDec => {
+            for child in node.children() {
+                eval(child, vars, state, reads);
+            }
+            if let Some(var) = var(node.lhs.as_deref()) {
+                state.assign(var);
+            }
+        }
+        // アドレスを取った変数はどこで代入されるか分からない
+        Addr => match var(node.lhs.as_deref()) {
+            Some(var) => state.assign(var),
+            None => eval(node.lhs.as_ref().unwrap(), vars, state, reads),
+        },
+        // `(void)x;`は使わない変数の警告を消すための書き方
+        Cast(crate::base_types::TypeKind::Void) if var(node.lhs.as_deref()).is_some() => (),
+        LogAnd | LogOr => {
+            eval(node.lhs.as_ref().unwrap(), vars, state, reads);
+            let skipped = state.clone();
+            eval(node.rhs.as_ref().unwrap(), vars, state, reads);
+            state.join(&skipped);
+        }
+        Ternary => {
+            eval(node.cond.as_ref().unwrap(), vars, state, reads);
+            let mut els = state.clone();
+            eval(node.then.as_ref().unwrap(), vars, state, reads);
+            eval(node.els.as_ref().unwrap(), vars, &mut els, reads);
+            state.join(&els);
+        }
+        // 文式の中の文は、制御フローグラフの中で先に評価している
+        StmtExpr(_) => (),
+        _ => {
+            for child in node.children() {
+                eval(child, vars, state, reads);
+            }
+        }
+    }
+}
+
+fn key(dec: &Declaration) -> VarKey {
+    (dec.span.filepath.clone(), dec.span.begin)
+}
+
+/// 初期化子なしで宣言した、自動記憶域のスカラー変数だけを調べる
+fn is_target(dec: &Declaration) -> bool {
+    !dec.span.is_dummy()
+        && !dec.is_static
+        && !dec.is_extern
+        && !dec.is_typedef
+        && (dec.type_kind.is_integer() || matches!(dec.type_kind, TypeKind::Ptr(_)))
+}
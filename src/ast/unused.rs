@@ -0,0 +1,93 @@
+use super::error::Warn;
+use super::{Context, Declaration, Function, NodeKind, Program};
+use std::collections::HashSet;
+
+/// 関数の中で使われていない変数, 引数, ラベルを警告する
+pub fn check_function(function: &Function, ctx: &mut Context) {
+    let mut warns = Vec::new();
+    let is_param = |dec: &Declaration| {
+        function
+            .def
+            .params
+            .iter()
+            .any(|param| param.span.begin == dec.span.begin)
+    };
+    let static_locals = std::mem::take(&mut ctx.usage.static_locals);
+    for dec in ctx.l.decs().iter().chain(&static_locals) {
+        if !is_target(dec) || ctx.usage.is_used(dec) {
+            continue;
+        }
+        let warn = if is_param(dec) {
+            Warn::unused_parameter
+        } else {
+            Warn::unused_variable
+        };
+        warns.push(warn(
+            dec.span.filepath.clone(),
+            dec.span.input.clone(),
+            dec.span.pos(),
+            dec.ident.name.clone(),
+        ));
+    }
+
+    let mut labels = Vec::new();
+    let mut gotos = HashSet::new();
+    for node in &function.nodes {
+        node.walk(&mut |node| match &node.kind {
+            NodeKind::Label(ident) => labels.push((ident.name.clone(), node.span.clone())),
+            NodeKind::Goto(ident) => {
+                gotos.insert(ident.name.clone());
+            }
+            _ => (),
+        });
+    }
+    for (name, span) in labels {
+        if !gotos.contains(&name) {
+            warns.push(Warn::unused_label(
+                span.filepath.clone(),
+                span.input.clone(),
+                span.pos(),
+                name,
+            ));
+        }
+    }
+    ctx.warn_all(warns);
+}
+
+/// ファイルの中で使われていないstatic変数とstatic関数を警告する
+pub fn check_program(program: &mut Program) {
+    let ctx = &mut program.ctx;
+    let mut warns = Vec::new();
+    let mut seen = HashSet::new();
+    for dec in std::mem::take(&mut ctx.usage.statics) {
+        let name = dec.ident.name.clone();
+        if !is_target(&dec) || ctx.usage.is_name_used(&name) || !seen.insert(name.clone()) {
+            continue;
+        }
+        warns.push(Warn::unused_static_variable(
+            dec.span.filepath.clone(),
+            dec.span.input.clone(),
+            dec.span.pos(),
+            name,
+        ));
+    }
+    for function in &program.functions {
+        let name = &function.def.ident.name;
+        if function.is_static && !ctx.usage.is_name_used(name) {
+            let span = &function.span;
+            warns.push(Warn::unused_function(
+                span.filepath.clone(),
+                span.input.clone(),
+                span.pos(),
+                name.clone(),
+            ));
+        }
+    }
+    ctx.warn_all(warns);
+}
+
+/// 自分で書いた名前のある変数だけを調べる
+/// 可変長引数の保存先のように、コンパイラが作った変数は位置を持たない
+fn is_target(dec: &Declaration) -> bool {
+    !dec.span.is_dummy() && !dec.ident.is_anonymous() && !dec.is_typedef && !dec.is_extern
+}
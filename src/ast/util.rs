@@ -1,14 +1,15 @@
 use super::{
-    ast::assign,
+    ast::{assign, is_null_pointer_constant},
     error::{Error, Warn},
 };
 use super::{
     Context, Declaration, Designator, FuncPrototype, FuncPrototypeMp, Gvar, GvarMp, Ident,
-    Initializer, Node, NodeKind, Var,
+    Initializer, Lvar, Node, NodeKind, Var,
 };
-use crate::base_types::{self, Member, TagTypeKind, TypeKind};
+use crate::base_types::{self, Member, Struct, TagTypeKind, TypeKind};
 
-use crate::token::{Block, KeyWord, Operator, TokenKind, TokenStream};
+use crate::render::{Location, Note};
+use crate::token::{Block, KeyWord, Operator, Span, TokenKind, TokenPos, TokenStream};
 use std::{cell::RefCell, rc::Rc};
 
 pub(crate) fn consume(iter: &mut TokenStream, op: Operator) -> bool {
@@ -23,7 +24,7 @@ pub(crate) fn consume(iter: &mut TokenStream, op: Operator) -> bool {
     return false;
 }
 
-pub(crate) fn _consume_keyword(iter: &mut TokenStream, key: KeyWord) -> bool {
+pub(crate) fn consume_keyword(iter: &mut TokenStream, key: KeyWord) -> bool {
     if let Some(x) = iter.peek() {
         if let TokenKind::KeyWord(x) = x.kind {
             if x == key {
@@ -35,6 +36,39 @@ pub(crate) fn _consume_keyword(iter: &mut TokenStream, key: KeyWord) -> bool {
     false
 }
 
+pub(crate) fn is_next_keyword(iter: &TokenStream, key: KeyWord) -> bool {
+    iter.peek().map(|x| x.kind) == Some(TokenKind::KeyWord(key))
+}
+
+pub(crate) fn is_next_block(iter: &TokenStream, block: Block) -> bool {
+    iter.peek().map(|x| x.kind) == Some(TokenKind::Block(block))
+}
+
+/// 初期化子の指示子(`[`か`.`)が続くかどうか
+pub(crate) fn is_designator(iter: &TokenStream) -> bool {
+    is_next(iter, Operator::LArr) || iter.peek().map(|x| x.kind) == Some(TokenKind::Period)
+}
+
+/// `,`の後には宣言子が続かなければならない
+pub(crate) fn expect_declarator(iter: &mut TokenStream) -> Result<(), Error> {
+    if is_next(iter, Operator::Mul) || is_next(iter, Operator::LParen) {
+        return Ok(());
+    }
+    if let Some(TokenKind::Ident(_)) = iter.peek().map(|x| x.kind) {
+        return Ok(());
+    }
+    expect_ident(iter).map(|_| ())
+}
+
+pub(crate) fn is_next(iter: &TokenStream, op: Operator) -> bool {
+    if let Some(x) = iter.peek() {
+        if let TokenKind::Reserved(x) = x.kind {
+            return x == op;
+        }
+    }
+    false
+}
+
 pub(crate) fn consume_semi(iter: &mut TokenStream) -> bool {
     if let Some(x) = iter.peek() {
         if x.kind == TokenKind::SemiColon {
@@ -75,6 +109,36 @@ pub(crate) fn consume_ident(iter: &mut TokenStream) -> Option<Ident> {
     return None;
 }
 
+/// `__attribute__((fallthrough));`か`[[fallthrough]];`を読む
+pub(crate) fn consume_fallthrough(iter: &mut TokenStream) -> bool {
+    let data = iter.save();
+    let is_fallthrough = |iter: &mut TokenStream| {
+        consume_ident(iter).map(|ident| ident.name) == Some("fallthrough".to_string())
+    };
+    let attribute = match consume_ident(iter) {
+        Some(ident) if ident.name == "__attribute__" => {
+            consume(iter, Operator::LParen)
+                && consume(iter, Operator::LParen)
+                && is_fallthrough(iter)
+                && consume(iter, Operator::RParen)
+                && consume(iter, Operator::RParen)
+        }
+        Some(_) => false,
+        None => {
+            consume(iter, Operator::LArr)
+                && consume(iter, Operator::LArr)
+                && is_fallthrough(iter)
+                && consume(iter, Operator::RArr)
+                && consume(iter, Operator::RArr)
+        }
+    };
+    if attribute && consume_semi(iter) {
+        return true;
+    }
+    iter.restore(data);
+    false
+}
+
 pub(crate) fn consume_block(iter: &mut TokenStream, block: Block) -> bool {
     if let Some(x) = iter.peek() {
         if let TokenKind::Block(x) = x.kind {
@@ -97,7 +161,7 @@ pub(crate) fn consume_comma(iter: &mut TokenStream) -> bool {
     false
 }
 
-pub(crate) fn _consume_period(iter: &mut TokenStream) -> bool {
+pub(crate) fn consume_period(iter: &mut TokenStream) -> bool {
     if let Some(x) = iter.peek() {
         if x.kind == TokenKind::Period {
             iter.next();
@@ -107,6 +171,11 @@ pub(crate) fn _consume_period(iter: &mut TokenStream) -> bool {
     false
 }
 
+/// 文字列が続くかどうか
+pub(crate) fn is_next_string(iter: &TokenStream) -> bool {
+    matches!(iter.peek().map(|x| x.kind), Some(TokenKind::String(_)))
+}
+
 pub(crate) fn consume_string(iter: &mut TokenStream) -> Option<String> {
     if let Some(x) = iter.peek() {
         if let TokenKind::String(string) = x.kind {
@@ -127,20 +196,22 @@ pub(crate) fn consume_char(iter: &mut TokenStream) -> Option<char> {
     None
 }
 
-pub(crate) fn consume_declarator(
+/// `(`が入れ子の宣言子の始まりかどうか
+/// `int (*f)(int)`の`(*f)`は入れ子の宣言子で、`int (int)`の`(int)`は関数の引数
+pub(crate) fn is_nested_declarator(
     iter: &mut TokenStream,
-    ctx: &mut Context,
-    type_kind: Rc<RefCell<TypeKind>>,
-    ident: &mut Ident,
-) -> Option<Rc<RefCell<TypeKind>>> {
+    ctx: &Context,
+    allow_ident: bool,
+) -> bool {
     let i_data = iter.save();
-    match crate::ast::ast::declarator(iter, ctx, type_kind, ident) {
-        Ok(type_kind) => Some(type_kind),
-        Err(_) => {
-            iter.restore(i_data);
-            None
-        }
-    }
+    let result = consume(iter, Operator::LParen)
+        && (is_next(iter, Operator::Mul)
+            || is_next(iter, Operator::LParen)
+            || (allow_ident
+                && matches!(iter.peek().map(|x| x.kind), Some(TokenKind::Ident(_)))
+                && !is_typename(iter, ctx)));
+    iter.restore(i_data);
+    result
 }
 
 pub(crate) fn consume_type_kind(iter: &mut TokenStream) -> Option<base_types::TypeKind> {
@@ -211,6 +282,30 @@ pub(crate) fn expect_num(iter: &mut TokenStream) -> Result<i64, Error> {
     ))
 }
 
+/// 末尾の`\0`を含まない文字列を返す
+pub(crate) fn expect_string(iter: &mut TokenStream) -> Result<String, Error> {
+    if let Some(x) = iter.peek() {
+        if let TokenKind::String(string) = x.kind {
+            iter.next();
+            return Ok(string);
+        } else {
+            return Err(Error::unexpected_token(
+                iter.filepath.clone(),
+                iter.input.clone(),
+                &x,
+                TokenKind::String(String::new()),
+            ));
+        }
+    }
+    Err(Error::eof(
+        iter.filepath.clone(),
+        iter.input.clone(),
+        iter.pos,
+        TokenKind::String(String::new()),
+        None,
+    ))
+}
+
 pub(crate) fn expect_semi(iter: &mut TokenStream) -> Result<(), Error> {
     if let Some(x) = iter.peek() {
         if x.kind == TokenKind::SemiColon {
@@ -389,7 +484,26 @@ pub(crate) fn check_g_var(
     // }
 }
 
-#[allow(unused)]
+/// 変数の実体を作れる型かどうかを確かめる
+pub(crate) fn expect_complete_object(
+    iter: &TokenStream,
+    ctx: &Context,
+    dec: &Declaration,
+) -> Result<(), Error> {
+    if dec.type_kind.is_incomplete() {
+        return Err(Error::incomplete_type(
+            iter.filepath.clone(),
+            iter.input.clone(),
+            iter.pos,
+            format!("variable {} has incomplete type", dec.ident.name),
+            suggest_incomplete_tag(ctx, &dec.type_kind),
+        ));
+    }
+    Ok(())
+}
+
+/// 同じ名前の関数がすでに宣言されていれば、型が矛盾しないか確かめる
+/// 登録すべき宣言を返す。プロトタイプのない宣言ではプロトタイプのある方を残す
 pub(crate) fn check_func_prototype(
     iter: &TokenStream,
     func_prototype_mp: &FuncPrototypeMp,
@@ -398,17 +512,252 @@ pub(crate) fn check_func_prototype(
     if func_prototype.ident == Ident::new_anonymous() {
         return Ok(func_prototype);
     }
-    match func_prototype_mp.get(&func_prototype.ident.name) {
-        Some(_) => {
-            return Err(Error::re_declare(
+    let prev = match func_prototype_mp.get(&func_prototype.ident.name) {
+        Some(prev) => prev,
+        None => return Ok(func_prototype),
+    };
+    if !TypeKind::is_compatible(&prev.func_type(), &func_prototype.func_type()) {
+        let pos = if func_prototype.span.is_dummy() {
+            iter.pos
+        } else {
+            func_prototype.span.pos()
+        };
+        let err = Error::conflicting_types(
+            iter.filepath.clone(),
+            iter.input.clone(),
+            pos,
+            func_prototype.ident,
+        );
+        return Err(with_previous(err, &prev.span, "declaration"));
+    }
+    if prev.has_prototype && !func_prototype.has_prototype {
+        return Ok(prev.as_ref().clone());
+    }
+    Ok(func_prototype)
+}
+
+/// `idx`番目の引数が仮引数に渡せるか確かめる
+/// 構造体の取り違えとvoidの値を渡すことはエラーにする
+/// ポインタと整数の混同は`check_assignment`で警告する
+pub(crate) fn check_func_arg(
+    iter: &TokenStream,
+    ctx: &mut Context,
+    func_prototype: &FuncPrototype,
+    idx: usize,
+    arg: &Node,
+) -> Result<(), Error> {
+    let invalid = |msg: String| {
+        Error::invalid_function_call(iter.filepath.clone(), iter.input.clone(), iter.pos, msg)
+    };
+    let arg_type = arg.get_type().unwrap_or_default();
+    if arg_type == TypeKind::Void {
+        return Err(invalid("invalid use of void expression".to_string()));
+    }
+    if !func_prototype.has_prototype {
+        return Ok(());
+    }
+    let param = match func_prototype.params.get(idx) {
+        Some(param) => param,
+        None if func_prototype.is_variadic => return Ok(()),
+        None => {
+            return Err(invalid(format!(
+                "too many arguments to {}",
+                describe_func(func_prototype)
+            )))
+        }
+    };
+    let is_struct = |type_kind: &TypeKind| matches!(type_kind, TypeKind::Struct(_));
+    if (is_struct(&arg_type) || is_struct(&param.type_kind))
+        && !TypeKind::is_compatible(&arg_type, &param.type_kind)
+    {
+        return Err(invalid(format!(
+            "incompatible type for argument {} of {}",
+            idx + 1,
+            describe_func(func_prototype)
+        )));
+    }
+    check_assignment(
+        iter,
+        ctx,
+        iter.pos,
+        &param.type_kind,
+        arg,
+        &format!("argument {} of {}", idx + 1, describe_func(func_prototype)),
+    )
+}
+
+/// 単純代入の規則で`rhs`を`lhs`の型に変換できるか確かめる
+/// 初期化, 引数, 戻り値にも使う。`context`は警告の中で変換した場所を表す
+/// 構造体の取り違えはエラー、ポインタと整数の混同や互換でないポインタは警告にする
+pub(crate) fn check_assignment(
+    iter: &TokenStream,
+    ctx: &mut Context,
+    pos: TokenPos,
+    lhs: &TypeKind,
+    rhs: &Node,
+    context: &str,
+) -> Result<(), Error> {
+    let rhs_type = match rhs.get_type() {
+        Ok(type_kind) => type_kind.decay(),
+        Err(_) => return Ok(()),
+    };
+    let lhs = lhs.decay();
+    let is_struct = |type_kind: &TypeKind| matches!(type_kind, TypeKind::Struct(_));
+    if is_struct(&lhs) || is_struct(&rhs_type) {
+        if TypeKind::is_compatible(&lhs, &rhs_type) {
+            return Ok(());
+        }
+        return Err(Error::invalid_assignment(
+            iter.filepath.clone(),
+            iter.input.clone(),
+            pos,
+            lhs,
+            rhs_type,
+        ));
+    }
+    match (&lhs, &rhs_type) {
+        // `void *`とはどのポインタとも暗黙に変換できる
+        (TypeKind::Ptr(_), TypeKind::Ptr(_))
+            if !lhs.is_void_ptr()
+                && !rhs_type.is_void_ptr()
+                && !TypeKind::is_compatible(&lhs, &rhs_type) =>
+        {
+            ctx.warn(Warn::incompatible_pointer_types(
                 iter.filepath.clone(),
                 iter.input.clone(),
-                func_prototype.ident,
-                iter.pos,
-                None,
-            ))
+                pos,
+                format!(
+                    "incompatible pointer types in {}. expected: {}, got: {}",
+                    context, lhs, rhs_type
+                ),
+            ));
         }
-        None => return Ok(func_prototype),
+        (TypeKind::Ptr(_), _) if rhs_type.is_integer() && !is_null_pointer_constant(iter, rhs) => {
+            ctx.warn(Warn::int_conversion(
+                iter.filepath.clone(),
+                iter.input.clone(),
+                pos,
+                format!("{} makes pointer from integer without a cast", context),
+            ));
+        }
+        (TypeKind::_Bool, TypeKind::Ptr(_)) => (),
+        (_, TypeKind::Ptr(_)) if lhs.is_integer() => {
+            ctx.warn(Warn::int_conversion(
+                iter.filepath.clone(),
+                iter.input.clone(),
+                pos,
+                format!("{} makes integer from pointer without a cast", context),
+            ));
+        }
+        _ => (),
+    }
+    Ok(())
+}
+
+/// 比較する両辺の型を確かめる
+/// 互換でないポインタどうしと、ポインタと空ポインタ定数でない整数の比較は警告にする
+pub(crate) fn check_comparison(
+    iter: &TokenStream,
+    ctx: &mut Context,
+    pos: TokenPos,
+    lhs: &Node,
+    rhs: &Node,
+) {
+    let (lhs_type, rhs_type) = match (lhs.get_type(), rhs.get_type()) {
+        (Ok(lhs_type), Ok(rhs_type)) => (lhs_type.decay(), rhs_type.decay()),
+        _ => return,
+    };
+    let int_compare = || {
+        Warn::pointer_integer_compare(
+            iter.filepath.clone(),
+            iter.input.clone(),
+            pos,
+            "comparison between pointer and integer",
+        )
+    };
+    match (&lhs_type, &rhs_type) {
+        (TypeKind::Ptr(_), TypeKind::Ptr(_))
+            if !lhs_type.is_void_ptr()
+                && !rhs_type.is_void_ptr()
+                && !TypeKind::is_compatible(&lhs_type, &rhs_type) =>
+        {
+            ctx.warn(Warn::compare_distinct_pointer_types(
+                iter.filepath.clone(),
+                iter.input.clone(),
+                pos,
+                format!(
+                    "comparison of distinct pointer types lacks a cast. lhs: {}, rhs: {}",
+                    lhs_type, rhs_type
+                ),
+            ));
+        }
+        (TypeKind::Ptr(_), _) if rhs_type.is_integer() && !is_null_pointer_constant(iter, rhs) => {
+            ctx.warn(int_compare());
+        }
+        (_, TypeKind::Ptr(_)) if lhs_type.is_integer() && !is_null_pointer_constant(iter, lhs) => {
+            ctx.warn(int_compare());
+        }
+        _ => (),
+    }
+}
+
+/// `case`の値の範囲が空でないか、同じ`switch`の前の`case`と重ならないか確かめる
+/// 本体はそのまま読み進められるので、エラーは記録するだけにする
+pub(crate) fn check_case(
+    iter: &TokenStream,
+    ctx: &mut Context,
+    pos: TokenPos,
+    low: i64,
+    high: i64,
+) {
+    if low > high {
+        ctx.diagnostics.push(Error::empty_case_range(
+            iter.filepath.clone(),
+            iter.input.clone(),
+            pos,
+        ));
+        return;
+    }
+    let duplicate = ctx
+        .cur_switch
+        .iter()
+        .flatten()
+        .find_map(|case| match case.kind {
+            NodeKind::Case(l, h) if l <= high && low <= h => Some(low.max(l)),
+            _ => None,
+        });
+    if let Some(val) = duplicate {
+        ctx.diagnostics.push(Error::duplicate_case(
+            iter.filepath.clone(),
+            iter.input.clone(),
+            pos,
+            val,
+        ));
+    }
+}
+
+/// 引数の数が足りているか確かめる
+pub(crate) fn check_func_arg_num(
+    iter: &TokenStream,
+    func_prototype: &FuncPrototype,
+    arg_num: usize,
+) -> Result<(), Error> {
+    if func_prototype.has_prototype && arg_num < func_prototype.param_num {
+        return Err(Error::invalid_function_call(
+            iter.filepath.clone(),
+            iter.input.clone(),
+            iter.pos,
+            format!("too few arguments to {}", describe_func(func_prototype)),
+        ));
+    }
+    Ok(())
+}
+
+fn describe_func(func_prototype: &FuncPrototype) -> String {
+    if func_prototype.ident.is_anonymous() {
+        "function call".to_string()
+    } else {
+        format!("function {}", func_prototype.ident.name)
     }
 }
 
@@ -432,10 +781,14 @@ pub(crate) fn is_typename(iter: &TokenStream, ctx: &Context) -> bool {
         match x.kind {
             TokenKind::TypeKind(_) => return true,
             TokenKind::KeyWord(KeyWord::Struct)
+            | TokenKind::KeyWord(KeyWord::Union)
             | TokenKind::KeyWord(KeyWord::Static)
             | TokenKind::KeyWord(KeyWord::Typedef)
             | TokenKind::KeyWord(KeyWord::Enum)
-            | TokenKind::KeyWord(KeyWord::Extern) => return true,
+            | TokenKind::KeyWord(KeyWord::Extern)
+            | TokenKind::KeyWord(KeyWord::Alignas)
+            | TokenKind::KeyWord(KeyWord::Typeof)
+            | TokenKind::KeyWord(KeyWord::TypeofUnqual) => return true,
             TokenKind::Ident(ident) => {
                 let ident = Rc::new(Ident::from(ident.clone()));
 
@@ -451,6 +804,77 @@ pub(crate) fn is_typename(iter: &TokenStream, ctx: &Context) -> bool {
     false
 }
 
+/// タイプミスと思われる名前に近い名前を`candidates`から探す
+/// 編集距離が長い方の名前のおよそ3分の1以下のものに限り、同じ距離なら辞書順で先のものを選ぶ
+/// 1文字の名前のように全部を書き換えれば届く名前は候補にしない
+pub(crate) fn suggest<'a>(
+    name: &str,
+    candidates: impl IntoIterator<Item = &'a str>,
+) -> Option<String> {
+    let len = name.chars().count();
+    let max = |candidate: &str| {
+        let longer = len.max(candidate.chars().count());
+        longer.div_ceil(3).min(len.saturating_sub(1))
+    };
+    candidates
+        .into_iter()
+        // コンパイラが作った`__va_area__`などは候補にしない
+        .filter(|candidate| *candidate != name && !candidate.starts_with("__"))
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .filter(|(distance, candidate)| *distance <= max(candidate))
+        .min()
+        .map(|(_, candidate)| candidate.to_string())
+}
+
+/// 前の宣言の場所を補足として加える。場所が分からなければそのまま返す
+pub(crate) fn with_previous(err: Error, span: &Span, what: &str) -> Error {
+    if span.is_dummy() {
+        return err;
+    }
+    err.with_note(Note::new(
+        Location::from_span(span),
+        format!("previous {} is here", what),
+    ))
+}
+
+/// 見えている変数, 列挙定数, 関数から似た名前を探す
+pub(crate) fn suggest_var(ctx: &Context, name: &str) -> Option<String> {
+    let funcs = ctx.g.func_prototype_mp.keys().map(|name| name.as_str());
+    suggest(name, ctx.s.visible_vars().into_iter().chain(funcs))
+}
+
+/// 構造体のメンバーから似た名前を探す
+pub(crate) fn suggest_member(_struct: &Struct, name: &str) -> Option<String> {
+    suggest(name, _struct.members.iter().map(|m| m.ident.name.as_str()))
+}
+
+/// 定義のない構造体, 共用体の型なら、見えているタグから似た名前を探す
+pub(crate) fn suggest_incomplete_tag(ctx: &Context, type_kind: &TypeKind) -> Option<String> {
+    match type_kind {
+        TypeKind::Array(_, base, true) => suggest_incomplete_tag(ctx, &base.borrow()),
+        TypeKind::Struct(_struct) if _struct.borrow().is_incomplete => {
+            let name = _struct.borrow().name()?.to_string();
+            suggest(&name, ctx.s.visible_tags())
+        }
+        _ => None,
+    }
+}
+
+/// 1文字の挿入, 削除, 置換を1回と数えた編集距離
+pub(crate) fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut cur = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let replace = prev[j] + if ca == *cb { 0 } else { 1 };
+            cur[j + 1] = replace.min(prev[j + 1] + 1).min(cur[j] + 1);
+        }
+        prev = cur;
+    }
+    prev[b.len()]
+}
+
 pub(crate) fn is_typedef_name(ident: Rc<Ident>, ctx: &Context) -> Option<Rc<Declaration>> {
     if let Some(tag) = ctx
         .s
@@ -577,6 +1001,89 @@ pub(crate) fn skip_excess_element2(iter: &mut TokenStream, ctx: &mut Context) ->
 
 pub(crate) fn skip_excess_elements(iter: &mut TokenStream, ctx: &mut Context) -> Result<(), Error> {
     expect_comma(iter)?;
-    Warn::excess_initializer(iter.filepath.clone(), iter.input.clone(), iter.pos);
+    ctx.warn(Warn::excess_initializer(
+        iter.filepath.clone(),
+        iter.input.clone(),
+        iter.pos,
+    ));
     skip_excess_element2(iter, ctx)
 }
+
+/// エラーのあとで解析を続けられる位置まで読み飛ばす
+/// `begin`から読み直し、括弧の対応を見ながら、外側の`;`か対応する`}`の次まで進める
+/// 囲んでいるブロックの`}`に当たったら、それは読まずに止まる
+pub(crate) fn synchronize(iter: &mut TokenStream, begin: usize) {
+    iter.idx = begin;
+    let mut braces = 0;
+    let mut parens = 0;
+    while let Some(token) = iter.peek() {
+        match token.kind {
+            TokenKind::Reserved(Operator::LParen) => parens += 1,
+            TokenKind::Reserved(Operator::RParen) if parens > 0 => parens -= 1,
+            // `{`の中は別の文なので、閉じていない`(`は忘れる
+            TokenKind::Block(Block::LParen) => {
+                braces += 1;
+                parens = 0;
+            }
+            TokenKind::Block(Block::RParen) => {
+                if braces == 0 {
+                    // 何も読めないと先に進まないので、余分な`}`は読み飛ばす
+                    if iter.idx == begin {
+                        iter.next();
+                    }
+                    return;
+                }
+                braces -= 1;
+                if braces == 0 {
+                    iter.next();
+                    consume_semi(iter);
+                    return;
+                }
+            }
+            TokenKind::SemiColon if braces == 0 && parens == 0 => {
+                iter.next();
+                return;
+            }
+            _ => (),
+        }
+        iter.next();
+    }
+}
+
+/// 文や宣言の解析で起きたエラーを記録して、`begin`から始まる文の次まで読み飛ばす
+/// 宣言に失敗した変数は、型の分からない変数として今のスコープに登録する
+/// エラーが多すぎるときとファイルの終わりに達したときは、続けられないのでエラーを返す
+pub(crate) fn recover(
+    iter: &mut TokenStream,
+    ctx: &mut Context,
+    begin: usize,
+    err: Error,
+) -> Result<(), Error> {
+    if err.is_fatal() {
+        return Err(err);
+    }
+    synchronize(iter, begin);
+    for ident in std::mem::take(&mut ctx.failed_decls) {
+        let dec = Declaration::new(TypeKind::_Invalid("invalid".to_string()), ident.clone());
+        ctx.push_scope(
+            ident,
+            Rc::new(Var::L(Rc::new(RefCell::new(Lvar::new_leaf(dec, 0))))),
+        );
+    }
+    ctx.recovered_errors += 1;
+    if err.is_silent() {
+        return Ok(());
+    }
+    if iter.peek().is_none() {
+        return Err(err);
+    }
+    ctx.diagnostics.push(err);
+    if ctx.error_limit != 0 && ctx.error_count() >= ctx.error_limit {
+        return Err(Error::too_many_errors(
+            iter.filepath.clone(),
+            iter.input.clone(),
+            iter.pos,
+        ));
+    }
+    Ok(())
+}
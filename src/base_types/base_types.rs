@@ -1,5 +1,6 @@
 use self::TypeKind::*;
-use crate::ast::{Declaration, Ident};
+use crate::ast::{Declaration, Ident, Node};
+use crate::token::Span;
 use std::collections::HashMap;
 use std::fmt;
 use std::{cell::RefCell, rc::Rc};
@@ -77,6 +78,12 @@ pub struct Struct {
     pub members: Rc<Vec<Rc<Member>>>,
     is_anonymous: bool,
     pub is_incomplete: bool,
+    /// union is struct whose members are all placed at offset 0
+    pub is_union: bool,
+    /// 8以上で、メンバのアライメントの最大値
+    pub align: u64,
+    /// 定義したときのタグのソース上の範囲
+    pub span: Span,
 }
 
 impl Struct {
@@ -86,6 +93,9 @@ impl Struct {
             members,
             is_anonymous: false,
             is_incomplete: false,
+            is_union: false,
+            align: 8,
+            span: Span::default(),
         }
     }
 
@@ -95,6 +105,28 @@ impl Struct {
             members,
             is_anonymous: true,
             is_incomplete: false,
+            is_union: false,
+            align: 8,
+            span: Span::default(),
+        }
+    }
+
+    /// `struct A`や`union B`のような型名
+    pub fn tag(&self) -> String {
+        let keyword = if self.is_union { "union" } else { "struct" };
+        if self.is_anonymous {
+            format!("{} <anonymous>", keyword)
+        } else {
+            format!("{} {}", keyword, self.ident.name)
+        }
+    }
+
+    /// タグの名前。無名の構造体ならNone
+    pub fn name(&self) -> Option<&str> {
+        if self.is_anonymous {
+            None
+        } else {
+            Some(&self.ident.name)
         }
     }
 
@@ -111,10 +143,17 @@ impl Struct {
         if self.members.len() < 1 {
             return 0;
         }
-        let last = self.members.last().unwrap();
-        let mut size = last.offset + last.type_kind.size();
-        size += (8 - size % 8) % 8;
-        size
+        let size = if self.is_union {
+            self.members
+                .iter()
+                .map(|m| m.type_kind.size())
+                .max()
+                .unwrap_or(0)
+        } else {
+            let last = self.members.last().unwrap();
+            last.offset + last.type_kind.size()
+        };
+        align_to(size, self.align)
     }
 }
 
@@ -153,8 +192,15 @@ pub enum TypeKind {
     Long,
     Ptr(Rc<RefCell<TypeKind>>),
     Array(u64, Rc<RefCell<TypeKind>>, bool), // bool is whether initialized or not
+    /// 可変長配列。Nodeは実行時にバイト数を計算する式
+    /// 変数自体は確保した領域へのポインタとして8バイトを持つ
+    Vla(Rc<RefCell<TypeKind>>, Rc<Node>),
     Struct(Rc<RefCell<Struct>>),
     Enum(Rc<Enum>),
+    /// 関数型。戻り値の型、引数、可変長引数かどうか
+    /// `int f()`のようにプロトタイプのない関数は引数がNone
+    /// 式の中ではほとんどの場合、関数へのポインタとして扱う
+    Func(Rc<RefCell<TypeKind>>, Option<Rc<Vec<Declaration>>>, bool),
 
     PlaceHolder, // virtual type
     /// this is virtual type for `get_deref_type`
@@ -169,16 +215,50 @@ pub enum TypeKind {
 //     }
 // }
 
+fn func_params(params: &Option<Rc<Vec<Declaration>>>, is_variadic: bool) -> String {
+    let mut params: Vec<_> = params
+        .iter()
+        .flat_map(|params| params.iter())
+        .map(|p| p.type_kind.to_string())
+        .collect();
+    if is_variadic {
+        params.push("...".to_string());
+    }
+    params.join(", ")
+}
+
 impl fmt::Display for TypeKind {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             Void | _Bool | Char | Short | Int | Long => write!(f, "{}", self.as_str()),
-            Ptr(x) => {
-                let (count, type_kind) = x.borrow().count_deref();
+            Ptr(_) => {
+                let (count, type_kind) = self.count_deref();
                 let ptr = format!("{:*<width$}", "*", width = count + 1);
-                write!(f, "{} {}", type_kind.as_str(), ptr)
+                // 配列や関数へのポインタは`int (*)[3]`のように書く
+                match type_kind {
+                    Array(size, base, _) => write!(f, "{} ({})[{}]", base.borrow(), ptr, size),
+                    Vla(base, _) => write!(f, "{} ({})[*]", base.borrow(), ptr),
+                    Func(ret, params, is_variadic) => write!(
+                        f,
+                        "{} ({})({})",
+                        ret.borrow(),
+                        ptr,
+                        func_params(&params, is_variadic)
+                    ),
+                    Struct(s) => write!(f, "{} {}", s.borrow().tag(), ptr),
+                    type_kind => write!(f, "{} {}", type_kind.as_str(), ptr),
+                }
             }
             Array(size, type_kind, _) => write!(f, "{} [{}]", type_kind.borrow(), size),
+            Vla(type_kind, _) => write!(f, "{} [*]", type_kind.borrow()),
+            Func(ret, params, is_variadic) => {
+                write!(
+                    f,
+                    "{} ({})",
+                    ret.borrow(),
+                    func_params(params, *is_variadic)
+                )
+            }
             Struct(s) => {
                 for member in &*s.borrow().members {
                     writeln!(f, "{}", member)?
@@ -221,7 +301,10 @@ impl TypeKind {
             Long => "long",
             Ptr(_) => "Ptr",
             Array(_, _, _) => "Array",
+            Vla(_, _) => "Vla",
             Struct(_) => "struct",
+            Enum(_) => "enum",
+            Func(_, _, _) => "function",
             _ => unreachable!(),
         }
     }
@@ -247,9 +330,13 @@ impl TypeKind {
             Long => 8,
             Ptr(_) => 8,
             Array(size, type_kind, _) => size * type_kind.borrow().size(),
+            Vla(_, _) => 8,
             Struct(s) => s.borrow().get_size(),
             Enum(_) => 4,
-            _ => unreachable!(),
+            // 不完全型のsizeofはパースの段階でエラーにしているので、ここでは
+            // gccの拡張に合わせてvoidと関数を1として扱う
+            Void | Func(_, _, _) => 1,
+            PlaceHolder | _Deref(_) | _Invalid(_) => 0,
         }
     }
 
@@ -262,9 +349,22 @@ impl TypeKind {
             Long => 8,
             Ptr(_) => 8,
             Array(_, type_kind, _) => type_kind.borrow().align(),
-            Struct(_) => 8,
+            Vla(_, _) => 8,
+            Struct(s) => s.borrow().align,
             Enum(_) => 4,
-            _ => unreachable!(),
+            Void | Func(_, _, _) | PlaceHolder | _Deref(_) | _Invalid(_) => 1,
+        }
+    }
+
+    /// 大きさが決まっていない型かどうか
+    /// `void`, 定義される前の構造体, 要素数のない配列(`int []`)
+    pub fn is_incomplete(&self) -> bool {
+        match self {
+            Void => true,
+            Array(_, _, false) => true,
+            Array(_, base, true) => base.borrow().is_incomplete(),
+            Struct(s) => s.borrow().is_incomplete,
+            _ => false,
         }
     }
 
@@ -275,6 +375,57 @@ impl TypeKind {
         }
     }
 
+    /// 整数型かどうか。`_Bool`と列挙型も含む
+    pub fn is_integer(&self) -> bool {
+        match self {
+            _Bool | Char | Short | Int | Long | Enum(_) => true,
+            _ => false,
+        }
+    }
+
+    /// ポインタとして扱える型かどうか。配列はポインタに変換される
+    pub fn is_pointer(&self) -> bool {
+        match self {
+            Ptr(_) | Array(_, _, _) | Vla(_, _) => true,
+            _ => false,
+        }
+    }
+
+    /// 値として使ったときの型。配列は先頭要素へのポインタ、関数は関数へのポインタになる
+    pub fn decay(&self) -> TypeKind {
+        match self {
+            Array(_, base, _) | Vla(base, _) => Ptr(base.clone()),
+            Func(_, _, _) => Ptr(Rc::new(RefCell::new(self.clone()))),
+            _ => self.clone(),
+        }
+    }
+
+    /// `void *`かどうか
+    pub fn is_void_ptr(&self) -> bool {
+        match self {
+            Ptr(base) => *base.borrow() == Void,
+            _ => false,
+        }
+    }
+
+    /// 整数拡張。intより小さい整数型はintになる
+    pub fn promote(&self) -> TypeKind {
+        match self {
+            _Bool | Char | Short | Enum(_) => Int,
+            other => other.clone(),
+        }
+    }
+
+    /// 通常の算術変換で決まる共通の型
+    /// 符号なし整数型はないので、どちらかがlongならlong、それ以外はint
+    pub fn common_type(lhs: &TypeKind, rhs: &TypeKind) -> TypeKind {
+        if lhs.promote() == Long || rhs.promote() == Long {
+            Long
+        } else {
+            Int
+        }
+    }
+
     pub fn count_deref(&self) -> (usize, TypeKind) {
         let mut count = 0;
 
@@ -297,7 +448,7 @@ impl TypeKind {
                 Rc::new(RefCell::new(self.clone())),
             ))),
             Ptr(type_kind) => type_kind.clone(),
-            Array(_, type_kind, _) => type_kind.clone(),
+            Array(_, type_kind, _) | Vla(type_kind, _) => type_kind.clone(),
             Struct(_) => Rc::new(RefCell::new(TypeKind::_Deref(Rc::new(RefCell::new(
                 self.clone(),
             ))))),
@@ -305,6 +456,8 @@ impl TypeKind {
                 self.clone(),
             ))))),
             _Deref(type_kind) => type_kind.clone(),
+            // `*f`は関数そのもの
+            Func(_, _, _) => Rc::new(RefCell::new(self.clone())),
             _Invalid(msg) => Rc::new(RefCell::new(_Invalid(msg.clone()))),
             PlaceHolder => Rc::new(RefCell::new(PlaceHolder)),
         }
@@ -324,13 +477,13 @@ impl TypeKind {
     pub fn eight_size(&self) -> u64 {
         match self {
             Char | Short | Int | Long => 8,
-            Ptr(_) => 8,
+            Ptr(_) | Vla(_, _) => 8,
             Array(_size, type_kind, _) => {
                 let mut size = _size * type_kind.borrow().size();
                 size += (8 - size % 8) % 8; // sizeを8の倍数にする
                 size
             }
-            Struct(_) => self.size(), // structは8の倍数にのともとパディングしてる
+            Struct(_) => self.size(), // structはアライメント(8以上)の倍数にパディングしてる
             _ => unreachable!(),
         }
     }
@@ -363,30 +516,46 @@ impl TypeKind {
         TypeKind::Ptr(base)
     }
 
-    /// `int [] == int *`
-    /// array and pointer is same
-    /// todo
-    /// this can not compare `int **` and `int *a[]`
-    pub fn partial_comp(lhs: &TypeKind, rhs: &TypeKind) -> bool {
-        let a = if let Array(_, b_type, _) = lhs {
-            Ptr(b_type.clone())
-        } else {
-            lhs.clone()
-        };
-
-        let b = if let Array(_, b_type, _) = rhs {
-            Ptr(b_type.clone())
-        } else {
-            rhs.clone()
-        };
-
-        // if lhs and rhs is Int or Char,
-        // return true
-        if a.is_num_type() && b.is_num_type() {
-            return true;
+    /// `_Generic`や関数の再宣言、ポインタの代入で使う型の互換性
+    /// 数値型どうしは同じ型のときだけ互換
+    /// ポインタは指す先の型が、配列は要素の型と要素数が互換なときだけ互換
+    /// 構造体は同じ定義から作った型だけが互換
+    pub fn is_compatible(lhs: &TypeKind, rhs: &TypeKind) -> bool {
+        if lhs.is_num_type() || rhs.is_num_type() {
+            return lhs == rhs;
+        }
+        match (lhs, rhs) {
+            // 引数の名前は型に含めない
+            // 引数の配列や関数はポインタとして比べる
+            // プロトタイプのない関数型は引数によらず互換
+            (Func(lret, lparams, lvariadic), Func(rret, rparams, rvariadic)) => {
+                if !TypeKind::is_compatible(&lret.borrow(), &rret.borrow()) {
+                    return false;
+                }
+                match (lparams, rparams) {
+                    (Some(lparams), Some(rparams)) => {
+                        lvariadic == rvariadic
+                            && lparams.len() == rparams.len()
+                            && lparams.iter().zip(rparams.iter()).all(|(l, r)| {
+                                TypeKind::is_compatible(&l.type_kind.decay(), &r.type_kind.decay())
+                            })
+                    }
+                    _ => true,
+                }
+            }
+            (Ptr(l), Ptr(r)) => TypeKind::is_compatible(&l.borrow(), &r.borrow()),
+            // 内側のスコープで定義し直した同じタグの構造体は別の型
+            (Struct(l), Struct(r)) => Rc::ptr_eq(l, r),
+            // 要素数の分からない配列はどの要素数とも互換
+            (Array(lsize, l, lsized), Array(rsize, r, rsized)) => {
+                (!lsized || !rsized || lsize == rsize)
+                    && TypeKind::is_compatible(&l.borrow(), &r.borrow())
+            }
+            (Array(_, l, _) | Vla(l, _), Array(_, r, _) | Vla(r, _)) => {
+                TypeKind::is_compatible(&l.borrow(), &r.borrow())
+            }
+            _ => lhs == rhs,
         }
-
-        a == b
     }
 }
 
@@ -455,4 +624,69 @@ pub mod tests {
             assert_eq!(align_to(*offset, type_kind.align()), *expected);
         }
     }
+
+    fn make_struct(name: &str) -> TypeKind {
+        let member = Member::new(Rc::new(Int), 0, Ident::new("x"));
+        Struct(Rc::new(RefCell::new(Struct::new(
+            Rc::new(Ident::new(name)),
+            Rc::new(vec![Rc::new(member)]),
+        ))))
+    }
+
+    #[test]
+    fn test_is_compatible() {
+        let ptr = |type_kind: TypeKind| Ptr(Rc::new(RefCell::new(type_kind)));
+        let a = make_struct("A");
+        let tests = [
+            (Int, Int, true),
+            (Int, Long, false),
+            (ptr(Int), ptr(Int), true),
+            (ptr(Int), ptr(Char), false),
+            (ptr(ptr(Int)), ptr(ptr(Int)), true),
+            (ptr(ptr(Int)), ptr(ptr(Char)), false),
+            (ptr(Void), ptr(Int), false),
+            (ptr(Int), make_array(3, Int, true), false),
+            (make_array(3, Int, true), make_array(3, Int, true), true),
+            (make_array(3, Int, true), make_array(4, Int, true), false),
+            (make_array(0, Int, false), make_array(4, Int, true), true),
+            (
+                ptr(make_array(3, Int, true)),
+                ptr(make_array(3, Int, true)),
+                true,
+            ),
+            (
+                ptr(make_array(3, Int, true)),
+                ptr(make_array(2, Int, true)),
+                false,
+            ),
+            (a.clone(), a.clone(), true),
+            (ptr(a.clone()), ptr(a.clone()), true),
+            // メンバが同じでも、別に定義した構造体は互換でない
+            (a.clone(), make_struct("A"), false),
+            (ptr(a), ptr(make_struct("A")), false),
+        ];
+        for (lhs, rhs, expected) in &tests {
+            assert_eq!(
+                TypeKind::is_compatible(lhs, rhs),
+                *expected,
+                "{} {}",
+                lhs,
+                rhs
+            );
+        }
+    }
+    #[test]
+    fn test_display_pointer() {
+        let ptr = |type_kind: TypeKind| Ptr(Rc::new(RefCell::new(type_kind)));
+        let tests = [
+            (ptr(Int), "int *"),
+            (ptr(ptr(Char)), "char **"),
+            (ptr(make_struct("A")), "struct A *"),
+            (ptr(ptr(make_struct("B"))), "struct B **"),
+            (ptr(make_array(3, Int, true)), "int (*)[3]"),
+        ];
+        for (type_kind, expected) in &tests {
+            assert_eq!(type_kind.to_string(), *expected);
+        }
+    }
 }
@@ -3,5 +3,5 @@ pub mod ast;
 pub mod base_types;
 pub mod error;
 pub mod preprocessor;
+pub mod render;
 pub mod token;
-pub mod util;
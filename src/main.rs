@@ -1,37 +1,115 @@
 extern crate rs9cc;
 
 use rs9cc::asm::code_gen;
-use rs9cc::ast::program;
+use rs9cc::ast::{program_with_context, Context, Error};
 use rs9cc::token;
 use std::env;
+use std::io::IsTerminal;
+use std::process;
 use std::rc::Rc;
 
 fn main() {
-    let filepath = env::args().nth(1).unwrap();
+    // -ferror-limit=N で報告するエラーの数の上限を変える。0なら上限なし
+    // -W... と -w で警告の設定を変える
+    // -fdiagnostics-color[=always|never|auto] で診断に色を付けるか決める。既定では端末のときだけ付ける
+    let mut ctx = Context::new();
+    let mut filepath = None;
+    let mut color = None;
+    for arg in env::args().skip(1) {
+        if let Some(when) = color_option(&arg) {
+            color = when;
+        } else if let Some(limit) = arg.strip_prefix("-ferror-limit=") {
+            match limit.parse() {
+                Ok(limit) => ctx.error_limit = limit,
+                Err(_) => fail(format!("invalid integral value '{}' in '{}'", limit, arg)),
+            }
+        } else if arg.starts_with("-W") || arg == "-w" {
+            if let Err(err) = ctx.diag.borrow_mut().parse_option(&arg) {
+                fail(err);
+            }
+        } else {
+            filepath = Some(arg);
+        }
+    }
+    rs9cc::render::set_color(color.unwrap_or_else(|| std::io::stderr().is_terminal()));
+    let filepath = filepath.unwrap_or_else(|| fail("no input files"));
     // token生成
     let mut token_stream = match token::tokenize_file(Rc::new(filepath)) {
         Ok(tokens) => tokens,
         Err(err) => {
             eprintln!("{}", err);
-            panic!();
+            process::exit(1);
         }
     };
 
     // ast生成
     // let node = expr(&mut iter).unwrap();
-    let program = match program(&mut token_stream) {
-        Ok(x) => x,
-        Err(err) => {
-            eprintln!("{}", err);
-            panic!()
+    let program = match program_with_context(&mut token_stream, ctx) {
+        Ok(mut program) => {
+            let warnings = std::mem::take(&mut program.ctx.diagnostics);
+            if !warnings.is_empty() {
+                report(&warnings);
+            }
+            program
+        }
+        Err(diagnostics) => {
+            report(&diagnostics);
+            process::exit(1);
         }
     };
 
     match code_gen(program) {
         Err(err) => {
             eprintln!("{}", err);
-            panic!()
+            process::exit(1);
         }
         Ok(asm) => print!("{}", asm),
     }
 }
+
+/// オプションの誤りを報告して終了する
+fn fail(msg: impl std::fmt::Display) -> ! {
+    eprintln!("error: {}", msg);
+    process::exit(1)
+}
+
+/// 色の設定のオプションなら`Some`を返す。中身が`None`なら自動で決める
+fn color_option(arg: &str) -> Option<Option<bool>> {
+    match arg {
+        "-fcolor-diagnostics" | "-fdiagnostics-color" | "-fdiagnostics-color=always" => {
+            Some(Some(true))
+        }
+        "-fno-color-diagnostics" | "-fno-diagnostics-color" | "-fdiagnostics-color=never" => {
+            Some(Some(false))
+        }
+        "-fdiagnostics-color=auto" => Some(None),
+        _ => None,
+    }
+}
+
+/// エラーと警告を出した順に表示して、数をまとめる
+fn report(diagnostics: &[Error]) {
+    for diagnostic in diagnostics {
+        eprintln!("{}", diagnostic);
+    }
+    let warnings = diagnostics.iter().filter(|d| d.is_warning()).count();
+    let errors = diagnostics
+        .iter()
+        .filter(|d| !d.is_warning() && !d.is_fatal())
+        .count();
+    eprintln!("{} generated.", summary(warnings, errors));
+}
+
+/// "2 warnings and 1 error" のように数をまとめる
+fn summary(warnings: usize, errors: usize) -> String {
+    let plural = |n: usize, word: &str| format!("{} {}{}", n, word, if n == 1 { "" } else { "s" });
+    match (warnings, errors) {
+        (0, _) => plural(errors, "error"),
+        (_, 0) => plural(warnings, "warning"),
+        _ => format!(
+            "{} and {}",
+            plural(warnings, "warning"),
+            plural(errors, "error")
+        ),
+    }
+}
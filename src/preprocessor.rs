@@ -2,4 +2,4 @@ pub mod error;
 pub mod preprocessor;
 
 pub use error::Error;
-pub use preprocessor::preprocessor;
+pub use preprocessor::{preprocessor, Pragma, PragmaKind};
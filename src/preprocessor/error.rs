@@ -5,7 +5,7 @@ use std::rc::Rc;
 
 #[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Debug)]
 pub enum ErrorKind {
-    InvalidPreprocessor(Token),
+    InvalidPreprocessor(Box<Token>),
     Todo,
 }
 
@@ -24,7 +24,7 @@ impl Error {
             pos: tk.pos,
             input: tk.input.clone(),
             filepath: tk.filepath.clone(),
-            kind: InvalidPreprocessor(tk),
+            kind: InvalidPreprocessor(Box::new(tk)),
             msg: None,
         }
     }
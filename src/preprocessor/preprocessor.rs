@@ -1,15 +1,35 @@
 use path::{Path, PathBuf};
 
 use super::error::Error;
-use crate::token::{self, Token, TokenKind};
+use crate::token::{self, Token, TokenKind, TokenStream};
 use std::{iter::Peekable, path, rc::Rc, todo, vec::IntoIter};
 
-pub fn preprocessor(tokens: Vec<Token>) -> Result<Vec<Token>, Error> {
+/// `#pragma GCC diagnostic`の指示
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Debug)]
+pub enum PragmaKind {
+    Push,
+    Pop,
+    /// 警告の名前(`-W`を除いたもの)を持つ
+    Ignored(String),
+    Warning(String),
+    Error(String),
+}
+
+/// ファイルのどこに`#pragma`が書かれていたか
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Debug)]
+pub struct Pragma {
+    pub filepath: Rc<String>,
+    pub bytes: usize,
+    pub kind: PragmaKind,
+}
+
+pub fn preprocessor(tokens: Vec<Token>) -> Result<(Vec<Token>, Vec<Pragma>), Error> {
     preprocessor_impl(tokens)
 }
 
-fn preprocessor_impl(tokens: Vec<Token>) -> Result<Vec<Token>, Error> {
+fn preprocessor_impl(tokens: Vec<Token>) -> Result<(Vec<Token>, Vec<Pragma>), Error> {
     let mut result = Vec::with_capacity(tokens.len());
+    let mut pragmas = Vec::new();
     let mut iter = tokens.into_iter().peekable();
     while let Some(token) = iter.next() {
         if !is_hash(&token) {
@@ -41,14 +61,67 @@ fn preprocessor_impl(tokens: Vec<Token>) -> Result<Vec<Token>, Error> {
                         let base_path = Path::new(&base_path);
 
                         iter.next();
-                        result.append(&mut include(&mut iter, base_path)?)
+                        let mut stream = include(&mut iter, base_path)?;
+                        result.append(&mut stream.tokens);
+                        pragmas.append(&mut stream.pragmas);
+                    }
+                    "pragma" => {
+                        let token = token.clone();
+                        iter.next();
+                        if let Some(kind) = pragma(&mut iter, &token)? {
+                            pragmas.push(Pragma {
+                                filepath: token.filepath.clone(),
+                                bytes: token.pos.bytes,
+                                kind,
+                            });
+                        }
                     }
                     _ => return Err(Error::invalid_preprocessor(token.clone())),
                 }
             }
         }
     }
-    Ok(result)
+    Ok((result, pragmas))
+}
+
+/// 行末までの`#pragma`を読む
+/// `GCC diagnostic`以外は知らないので読み飛ばす
+fn pragma(
+    iter: &mut Peekable<IntoIter<Token>>,
+    token: &Token,
+) -> Result<Option<PragmaKind>, Error> {
+    let mut line = Vec::new();
+    while let Some(x) = iter.peek() {
+        if x.is_bol || x.kind == TokenKind::EOF {
+            break;
+        }
+        line.push(iter.next().unwrap());
+    }
+    let words = line
+        .iter()
+        .map(|token| token.kind.as_string())
+        .collect::<Vec<_>>();
+    let words = words.iter().map(|word| word.as_str()).collect::<Vec<_>>();
+    let option = |token: Option<&Token>| match token.map(|token| &token.kind) {
+        Some(TokenKind::String(option)) => match option.strip_prefix("-W") {
+            Some(name) => Ok(name.to_string()),
+            None => Err(Error::invalid_preprocessor(token.unwrap().clone())),
+        },
+        _ => Err(Error::invalid_preprocessor(
+            token.unwrap_or_else(|| line.last().unwrap()).clone(),
+        )),
+    };
+    match words.as_slice() {
+        ["GCC", "diagnostic", "push"] => Ok(Some(PragmaKind::Push)),
+        ["GCC", "diagnostic", "pop"] => Ok(Some(PragmaKind::Pop)),
+        ["GCC", "diagnostic", "ignored", ..] => Ok(Some(PragmaKind::Ignored(option(line.get(3))?))),
+        ["GCC", "diagnostic", "warning", ..] => Ok(Some(PragmaKind::Warning(option(line.get(3))?))),
+        ["GCC", "diagnostic", "error", ..] => Ok(Some(PragmaKind::Error(option(line.get(3))?))),
+        ["GCC", "diagnostic", ..] => Err(Error::invalid_preprocessor(
+            line.get(2).unwrap_or(token).clone(),
+        )),
+        _ => Ok(None),
+    }
 }
 
 fn is_hash(token: &Token) -> bool {
@@ -59,7 +132,7 @@ fn is_hash(token: &Token) -> bool {
     }
 }
 
-fn include(iter: &mut Peekable<IntoIter<Token>>, base_path: &Path) -> Result<Vec<Token>, Error> {
+fn include(iter: &mut Peekable<IntoIter<Token>>, base_path: &Path) -> Result<TokenStream, Error> {
     let base_dirs = vec![
         base_path,
         Path::new("/usr/lib/gcc/x86_64-linux-gnu/8/include"),
@@ -86,7 +159,7 @@ fn include(iter: &mut Peekable<IntoIter<Token>>, base_path: &Path) -> Result<Vec
                 Some(path) => {
                     let path = Rc::new(path.to_str().unwrap().to_string());
                     return match token::tokenize_file(path.clone()) {
-                        Ok(stream) => Ok(stream.tokens),
+                        Ok(stream) => Ok(stream),
                         Err(e) => Err(Error::todo(e.pos, e.input, path)),
                     };
                 }
@@ -0,0 +1,336 @@
+use crate::token::Span;
+use std::fmt::{self, Write};
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// タブを何文字分の幅で表示するか
+const TAB_WIDTH: usize = 8;
+
+static COLOR: AtomicBool = AtomicBool::new(false);
+
+/// 診断を色付きで表示するかどうかを決める。既定では色を付けない
+pub fn set_color(enabled: bool) {
+    COLOR.store(enabled, Ordering::Relaxed);
+}
+
+fn color_enabled() -> bool {
+    COLOR.load(Ordering::Relaxed)
+}
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+const RED: &str = "\x1b[1;31m";
+const MAGENTA: &str = "\x1b[1;35m";
+const CYAN: &str = "\x1b[1;36m";
+const GREEN: &str = "\x1b[1;32m";
+
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug)]
+pub enum Level {
+    Error,
+    Warning,
+    Note,
+}
+
+impl Level {
+    fn name(&self) -> &'static str {
+        match self {
+            Level::Error => "error",
+            Level::Warning => "warning",
+            Level::Note => "note",
+        }
+    }
+
+    fn color(&self) -> &'static str {
+        match self {
+            Level::Error => RED,
+            Level::Warning => MAGENTA,
+            Level::Note => CYAN,
+        }
+    }
+}
+
+/// ソース上の位置。`end`がなければ`begin`から始まるトークンに下線を引く
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Debug)]
+pub struct Location {
+    pub filepath: Rc<String>,
+    pub input: Rc<String>,
+    pub begin: usize,
+    pub end: Option<usize>,
+}
+
+impl Location {
+    pub fn new(filepath: Rc<String>, input: Rc<String>, begin: usize) -> Self {
+        Self {
+            filepath,
+            input,
+            begin,
+            end: None,
+        }
+    }
+
+    pub fn from_span(span: &Span) -> Self {
+        Self {
+            filepath: span.filepath.clone(),
+            input: span.input.clone(),
+            begin: span.begin,
+            end: Some(span.end),
+        }
+    }
+}
+
+/// 前の宣言の場所など、診断に添える補足
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Debug)]
+pub struct Note {
+    pub loc: Location,
+    pub msg: String,
+}
+
+impl Note {
+    pub fn new(loc: Location, msg: impl Into<String>) -> Self {
+        Self {
+            loc,
+            msg: msg.into(),
+        }
+    }
+}
+
+/// 修正の提案。`begin..end`のバイトを`text`に置き換える。挿入ならbeginとendは同じ
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Debug)]
+pub struct FixIt {
+    pub begin: usize,
+    pub end: usize,
+    pub text: String,
+}
+
+impl FixIt {
+    pub fn replace(begin: usize, end: usize, text: impl Into<String>) -> Self {
+        Self {
+            begin,
+            end,
+            text: text.into(),
+        }
+    }
+
+    pub fn insert(pos: usize, text: impl Into<String>) -> Self {
+        Self::replace(pos, pos, text)
+    }
+}
+
+/// 表示する診断。`file:line:col: level: msg`の後に、ソースの行と下線, 修正の提案, 補足が続く
+#[derive(Clone, Debug)]
+pub struct Diagnostic<'a> {
+    pub level: Level,
+    pub loc: &'a Location,
+    pub msg: &'a str,
+    pub notes: &'a [Note],
+    pub fixits: &'a [FixIt],
+}
+
+impl<'a> Diagnostic<'a> {
+    pub fn new(level: Level, loc: &'a Location, msg: &'a str) -> Self {
+        Self {
+            level,
+            loc,
+            msg,
+            notes: &[],
+            fixits: &[],
+        }
+    }
+
+    pub fn render(&self, color: bool) -> String {
+        let mut buf = String::new();
+        // Stringへの書き込みは失敗しない
+        write_one(&mut buf, self.level, self.loc, self.msg, self.fixits, color).unwrap();
+        for note in self.notes {
+            buf.push('\n');
+            write_one(&mut buf, Level::Note, &note.loc, &note.msg, &[], color).unwrap();
+        }
+        buf
+    }
+}
+
+impl<'a> fmt::Display for Diagnostic<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.render(color_enabled()))
+    }
+}
+
+fn write_one(
+    buf: &mut String,
+    level: Level,
+    loc: &Location,
+    msg: &str,
+    fixits: &[FixIt],
+    color: bool,
+) -> fmt::Result {
+    let paint = |code: &'static str| if color { code } else { "" };
+    let input = loc.input.as_str();
+    let begin = floor_char_boundary(input, loc.begin.min(input.len()));
+    let line_begin = input[..begin].rfind('\n').map_or(0, |i| i + 1);
+    let line_end = input[begin..].find('\n').map_or(input.len(), |i| begin + i);
+    let line = &input[line_begin..line_end];
+    let line_num = input[..line_begin].matches('\n').count() + 1;
+    let col = begin - line_begin + 1;
+    let end = loc
+        .end
+        .unwrap_or_else(|| token_end(input, begin))
+        .clamp(begin, line_end);
+    let end = floor_char_boundary(input, end);
+
+    writeln!(
+        buf,
+        "{}{}:{}:{}: {}{}: {}{}{}{}",
+        paint(BOLD),
+        loc.filepath,
+        line_num,
+        col,
+        paint(level.color()),
+        level.name(),
+        paint(RESET),
+        paint(BOLD),
+        msg,
+        paint(RESET),
+    )?;
+    writeln!(buf, "{}", expand_tabs(line))?;
+
+    // `^`の後に範囲の残りを`~`で示す
+    let caret_col = display_width(&line[..begin - line_begin]);
+    let width = display_width(&line[..end - line_begin]).saturating_sub(caret_col);
+    write!(
+        buf,
+        "{}{}^{}{}",
+        " ".repeat(caret_col),
+        paint(GREEN),
+        "~".repeat(width.saturating_sub(1)),
+        paint(RESET),
+    )?;
+
+    // 同じ行の修正の提案を下に並べる
+    let mut fixit_line = String::new();
+    for fixit in fixits {
+        if fixit.begin < line_begin || fixit.begin > line_end {
+            continue;
+        }
+        let col = display_width(&line[..fixit.begin - line_begin]);
+        let len = fixit_line.chars().count();
+        if col < len {
+            continue;
+        }
+        fixit_line.push_str(&" ".repeat(col - len));
+        fixit_line.push_str(&fixit.text);
+    }
+    if !fixit_line.is_empty() {
+        write!(buf, "\n{}{}{}", paint(GREEN), fixit_line, paint(RESET))?;
+    }
+    Ok(())
+}
+
+/// `begin`から始まるトークンの終わり。識別子, 数, 文字列, 文字はまとめて、それ以外は1文字とする
+fn token_end(input: &str, begin: usize) -> usize {
+    let rest = &input[begin..];
+    let mut chars = rest.char_indices();
+    let len = match chars.next() {
+        None => 0,
+        Some((_, c)) if c.is_alphanumeric() || c == '_' => rest
+            .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+            .unwrap_or(rest.len()),
+        Some((_, quote)) if quote == '"' || quote == '\'' => {
+            let mut escaped = false;
+            let mut len = rest.len();
+            for (i, c) in chars {
+                match c {
+                    '\n' => {
+                        len = i;
+                        break;
+                    }
+                    _ if escaped => escaped = false,
+                    '\\' => escaped = true,
+                    _ if c == quote => {
+                        len = i + 1;
+                        break;
+                    }
+                    _ => (),
+                }
+            }
+            len
+        }
+        Some((_, c)) => c.len_utf8(),
+    };
+    begin + len
+}
+
+/// タブを展開したときの表示幅
+fn display_width(text: &str) -> usize {
+    text.chars().fold(0, |width, c| match c {
+        '\t' => (width / TAB_WIDTH + 1) * TAB_WIDTH,
+        _ => width + 1,
+    })
+}
+
+fn expand_tabs(line: &str) -> String {
+    let mut result = String::new();
+    for c in line.chars() {
+        match c {
+            '\t' => {
+                let width = display_width(&result);
+                result.push_str(&" ".repeat((width / TAB_WIDTH + 1) * TAB_WIDTH - width));
+            }
+            _ => result.push(c),
+        }
+    }
+    result
+}
+
+fn floor_char_boundary(input: &str, mut pos: usize) -> usize {
+    while !input.is_char_boundary(pos) {
+        pos -= 1;
+    }
+    pos
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn location(input: &str, begin: usize, end: Option<usize>) -> Location {
+        Location {
+            filepath: Rc::new("a.c".to_string()),
+            input: Rc::new(input.to_string()),
+            begin,
+            end,
+        }
+    }
+
+    #[test]
+    fn test_render() {
+        let input = "int main() {\n    return foo;\n}\n";
+        let loc = location(input, 24, None);
+        let actual = Diagnostic::new(Level::Error, &loc, "undefined").render(false);
+        assert_eq!(
+            "a.c:2:12: error: undefined\n    return foo;\n           ^~~",
+            actual
+        );
+
+        // タブは8文字の幅に揃える
+        let input = "int f() {\n\treturn \"abc\" + x;\n}";
+        let loc = location(input, 18, None);
+        let actual = Diagnostic::new(Level::Warning, &loc, "w").render(false);
+        assert_eq!(
+            "a.c:2:9: warning: w\n        return \"abc\" + x;\n               ^~~~~",
+            actual
+        );
+
+        let input = "int x;\nint x;\n";
+        let notes = [Note::new(location(input, 4, Some(5)), "previous")];
+        let fixits = [FixIt::replace(11, 12, "y")];
+        let loc = location(input, 11, Some(12));
+        let mut diagnostic = Diagnostic::new(Level::Error, &loc, "redeclared");
+        diagnostic.notes = &notes;
+        diagnostic.fixits = &fixits;
+        assert_eq!(
+            "a.c:2:5: error: redeclared\nint x;\n    ^\n    y\na.c:1:5: note: previous\nint x;\n    ^",
+            diagnostic.render(false)
+        );
+        assert!(diagnostic.render(true).contains("\x1b[1;31merror: "));
+    }
+}
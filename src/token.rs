@@ -4,6 +4,6 @@ pub mod token;
 pub use error::Error;
 pub(crate) use token::TokenPos;
 pub use token::{
-    tokenize, tokenize_file, Block, Ident, KeyWord, Operator, Token, TokenIter, TokenKind,
+    tokenize, tokenize_file, Block, Ident, KeyWord, Operator, Span, Token, TokenIter, TokenKind,
     TokenStream,
 };
@@ -1,6 +1,6 @@
 use self::ErrorKind::*;
+use crate::render::{Diagnostic, Level, Location};
 use crate::token::TokenPos;
-use crate::util;
 use std::error::Error as StdError;
 use std::fmt;
 use std::rc::Rc;
@@ -44,11 +44,11 @@ impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match &self.kind {
             Invalid(x) | Preprocessor(x) => {
-                let err = util::err_format(self.input.clone(), self.filepath.clone(), self.pos, x)?;
-                writeln!(f, "{}", err)
+                let loc = Location::new(self.filepath.clone(), self.input.clone(), self.pos.bytes);
+                write!(f, "{}", Diagnostic::new(Level::Error, &loc, x.trim_end()))
             }
 
-            Eof => writeln!(f, "reached EOF"),
+            Eof => write!(f, "error: reached EOF"),
         }
     }
 }
@@ -1,7 +1,9 @@
 use super::error::Error;
 use crate::base_types::TypeKind;
 use crate::preprocessor;
+use std::cmp::{min, Ordering};
 use std::fs;
+use std::hash::{Hash, Hasher};
 use std::ops::{Add, AddAssign};
 use std::rc::Rc;
 use std::str::FromStr;
@@ -229,6 +231,7 @@ pub enum KeyWord {
     While,
     For,
     Struct,
+    Union,
     Enum,
     Typedef,
     Static,
@@ -240,6 +243,12 @@ pub enum KeyWord {
     Default,
     Extern,
     Do,
+    Alignof,
+    Alignas,
+    StaticAssert,
+    Typeof,
+    TypeofUnqual,
+    Generic,
 }
 
 impl KeyWord {
@@ -252,6 +261,7 @@ impl KeyWord {
             While => "while",
             For => "for",
             Struct => "struct",
+            Union => "union",
             Enum => "enum",
             Typedef => "typedef",
             Static => "static",
@@ -263,9 +273,32 @@ impl KeyWord {
             Default => "default",
             Extern => "extern",
             Do => "do",
+            Alignof => "alignof",
+            Alignas => "alignas",
+            StaticAssert => "static_assert",
+            Typeof => "typeof",
+            TypeofUnqual => "typeof_unqual",
+            Generic => "_Generic",
         }
     }
 
+    /// C11での綴り(`_Alignof`など)とGNU拡張の綴り(`__typeof__`など)
+    fn alias(&self) -> Option<&'static str> {
+        use KeyWord::*;
+        match self {
+            Alignof => Some("_Alignof"),
+            Alignas => Some("_Alignas"),
+            StaticAssert => Some("_Static_assert"),
+            Typeof => Some("__typeof__"),
+            TypeofUnqual => Some("__typeof_unqual__"),
+            _ => None,
+        }
+    }
+
+    fn starts_with_alias(&self, s: &str) -> bool {
+        self.alias().is_some_and(|alias| s.starts_with(alias))
+    }
+
     fn from_starts(s: &str) -> Result<KeyWord, ()> {
         use self::KeyWord::*;
         match s {
@@ -275,8 +308,13 @@ impl KeyWord {
             x if x.starts_with(While.as_str()) => Ok(While),
             x if x.starts_with(For.as_str()) => Ok(For),
             x if x.starts_with(Struct.as_str()) => Ok(Struct),
+            x if x.starts_with(Union.as_str()) => Ok(Union),
             x if x.starts_with(Enum.as_str()) => Ok(Enum),
             x if x.starts_with(Typedef.as_str()) => Ok(Typedef),
+            // `static`より先に調べる
+            x if x.starts_with(StaticAssert.as_str()) || StaticAssert.starts_with_alias(x) => {
+                Ok(StaticAssert)
+            }
             x if x.starts_with(Static.as_str()) => Ok(Static),
             x if x.starts_with(Break.as_str()) => Ok(Break),
             x if x.starts_with(Continue.as_str()) => Ok(Continue),
@@ -286,6 +324,14 @@ impl KeyWord {
             x if x.starts_with(Default.as_str()) => Ok(Default),
             x if x.starts_with(Extern.as_str()) => Ok(Extern),
             x if x.starts_with(Do.as_str()) => Ok(Do),
+            x if x.starts_with(Alignof.as_str()) || Alignof.starts_with_alias(x) => Ok(Alignof),
+            x if x.starts_with(Alignas.as_str()) || Alignas.starts_with_alias(x) => Ok(Alignas),
+            // `typeof`より先に調べる
+            x if x.starts_with(TypeofUnqual.as_str()) || TypeofUnqual.starts_with_alias(x) => {
+                Ok(TypeofUnqual)
+            }
+            x if x.starts_with(Typeof.as_str()) || Typeof.starts_with_alias(x) => Ok(Typeof),
+            x if x.starts_with(Generic.as_str()) => Ok(Generic),
             _ => Err(()),
         }
     }
@@ -302,6 +348,7 @@ impl FromStr for KeyWord {
             x if x == While.as_str() => Ok(While),
             x if x == For.as_str() => Ok(For),
             x if x == Struct.as_str() => Ok(Struct),
+            x if x == Union.as_str() => Ok(Union),
             x if x == Enum.as_str() => Ok(Enum),
             x if x == Typedef.as_str() => Ok(Typedef),
             x if x == Static.as_str() => Ok(Static),
@@ -313,6 +360,12 @@ impl FromStr for KeyWord {
             x if x == Default.as_str() => Ok(Default),
             x if x == Extern.as_str() => Ok(Extern),
             x if x == Do.as_str() => Ok(Do),
+            x if x == Alignof.as_str() || Some(x) == Alignof.alias() => Ok(Alignof),
+            x if x == Alignas.as_str() || Some(x) == Alignas.alias() => Ok(Alignas),
+            x if x == StaticAssert.as_str() || Some(x) == StaticAssert.alias() => Ok(StaticAssert),
+            x if x == Typeof.as_str() || Some(x) == Typeof.alias() => Ok(Typeof),
+            x if x == TypeofUnqual.as_str() || Some(x) == TypeofUnqual.alias() => Ok(TypeofUnqual),
+            x if x == Generic.as_str() => Ok(Generic),
             _ => Err(()),
         }
     }
@@ -394,6 +447,11 @@ pub struct Token {
     pub pos: TokenPos,
     pub prev_pos: TokenPos,
     pub is_bol: bool,
+    /// トークンのバイト数
+    pub len: usize,
+    /// トークンの先頭の行と列。どちらも1から数える
+    pub line: usize,
+    pub col: usize,
 }
 
 impl Token {
@@ -412,6 +470,9 @@ impl Token {
             pos,
             prev_pos,
             is_bol,
+            len: 0,
+            line: 0,
+            col: 0,
         }
     }
 }
@@ -438,6 +499,94 @@ impl TokenPos {
     }
 }
 
+/// ソース上の範囲
+/// 構文木のノードや宣言から元のソースを指すために使う
+/// 位置だけが違う構文木を同じものとして扱えるように、比較やハッシュには含めない
+#[derive(Clone, Debug, Default)]
+pub struct Span {
+    pub filepath: Rc<String>,
+    pub input: Rc<String>,
+    /// 先頭と末尾のバイト位置。末尾は含まない
+    pub begin: usize,
+    pub end: usize,
+    /// 先頭の行と列。どちらも1から数え、0は位置がないことを表す
+    pub line: usize,
+    pub col: usize,
+}
+
+impl Span {
+    /// `first`から`last`までのトークンを含む範囲
+    pub fn new(first: &Token, last: &Token) -> Self {
+        let last = if first.filepath == last.filepath && first.pos.bytes <= last.pos.bytes {
+            last
+        } else {
+            first
+        };
+        Self {
+            filepath: first.filepath.clone(),
+            input: first.input.clone(),
+            begin: first.pos.bytes,
+            end: last.pos.bytes + last.len,
+            line: first.line,
+            col: first.col,
+        }
+    }
+
+    pub fn is_dummy(&self) -> bool {
+        self.line == 0
+    }
+
+    /// 両方を含む範囲
+    /// 片方に位置がないときや、別のファイルを指しているときは位置のある方を返す
+    pub fn merge(&self, other: &Span) -> Span {
+        if other.is_dummy() || self.filepath != other.filepath {
+            return self.clone();
+        }
+        if self.is_dummy() {
+            return other.clone();
+        }
+        let first = if self.begin <= other.begin {
+            self
+        } else {
+            other
+        };
+        Span {
+            begin: first.begin,
+            end: self.end.max(other.end),
+            ..first.clone()
+        }
+    }
+
+    /// エラーの表示に使う先頭の位置
+    pub fn pos(&self) -> TokenPos {
+        TokenPos::new(0, self.begin)
+    }
+}
+
+impl PartialEq for Span {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for Span {}
+
+impl PartialOrd for Span {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Span {
+    fn cmp(&self, _: &Self) -> Ordering {
+        Ordering::Equal
+    }
+}
+
+impl Hash for Span {
+    fn hash<H: Hasher>(&self, _: &mut H) {}
+}
+
 impl Add for TokenPos {
     type Output = Self;
 
@@ -462,6 +611,8 @@ pub struct TokenStream {
     pub pos: TokenPos,
     pub idx: usize,
     pub tokens: Vec<Token>,
+    /// 警告の設定を変える`#pragma`。書かれていた順に並ぶ
+    pub pragmas: Vec<preprocessor::Pragma>,
 }
 
 impl TokenStream {
@@ -472,6 +623,7 @@ impl TokenStream {
             pos: TokenPos::new(0, 0),
             idx: 0,
             tokens,
+            pragmas: Vec::new(),
         }
     }
 
@@ -515,6 +667,22 @@ impl TokenStream {
         self.idx = data.0;
         self.pos = data.1;
     }
+
+    /// `begin`番目のトークンから最後に読んだトークンまでの範囲
+    /// まだ何も読んでいなければ`begin`番目のトークンだけを指す
+    pub fn span_from(&self, begin: usize) -> Span {
+        let first = match self.tokens.get(begin) {
+            Some(first) => first,
+            None => return Span::default(),
+        };
+        let end = min(self.idx, self.tokens.len());
+        let last = if end > begin {
+            &self.tokens[end - 1]
+        } else {
+            first
+        };
+        Span::new(first, last)
+    }
 }
 
 /// token iterator
@@ -524,6 +692,10 @@ pub struct TokenIter {
     pub pos: TokenPos,
     pub prev_pos: TokenPos,
     pub filepath: Rc<String>,
+    // 行と列を数え終わった位置と、その時点の行と行頭の位置
+    scanned: usize,
+    line: usize,
+    line_begin: usize,
 }
 
 pub fn tokenize(input: Rc<String>, filepath: Rc<String>) -> Result<TokenStream, Error> {
@@ -537,9 +709,11 @@ pub fn tokenize(input: Rc<String>, filepath: Rc<String>) -> Result<TokenStream,
         vec.push(x);
     }
 
-    vec = preprocessor::preprocessor(vec)?;
+    let (vec, pragmas) = preprocessor::preprocessor(vec)?;
 
-    Ok(TokenStream::new(input, filepath, vec))
+    let mut stream = TokenStream::new(input, filepath, vec);
+    stream.pragmas = pragmas;
+    Ok(stream)
 }
 
 pub fn tokenize_file(filepath: Rc<String>) -> Result<TokenStream, Error> {
@@ -559,6 +733,9 @@ impl TokenIter {
             pos: TokenPos { tk: 0, bytes: 0 },
             prev_pos: TokenPos { tk: 0, bytes: 0 },
             filepath,
+            scanned: 0,
+            line: 1,
+            line_begin: 0,
         }
     }
 
@@ -576,109 +753,99 @@ impl TokenIter {
             return Ok(None);
         }
 
-        if let Some((mut tk, pos)) = self.is_op(s) {
-            self.prev_pos = self.pos;
-            self.pos += pos;
-            tk.is_bol = is_bol;
-            return Ok(Some(tk));
+        if let Some((tk, pos)) = self.is_op(s) {
+            return Ok(Some(self.advance(tk, pos, is_bol)));
         }
 
-        if let Some((mut tk, pos)) = self.is_keyword(s) {
-            self.prev_pos = self.pos;
-            self.pos += pos;
-            tk.is_bol = is_bol;
-            tk.is_bol = is_bol;
-            return Ok(Some(tk));
+        if let Some((tk, pos)) = self.is_keyword(s) {
+            return Ok(Some(self.advance(tk, pos, is_bol)));
         }
 
-        if let Some((mut tk, pos)) = self.is_num(s) {
-            self.prev_pos = self.pos;
-            self.pos += pos;
-            tk.is_bol = is_bol;
-            return Ok(Some(tk));
+        if let Some((tk, pos)) = self.is_num(s) {
+            return Ok(Some(self.advance(tk, pos, is_bol)));
         }
 
-        if let Some((mut tk, pos)) = self.is_semi(s) {
-            self.prev_pos = self.pos;
-            self.pos += pos;
-            tk.is_bol = is_bol;
-            return Ok(Some(tk));
+        if let Some((tk, pos)) = self.is_semi(s) {
+            return Ok(Some(self.advance(tk, pos, is_bol)));
         }
 
-        if let Some((mut tk, pos)) = self.is_colon(s) {
-            self.prev_pos = self.pos;
-            self.pos += pos;
-            tk.is_bol = is_bol;
-            return Ok(Some(tk));
+        if let Some((tk, pos)) = self.is_colon(s) {
+            return Ok(Some(self.advance(tk, pos, is_bol)));
         }
 
-        if let Some((mut tk, pos)) = self.is_block_paren(s) {
-            self.prev_pos = self.pos;
-            self.pos += pos;
-            tk.is_bol = is_bol;
-            return Ok(Some(tk));
+        if let Some((tk, pos)) = self.is_block_paren(s) {
+            return Ok(Some(self.advance(tk, pos, is_bol)));
         }
 
-        if let Some((mut tk, pos)) = self.is_comma(s) {
-            self.prev_pos = self.pos;
-            self.pos += pos;
-            tk.is_bol = is_bol;
-            return Ok(Some(tk));
+        if let Some((tk, pos)) = self.is_comma(s) {
+            return Ok(Some(self.advance(tk, pos, is_bol)));
         }
 
-        if let Some((mut tk, pos)) = self.is_period(s) {
-            self.prev_pos = self.pos;
-            self.pos += pos;
-            tk.is_bol = is_bol;
-            return Ok(Some(tk));
+        if let Some((tk, pos)) = self.is_period(s) {
+            return Ok(Some(self.advance(tk, pos, is_bol)));
         }
 
-        if let Some((mut tk, pos)) = self.is_question(s) {
-            self.prev_pos = self.pos;
-            self.pos += pos;
-            tk.is_bol = is_bol;
-            return Ok(Some(tk));
+        if let Some((tk, pos)) = self.is_question(s) {
+            return Ok(Some(self.advance(tk, pos, is_bol)));
         }
 
-        if let Some((mut tk, pos)) = self.is_hashmark(s) {
-            self.prev_pos = self.pos;
-            self.pos += pos;
-            tk.is_bol = is_bol;
-            return Ok(Some(tk));
+        if let Some((tk, pos)) = self.is_hashmark(s) {
+            return Ok(Some(self.advance(tk, pos, is_bol)));
         }
 
-        if let Some((mut tk, pos)) = self.is_string(s)? {
-            self.prev_pos = self.pos;
-            self.pos += pos;
-            tk.is_bol = is_bol;
-            return Ok(Some(tk));
+        if let Some((tk, pos)) = self.is_string(s)? {
+            return Ok(Some(self.advance(tk, pos, is_bol)));
         }
 
-        if let Some((mut tk, pos)) = self.is_char(s)? {
-            self.prev_pos = self.pos;
-            self.pos += pos;
-            tk.is_bol = is_bol;
-            return Ok(Some(tk));
+        if let Some((tk, pos)) = self.is_char(s)? {
+            return Ok(Some(self.advance(tk, pos, is_bol)));
         }
 
-        if let Some((mut tk, pos)) = self.is_base_type(s) {
-            self.prev_pos = self.pos;
-            self.pos += pos;
-            tk.is_bol = is_bol;
-            return Ok(Some(tk));
+        if let Some((tk, pos)) = self.is_base_type(s) {
+            return Ok(Some(self.advance(tk, pos, is_bol)));
         }
 
         // これ最後の方がいい
         // 最後にしないと予約後が変数として扱われちゃう
-        if let Some((mut tk, pos)) = self.is_ident(s) {
-            self.prev_pos = self.pos;
-            self.pos += pos;
-            tk.is_bol = is_bol;
-            return Ok(Some(tk));
+        if let Some((tk, pos)) = self.is_ident(s) {
+            return Ok(Some(self.advance(tk, pos, is_bol)));
         }
         Err(self.error_at("トークナイズできません"))
     }
 
+    /// 読んだトークンの分だけ進めて、トークンに長さと行, 列を記録する
+    fn advance(&mut self, mut tk: Token, pos: TokenPos, is_bol: bool) -> Token {
+        self.prev_pos = self.pos;
+        self.pos += pos;
+        tk.is_bol = is_bol;
+        tk.len = pos.bytes;
+        let (line, col) = self.line_col(tk.pos.bytes);
+        tk.line = line;
+        tk.col = col;
+        tk
+    }
+
+    /// `bytes`の位置の行と列
+    /// 前回数えた位置から続けて数える
+    fn line_col(&mut self, bytes: usize) -> (usize, usize) {
+        if bytes < self.scanned {
+            self.scanned = 0;
+            self.line = 1;
+            self.line_begin = 0;
+        }
+        for (i, c) in self.input.as_bytes()[self.scanned..bytes]
+            .iter()
+            .enumerate()
+        {
+            if *c == b'\n' {
+                self.line += 1;
+                self.line_begin = self.scanned + i + 1;
+            }
+        }
+        self.scanned = bytes;
+        (self.line, bytes - self.line_begin + 1)
+    }
+
     // /// std::iter::Peekable.peek()に似てるけど、posを元に戻す
     // pub fn peek(&mut self) -> Option<Token> {
     //     let cur_pos = self.pos;
@@ -722,7 +889,11 @@ impl TokenIter {
 
     fn is_keyword(&self, s: &str) -> Option<(Token, TokenPos)> {
         if let Ok(keyword) = KeyWord::from_starts(s) {
-            let len = keyword.as_str().len();
+            let len = if keyword.starts_with_alias(s) {
+                keyword.alias().unwrap().len()
+            } else {
+                keyword.as_str().len()
+            };
             if !is_alnum(s.chars().nth(len).unwrap_or_else(|| ' ')) {
                 let kind = TokenKind::KeyWord(keyword);
                 return Some((self.new_token(kind), TokenPos::new_bytes(len)));
@@ -1073,7 +1244,7 @@ mod tests {
         }
         assert_eq!(None, iter.next());
 
-        let input = "return; returnx return1 return 1 for while if else force whilet ifelse elseif  struct . typedef enum static break continue goto : switch case default ? extern do #";
+        let input = "return; returnx return1 return 1 for while if else force whilet ifelse elseif  struct . union typedef enum static break continue goto : switch case default ? extern do _Alignof alignas _Alignasx static_assert _Static_assert staticx typeof __typeof__ typeof_unqual __typeof_unqual__ typeofx _Generic _Genericx #";
 
         let expected = vec![
             KeyWord(Return),
@@ -1092,6 +1263,7 @@ mod tests {
             TokenKind::Ident(Ident::new("elseif")),
             KeyWord(Struct),
             TokenKind::Period,
+            KeyWord(Union),
             KeyWord(Typedef),
             KeyWord(Enum),
             KeyWord(Static),
@@ -1105,6 +1277,19 @@ mod tests {
             TokenKind::Question,
             KeyWord(Extern),
             KeyWord(Do),
+            KeyWord(Alignof),
+            KeyWord(Alignas),
+            TokenKind::Ident(Ident::new("_Alignasx")),
+            KeyWord(StaticAssert),
+            KeyWord(StaticAssert),
+            TokenKind::Ident(Ident::new("staticx")),
+            KeyWord(Typeof),
+            KeyWord(Typeof),
+            KeyWord(TypeofUnqual),
+            KeyWord(TypeofUnqual),
+            TokenKind::Ident(Ident::new("typeofx")),
+            KeyWord(Generic),
+            TokenKind::Ident(Ident::new("_Genericx")),
             TokenKind::HashMark,
         ];
         let mut iter = tokenize(Rc::new(input.to_string()), Rc::new(String::new())).unwrap();
@@ -1227,7 +1412,20 @@ mod tests {
     #[test]
     fn test_keyword_from_starts() {
         use self::KeyWord::*;
-        let tests = [("return", Ok(Return)), ("noreturn", Err(()))];
+        let tests = [
+            ("return", Ok(Return)),
+            ("noreturn", Err(())),
+            ("alignof", Ok(Alignof)),
+            ("_Alignof", Ok(Alignof)),
+            ("_Alignas(8)", Ok(Alignas)),
+            ("static", Ok(Static)),
+            ("static_assert(", Ok(StaticAssert)),
+            ("_Static_assert(", Ok(StaticAssert)),
+            ("typeof(", Ok(Typeof)),
+            ("__typeof__(", Ok(Typeof)),
+            ("typeof_unqual(", Ok(TypeofUnqual)),
+            ("_Generic(", Ok(Generic)),
+        ];
 
         for (s, expected) in &tests {
             assert_eq!(expected, &KeyWord::from_starts(s));